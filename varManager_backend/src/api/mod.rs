@@ -1,31 +1,57 @@
 use axum::{
     body::Body,
-    extract::{Path, Query, State},
-    http::{header, StatusCode},
-    response::{IntoResponse, Response},
+    extract::{ConnectInfo, FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
 use sqlx::{QueryBuilder, Row, SqlitePool};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    io::SeekFrom,
+    net::SocketAddr,
     path::{Component, Path as StdPath, PathBuf},
-    sync::atomic::Ordering,
     sync::Arc,
 };
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio::sync::Semaphore;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+use tokio_util::io::ReaderStream;
 use walkdir::WalkDir;
 
 use crate::jobs::job_channel::{
-    min_job_log_level, JobLogsResponse, JobResultResponse, JobState, JobStatus, JobView,
+    min_job_log_level, JobFailedItemsResponse, JobLogsResponse, JobResultResponse, JobState,
+    JobStatus, JobView,
+};
+use crate::jobs::job_locks::{job_resources, JobResource};
+use crate::jobs::job_log_store::JobLogFilter;
+use crate::jobs::kinds::{validate_job_args, JOB_KINDS};
+use crate::jobs::outcome::failed_items;
+use crate::jobs::retry::{plan_retry, RetryPlan};
+use crate::infra::download_manager::{
+    parse_window_time, DownloadAction, DownloadEnqueueItem, DownloadHistoryEntry,
+    DownloadListResponse, EnqueueReport,
 };
-use crate::infra::download_manager::{DownloadAction, DownloadEnqueueItem, DownloadListResponse};
+use crate::infra::package_sources::{SourceStatus, VAR_URL_PREFIX};
 use crate::app::{app_root, data_dir, AppState, APP_VERSION, Config};
+use crate::domain::library_stats::{self, LibraryStats, TrendPeriod};
+use crate::domain::providers::{find_providers, gather_refs, summarize, ProvidersReport};
 use crate::infra::db;
+use crate::infra::db_maintenance::{self, DbBackupInfo};
+use crate::infra::hub_auth::{self, HubAuthStatus, HubAuthUpdate};
+use crate::infra::paths::{resolve_profile, resolve_var_file_path, vam_profile, PREVIEW_DIR};
 use crate::services::image_cache::{
     CacheStats, ImageCacheError, ImageSource, ResolvedImageSource,
 };
+use crate::services::lan_cache;
 use crate::{jobs, scenes};
 
 #[derive(Deserialize)]
@@ -33,6 +59,9 @@ pub(crate) struct StartJobRequest {
     kind: String,
     #[serde(default)]
     args: Option<Value>,
+    /// VaM profile to run against; overrides the header and query selector.
+    #[serde(default)]
+    profile: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -41,9 +70,23 @@ pub(crate) struct StartJobResponse {
     status: JobStatus,
 }
 
+#[derive(Serialize)]
+pub(crate) struct JobKindView {
+    kind: &'static str,
+    args_required: bool,
+    resources: &'static [JobResource],
+    args_schema: schemars::Schema,
+    result_schema: schemars::Schema,
+}
+
 #[derive(Deserialize)]
 pub(crate) struct JobLogsQuery {
     from: Option<usize>,
+    /// Minimum level to return, on top of the configured log level.
+    level: Option<String>,
+    /// Case-insensitive substring the message must contain.
+    contains: Option<String>,
+    limit: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -98,6 +141,49 @@ pub(crate) struct ScenesQuery {
     order: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub(crate) struct SearchQuery {
+    q: String,
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct EntriesQuery {
+    var_name: Option<String>,
+    /// Exact entry path, or a folder prefix when it ends with `/`.
+    path: Option<String>,
+    /// File name in any folder, e.g. `Foo.cs`.
+    file_name: Option<String>,
+    content_type: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DbBackupRequest {
+    #[serde(default)]
+    label: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DbRestoreRequest {
+    file: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct DbBackupsResponse {
+    dir: String,
+    items: Vec<DbBackupInfo>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ProvidersRequest {
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    scene: Option<Value>,
+}
+
 #[derive(Deserialize)]
 pub(crate) struct DependentsQuery {
     name: String,
@@ -215,9 +301,19 @@ pub(crate) struct UpdateConfigRequest {
     log_level: Option<String>,
     job_concurrency: Option<usize>,
     varspath: Option<String>,
+    library_roots: Option<Vec<crate::app::LibraryRoot>>,
     vampath: Option<String>,
     vam_exec: Option<String>,
+    vam_profiles: Option<Vec<crate::app::VamProfile>>,
+    active_profile: Option<String>,
     downloader_save_path: Option<String>,
+    download_speed_limit_kbps: Option<u64>,
+    download_per_download_speed_limit_kbps: Option<u64>,
+    download_windows: Option<Vec<crate::app::DownloadWindow>>,
+    download_sources: Option<Vec<crate::app::PackageSource>>,
+    download_resume_on_startup: Option<bool>,
+    lan_cache: Option<crate::app::LanCacheConfig>,
+    read_only_library: Option<bool>,
     image_cache: Option<crate::app::ImageCacheConfig>,
     proxy_mode: Option<crate::app::ProxyMode>,
     proxy: Option<crate::app::ProxyConfig>,
@@ -278,6 +374,48 @@ impl IntoResponse for ApiError {
     }
 }
 
+/// Header naming the VaM profile a request works on.
+const PROFILE_HEADER: &str = "x-vam-profile";
+
+#[derive(Deserialize)]
+struct ProfileQuery {
+    profile: Option<String>,
+}
+
+/// `AppState` scoped to the VaM profile picked by the `X-VaM-Profile`
+/// header or the `profile` query parameter; the active profile otherwise.
+pub(crate) struct ProfileState(AppState);
+
+impl FromRequestParts<AppState> for ProfileState {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        let selector = parts
+            .headers
+            .get(PROFILE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| {
+                Query::<ProfileQuery>::try_from_uri(&parts.uri)
+                    .ok()
+                    .and_then(|Query(query)| query.profile)
+            });
+        scoped_state(state, selector).map(ProfileState)
+    }
+}
+
+fn scoped_state(state: &AppState, selector: Option<String>) -> ApiResult<AppState> {
+    let selector = selector
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    let Some(selector) = selector else {
+        return Ok(state.clone());
+    };
+    let cfg = read_config(state).map_err(internal_error)?;
+    let profile = resolve_profile(&cfg, Some(&selector)).map_err(ApiError::bad_request)?;
+    Ok(state.with_profile(Some(profile.name)))
+}
+
 type ApiResult<T> = Result<T, ApiError>;
 
 fn internal_error<E: ToString>(err: E) -> ApiError {
@@ -413,6 +551,71 @@ pub(crate) struct StatsResponse {
     missing_deps: u64,
 }
 
+#[derive(Deserialize)]
+pub(crate) struct LibraryStatsQuery {
+    /// Length of the largest / most depended / orphan lists.
+    top: Option<usize>,
+    /// `day`, `month` (default) or `year`.
+    period: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SearchVarHit {
+    var_name: String,
+    creator_name: String,
+    package_name: String,
+    /// bm25 rank of the name, description and tags match, lower is better.
+    /// Set when `name_match` is.
+    score: Option<f64>,
+    /// The name, description or tags matched, not only archive entries.
+    name_match: bool,
+    /// bm25 rank of the best matching entry. Ranks from the two indexes are
+    /// not comparable, so name matches are ordered by `score` and come
+    /// first, then entry-only matches by this.
+    entry_score: Option<f64>,
+    entry_matches: usize,
+    /// The best matching entry paths.
+    entries: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SearchItemHit {
+    var_name: String,
+    path: String,
+    /// Scene record type (`scenes`, `looks`, ...) when the entry is one.
+    atom_type: Option<String>,
+    score: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SearchResponse {
+    query: String,
+    vars: Vec<SearchVarHit>,
+    items: Vec<SearchItemHit>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct EntryListItem {
+    var_name: String,
+    path: String,
+    size: Option<i64>,
+    compressed_size: Option<i64>,
+    crc: Option<i64>,
+    content_type: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct EntriesListResponse {
+    items: Vec<EntryListItem>,
+    page: u32,
+    per_page: u32,
+    total: u64,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "snake_case")]
 pub(crate) struct DependencyStatus {
@@ -457,29 +660,130 @@ pub async fn shutdown(State(state): State<AppState>) -> impl IntoResponse {
 }
 
 pub async fn start_job(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
     Json(req): Json<StartJobRequest>,
 ) -> ApiResult<Json<StartJobResponse>> {
     let kind = req.kind.trim();
     if kind.is_empty() {
         return Err(ApiError::bad_request("kind is required"));
     }
+    let state = match req.profile {
+        Some(profile) => scoped_state(&state, Some(profile))?,
+        None => state,
+    };
+    validate_job_args(kind, req.args.as_ref()).map_err(ApiError::bad_request)?;
+    jobs::check_library_writable(&state, kind).map_err(ApiError::conflict)?;
 
-    let id = state.job_counter.fetch_add(1, Ordering::SeqCst);
-    let job = JobState::new(id, kind.to_string());
-    {
-        let mut jobs = state.jobs.write().await;
-        jobs.insert(id, job);
-    }
+    let id = jobs::queue_job(&state, kind, req.args, None).await;
+    Ok(Json(StartJobResponse {
+        id,
+        status: JobStatus::Queued,
+    }))
+}
 
-    jobs::spawn_job(state.clone(), id, kind.to_string(), req.args);
+/// `POST /db/backup`: queue a `db_backup` job.
+pub async fn backup_db(
+    State(state): State<AppState>,
+    body: Option<Json<DbBackupRequest>>,
+) -> ApiResult<Json<StartJobResponse>> {
+    let label = body.and_then(|Json(req)| req.label);
+    start_db_job(&state, "db_backup", Some(json!({ "label": label }))).await
+}
 
+/// `POST /db/restore`: queue a `db_restore` job for a file from
+/// `GET /db/backups`.
+pub async fn restore_db(
+    State(state): State<AppState>,
+    Json(req): Json<DbRestoreRequest>,
+) -> ApiResult<Json<StartJobResponse>> {
+    db_maintenance::resolve_backup(&req.file).map_err(bad_request_error)?;
+    start_db_job(&state, "db_restore", Some(json!({ "file": req.file }))).await
+}
+
+/// `POST /db/check`: queue a `db_check` job.
+pub async fn check_db(State(state): State<AppState>) -> ApiResult<Json<StartJobResponse>> {
+    start_db_job(&state, "db_check", None).await
+}
+
+pub async fn list_db_backups() -> ApiResult<Json<DbBackupsResponse>> {
+    let items = tokio::task::spawn_blocking(db_maintenance::list_backups)
+        .await
+        .map_err(internal_error)?
+        .map_err(internal_error)?;
+    Ok(Json(DbBackupsResponse {
+        dir: db_maintenance::backup_dir().to_string_lossy().to_string(),
+        items,
+    }))
+}
+
+async fn start_db_job(
+    state: &AppState,
+    kind: &str,
+    args: Option<Value>,
+) -> ApiResult<Json<StartJobResponse>> {
+    validate_job_args(kind, args.as_ref()).map_err(ApiError::bad_request)?;
+    let id = jobs::queue_job(state, kind, args, None).await;
     Ok(Json(StartJobResponse {
         id,
         status: JobStatus::Queued,
     }))
 }
 
+pub async fn retry_job(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> ApiResult<Json<StartJobResponse>> {
+    let (plan, profile) = {
+        let jobs = state.jobs.read().await;
+        let job = jobs
+            .get(&id)
+            .ok_or_else(|| ApiError::not_found("job not found"))?;
+        let plan = match (&job.status, &job.result) {
+            (JobStatus::Queued | JobStatus::Running, _) => {
+                return Err(ApiError::conflict("job has not finished"));
+            }
+            // Failed before reporting any items: run it again as it was.
+            (JobStatus::Failed, None) => RetryPlan {
+                kind: job.kind.clone(),
+                args: job.args.clone(),
+            },
+            (_, result) => {
+                let failed = result.as_ref().map(failed_items).unwrap_or_default();
+                if failed.is_empty() {
+                    return Err(ApiError::conflict("job has no failed items"));
+                }
+                plan_retry(&job.kind, job.args.as_ref(), &failed)
+                    .map_err(ApiError::bad_request)?
+            }
+        };
+        (plan, job.profile.clone())
+    };
+    // Retries run against the profile the original job used.
+    let state = scoped_state(&state, profile)?;
+    validate_job_args(&plan.kind, plan.args.as_ref()).map_err(ApiError::bad_request)?;
+    jobs::check_library_writable(&state, &plan.kind).map_err(ApiError::conflict)?;
+
+    let new_id = jobs::queue_job(&state, &plan.kind, plan.args, Some(id)).await;
+    Ok(Json(StartJobResponse {
+        id: new_id,
+        status: JobStatus::Queued,
+    }))
+}
+
+pub async fn list_job_kinds() -> Json<Vec<JobKindView>> {
+    let kinds = JOB_KINDS
+        .iter()
+        .map(|spec| JobKindView {
+            kind: spec.kind,
+            args_required: spec.args_required,
+            resources: job_resources(spec.kind),
+            args_schema: spec.args_schema(),
+            result_schema: spec.result_schema(),
+        })
+        .collect();
+    Json(kinds)
+}
+
 pub async fn get_job(
     State(state): State<AppState>,
     Path(id): Path<u64>,
@@ -496,34 +800,114 @@ pub async fn get_job_logs(
     Path(id): Path<u64>,
     Query(query): Query<JobLogsQuery>,
 ) -> ApiResult<Json<JobLogsResponse>> {
+    let cfg = read_config(&state).map_err(ApiError::internal)?;
+    let mut min_level = min_job_log_level(&cfg.log_level);
+    if let Some(level) = query.level.as_deref() {
+        let requested = min_job_log_level(level);
+        if requested.severity() > min_level.severity() {
+            min_level = requested;
+        }
+    }
+    let contains = query
+        .contains
+        .as_deref()
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty());
+    let limit = query.limit.unwrap_or(MAX_JOB_LOG_PAGE).clamp(1, MAX_JOB_LOG_PAGE);
+
     let jobs = state.jobs.read().await;
     let job = jobs
         .get(&id)
         .ok_or_else(|| ApiError::not_found("job not found"))?;
+    let request_from = query.from.unwrap_or(job.log_offset);
+    let filter = JobLogFilter {
+        from: request_from,
+        min_level,
+        contains,
+        limit,
+    };
 
-    let cfg = read_config(&state).map_err(ApiError::internal)?;
-    let min_level = min_job_log_level(&cfg.log_level);
+    // Lines older than the in-memory tail come from the job's log file.
+    if request_from < job.log_offset {
+        drop(jobs);
+        let store = Arc::clone(&state.job_logs);
+        let (page, mut filter) = tokio::task::spawn_blocking(move || {
+            let page = store.read(id, &filter);
+            (page, filter)
+        })
+        .await
+        .map_err(internal_error)?;
+        let page = page.map_err(internal_error)?;
+        if let Some(first) = page.first {
+            let from = first.max(request_from);
+            return Ok(Json(JobLogsResponse {
+                id,
+                from,
+                next: page.next.max(from),
+                dropped: first > request_from,
+                entries: page.entries,
+            }));
+        }
+        let jobs = state.jobs.read().await;
+        let job = jobs
+            .get(&id)
+            .ok_or_else(|| ApiError::not_found("job not found"))?;
+        filter.from = job.log_offset;
+        return Ok(Json(tail_logs(job, &filter, true)));
+    }
 
-    let request_from = query.from.unwrap_or(job.log_offset);
-    let dropped = request_from < job.log_offset;
-    let from = if dropped { job.log_offset } else { request_from };
-    let start = from.saturating_sub(job.log_offset);
-    let entries = job
-        .logs
-        .iter()
-        .skip(start)
-        .filter(|entry| entry.level.severity() >= min_level.severity())
-        .cloned()
-        .collect();
-    let next = job.log_offset + job.logs.len();
+    Ok(Json(tail_logs(job, &filter, false)))
+}
 
-    Ok(Json(JobLogsResponse {
-        id,
-        from,
+const MAX_JOB_LOG_PAGE: usize = 5000;
+
+fn tail_logs(job: &JobState, filter: &JobLogFilter, dropped: bool) -> JobLogsResponse {
+    let start = filter.from.saturating_sub(job.log_offset);
+    let mut entries = Vec::new();
+    let mut next = job.log_end();
+    for (idx, entry) in job.logs.iter().enumerate().skip(start) {
+        if entries.len() >= filter.limit {
+            next = job.log_offset + idx;
+            break;
+        }
+        if filter.matches(entry) {
+            entries.push(entry.clone());
+        }
+    }
+    JobLogsResponse {
+        id: job.id,
+        from: filter.from,
         next,
         dropped,
         entries,
-    }))
+    }
+}
+
+pub async fn download_job_logs(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> ApiResult<Response> {
+    let kind = {
+        let jobs = state.jobs.read().await;
+        jobs.get(&id)
+            .map(|job| job.kind.clone())
+            .ok_or_else(|| ApiError::not_found("job not found"))?
+    };
+    let store = Arc::clone(&state.job_logs);
+    let text = tokio::task::spawn_blocking(move || store.render_text(id))
+        .await
+        .map_err(internal_error)?
+        .map_err(internal_error)?;
+    let resp = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"job-{}-{}.log\"", id, kind),
+        )
+        .body(Body::from(text))
+        .map_err(internal_error)?;
+    Ok(resp)
 }
 
 pub async fn get_job_result(
@@ -543,6 +927,27 @@ pub async fn get_job_result(
     Ok(Json(JobResultResponse { id, result }))
 }
 
+pub async fn get_job_failed_items(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> ApiResult<Json<JobFailedItemsResponse>> {
+    let jobs = state.jobs.read().await;
+    let job = jobs
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found("job not found"))?;
+
+    let result = job
+        .result
+        .as_ref()
+        .ok_or_else(|| ApiError::conflict("job result not ready"))?;
+
+    Ok(Json(JobFailedItemsResponse {
+        id,
+        kind: job.kind.clone(),
+        items: failed_items(result),
+    }))
+}
+
 #[derive(Deserialize)]
 pub struct DownloadEnqueueItemRequest {
     pub url: String,
@@ -554,6 +959,8 @@ pub struct DownloadEnqueueItemRequest {
 pub struct DownloadEnqueueRequest {
     pub urls: Option<Vec<String>>,
     pub items: Option<Vec<DownloadEnqueueItemRequest>>,
+    /// Packages without a known URL, looked up in the package sources.
+    pub var_names: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
@@ -576,7 +983,7 @@ pub async fn list_downloads(
 pub async fn enqueue_downloads(
     State(state): State<AppState>,
     Json(req): Json<DownloadEnqueueRequest>,
-) -> ApiResult<Json<Value>> {
+) -> ApiResult<Json<EnqueueReport>> {
     let mut items = Vec::new();
     if let Some(urls) = req.urls {
         for url in urls {
@@ -596,15 +1003,55 @@ pub async fn enqueue_downloads(
             });
         }
     }
+    if let Some(names) = req.var_names {
+        for name in names {
+            let name = name.trim().trim_end_matches(".var");
+            if name.is_empty() {
+                continue;
+            }
+            items.push(DownloadEnqueueItem {
+                url: format!("{}{}", VAR_URL_PREFIX, name),
+                name: Some(name.to_string()),
+                size: None,
+            });
+        }
+    }
     if items.is_empty() {
         return Err(ApiError::bad_request("download urls required"));
     }
-    let added = state
+    let report = state
         .download_manager
         .enqueue_items(items)
         .await
         .map_err(ApiError::internal)?;
-    Ok(Json(json!({ "added": added })))
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+pub struct DownloadHistoryQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Completed downloads with the var each one turned out to be, newest first.
+pub async fn list_download_history(
+    State(state): State<AppState>,
+    Query(query): Query<DownloadHistoryQuery>,
+) -> ApiResult<Json<Vec<DownloadHistoryEntry>>> {
+    let limit = query.limit.unwrap_or(200).clamp(1, 1000);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let entries = state
+        .download_manager
+        .history(limit, offset)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(entries))
+}
+
+pub async fn list_download_sources(
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<SourceStatus>>> {
+    Ok(Json(state.download_manager.source_statuses()))
 }
 
 pub async fn download_actions(
@@ -621,12 +1068,113 @@ pub async fn download_actions(
     Ok(Json(json!({ "status": "ok" })))
 }
 
+/// Server-sent events for download status, progress and total throughput.
+/// A `lagged` event means some were dropped; reload `GET /downloads`.
+pub async fn download_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let stream = BroadcastStream::new(state.download_manager.subscribe()).map(|item| {
+        let event = match item {
+            Ok(event) => Event::default()
+                .event(event.name())
+                .json_data(&event)
+                .unwrap_or_else(|err| Event::default().event("error").data(err.to_string())),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                Event::default().event("lagged").data(skipped.to_string())
+            }
+        };
+        Ok(event)
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+pub struct DownloadPriorityRequest {
+    pub ids: Vec<i64>,
+    pub priority: i64,
+}
+
+pub async fn set_download_priority(
+    State(state): State<AppState>,
+    Json(req): Json<DownloadPriorityRequest>,
+) -> ApiResult<Json<Value>> {
+    state
+        .download_manager
+        .set_priority(&req.ids, req.priority)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+/// A scene inside a var (`var_name`) or a loose save (`save_path`, as
+/// recorded by the saves dependency scan).
+#[derive(Deserialize)]
+pub struct PrioritizeSceneRequest {
+    pub var_name: Option<String>,
+    pub save_path: Option<String>,
+}
+
+/// Move the queued downloads a scene still needs to the top of the queue.
+/// Dependencies are followed through vars already in the library, since
+/// those may need further missing packages.
+pub async fn prioritize_scene_downloads(
+    State(state): State<AppState>,
+    Json(req): Json<PrioritizeSceneRequest>,
+) -> ApiResult<Json<Value>> {
+    let pool = &state.db_pool;
+    let mut pending = if let Some(save_path) = req.save_path.as_deref().map(str::trim) {
+        sqlx::query_scalar::<_, Option<String>>(
+            "SELECT dependency FROM savedepens WHERE SavePath = ?1",
+        )
+        .bind(save_path)
+        .fetch_all(pool)
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+    } else if let Some(var_name) = req.var_name.as_deref().map(str::trim) {
+        crate::infra::db::list_dependencies_for_vars(pool, &[var_name.to_string()])
+            .await
+            .map_err(ApiError::internal)?
+    } else {
+        return Err(ApiError::bad_request("var_name or save_path required"));
+    };
+
+    let mut seen = HashSet::new();
+    let mut needed = Vec::new();
+    while let Some(dep) = pending.pop() {
+        if !seen.insert(dep.clone()) {
+            continue;
+        }
+        let exist = crate::domain::var_logic::resolve_var_exist_name(pool, &dep)
+            .await
+            .map_err(ApiError::internal)?;
+        if exist == "missing" {
+            needed.push(dep);
+        } else {
+            let deps = crate::infra::db::list_dependencies_for_vars(pool, &[exist])
+                .await
+                .map_err(ApiError::internal)?;
+            pending.extend(deps);
+        }
+    }
+    let moved = state
+        .download_manager
+        .prioritize_vars(&needed)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(Json(json!({ "missing": needed, "moved": moved })))
+}
+
 fn parse_download_action(raw: &str) -> Option<DownloadAction> {
     match raw.trim().to_ascii_lowercase().as_str() {
         "pause" => Some(DownloadAction::Pause),
         "resume" => Some(DownloadAction::Resume),
         "remove" => Some(DownloadAction::Remove),
         "delete" => Some(DownloadAction::Delete),
+        "top" => Some(DownloadAction::MoveTop),
+        "bottom" => Some(DownloadAction::MoveBottom),
         _ => None,
     }
 }
@@ -671,6 +1219,47 @@ fn normalize_vam_exec(value: Option<String>) -> Option<String> {
     })
 }
 
+fn normalize_package_sources(
+    sources: Vec<crate::app::PackageSource>,
+) -> Result<Vec<crate::app::PackageSource>, String> {
+    use crate::app::PackageSourceKind;
+    let mut normalized: Vec<crate::app::PackageSource> = Vec::with_capacity(sources.len());
+    for mut source in sources {
+        source.name = source.name.trim().to_string();
+        source.location = source.location.trim().to_string();
+        if source.name.is_empty() {
+            return Err("download_sources name cannot be empty".to_string());
+        }
+        if normalized.iter().any(|s| s.name.eq_ignore_ascii_case(&source.name)) {
+            return Err(format!("duplicate download_sources name: {}", source.name));
+        }
+        match source.kind {
+            PackageSourceKind::Hub => {}
+            PackageSourceKind::Http => {
+                let valid = url::Url::parse(&source.location)
+                    .map(|url| matches!(url.scheme(), "http" | "https"))
+                    .unwrap_or(false);
+                if !valid {
+                    return Err(format!(
+                        "download_sources '{}' needs an http(s) location",
+                        source.name
+                    ));
+                }
+            }
+            PackageSourceKind::Directory => {
+                if source.location.is_empty() {
+                    return Err(format!(
+                        "download_sources '{}' location cannot be empty",
+                        source.name
+                    ));
+                }
+            }
+        }
+        normalized.push(source);
+    }
+    Ok(normalized)
+}
+
 fn normalize_proxy(mut proxy: crate::app::ProxyConfig) -> crate::app::ProxyConfig {
     proxy.host = proxy.host.trim().to_string();
     proxy.username = normalize_optional(proxy.username);
@@ -715,15 +1304,110 @@ fn apply_config_update(current: &Config, req: UpdateConfigRequest) -> Result<Con
     if req.varspath.is_some() {
         next.varspath = normalize_optional(req.varspath);
     }
+    if let Some(roots) = req.library_roots {
+        let mut normalized = Vec::with_capacity(roots.len());
+        for root in roots {
+            let path = root.path.trim();
+            if path.is_empty() {
+                return Err("library_roots path cannot be empty".to_string());
+            }
+            normalized.push(crate::app::LibraryRoot {
+                path: path.to_string(),
+                priority: root.priority,
+            });
+        }
+        next.library_roots = normalized;
+    }
     if req.vampath.is_some() {
         next.vampath = normalize_optional(req.vampath);
     }
     if req.vam_exec.is_some() {
         next.vam_exec = normalize_vam_exec(req.vam_exec);
     }
+    if let Some(profiles) = req.vam_profiles {
+        let mut normalized: Vec<crate::app::VamProfile> = Vec::with_capacity(profiles.len());
+        for profile in profiles {
+            let name = profile.name.trim();
+            if name.is_empty() {
+                return Err("vam_profiles name cannot be empty".to_string());
+            }
+            if name.eq_ignore_ascii_case(crate::app::DEFAULT_PROFILE) {
+                return Err(format!(
+                    "vam_profiles name '{}' is reserved for the top-level vampath",
+                    name
+                ));
+            }
+            if normalized.iter().any(|p| p.name.eq_ignore_ascii_case(name)) {
+                return Err(format!("duplicate vam_profiles name: {}", name));
+            }
+            let vampath = profile.vampath.trim();
+            if vampath.is_empty() {
+                return Err(format!("vam_profiles '{}' vampath cannot be empty", name));
+            }
+            normalized.push(crate::app::VamProfile {
+                name: name.to_string(),
+                vampath: vampath.to_string(),
+                vam_exec: normalize_vam_exec(profile.vam_exec),
+            });
+        }
+        next.vam_profiles = normalized;
+    }
+    if req.active_profile.is_some() {
+        next.active_profile = normalize_optional(req.active_profile);
+    }
+    if let Some(active) = next.active_profile.as_deref() {
+        resolve_profile(&next, Some(active))?;
+    }
     if req.downloader_save_path.is_some() {
         next.downloader_save_path = normalize_optional(req.downloader_save_path);
     }
+    if let Some(limit) = req.download_speed_limit_kbps {
+        next.download.speed_limit_kbps = limit;
+    }
+    if let Some(limit) = req.download_per_download_speed_limit_kbps {
+        next.download.per_download_speed_limit_kbps = limit;
+    }
+    if let Some(windows) = req.download_windows {
+        let mut normalized = Vec::with_capacity(windows.len());
+        for window in windows {
+            parse_window_time(&window.start)?;
+            parse_window_time(&window.end)?;
+            normalized.push(crate::app::DownloadWindow {
+                start: window.start.trim().to_string(),
+                end: window.end.trim().to_string(),
+            });
+        }
+        next.download.windows = normalized;
+    }
+    if let Some(sources) = req.download_sources {
+        next.download.sources = normalize_package_sources(sources)?;
+    }
+    if let Some(resume) = req.download_resume_on_startup {
+        next.download.resume_on_startup = resume;
+    }
+    if let Some(mut lan_cache) = req.lan_cache {
+        lan_cache.listen_host = lan_cache.listen_host.trim().to_string();
+        if lan_cache.listen_host.is_empty() {
+            return Err("lan_cache.listen_host cannot be empty".to_string());
+        }
+        if lan_cache.listen_port == 0 {
+            return Err("lan_cache.listen_port must be between 1 and 65535".to_string());
+        }
+        if lan_cache.listen_port == next.listen_port {
+            return Err("lan_cache.listen_port must differ from listen_port".to_string());
+        }
+        lan_cache.allowed_clients = lan_cache
+            .allowed_clients
+            .iter()
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| lan_cache::parse_allow_entry(entry).map(|_| entry.to_string()))
+            .collect::<Result<_, _>>()?;
+        next.lan_cache = lan_cache;
+    }
+    if let Some(read_only) = req.read_only_library {
+        next.read_only_library = read_only;
+    }
     if let Some(image_cache) = req.image_cache {
         next.image_cache = image_cache;
     }
@@ -768,12 +1452,17 @@ pub async fn update_config(
             .map_err(|_| ApiError::internal("semaphore lock poisoned"))?;
         *guard = Arc::new(Semaphore::new(next.job_concurrency));
     }
+    state
+        .download_manager
+        .config_changed()
+        .await
+        .map_err(ApiError::internal)?;
 
     Ok(Json(next))
 }
 
 pub async fn list_vars(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
     Query(query): Query<VarsQuery>,
 ) -> ApiResult<Json<VarsListResponse>> {
     let _cfg = read_config(&state).map_err(internal_error)?;
@@ -790,7 +1479,9 @@ pub async fn list_vars(
     }
 
     let mut conditions = Vec::new();
-    let mut params: Vec<BindValue> = Vec::new();
+    // The installStatus join binds the profile ahead of the WHERE params.
+    let profile = crate::infra::paths::profile_name(&state).map_err(internal_error)?;
+    let mut params: Vec<BindValue> = vec![BindValue::Text(profile)];
 
     if let Some(creator) = query.creator.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
         conditions.push("v.creatorName = ?".to_string());
@@ -950,7 +1641,8 @@ pub async fn list_vars(
     };
 
     let count_sql = format!(
-        "SELECT COUNT(1) FROM vars v LEFT JOIN installStatus i ON v.varName = i.varName {}",
+        "SELECT COUNT(1) FROM vars v \
+         LEFT JOIN installStatus i ON v.varName = i.varName AND i.profile = ? {}",
         where_clause
     );
     let mut count_query = sqlx::query_scalar::<_, i64>(&count_sql);
@@ -973,7 +1665,7 @@ pub async fn list_vars(
                 v.look, v.subScene, v.appearance, v.dependencyCnt, v.fsize,
                 COALESCE(i.installed, 0), COALESCE(i.disabled, 0)
          FROM vars v
-         LEFT JOIN installStatus i ON v.varName = i.varName
+         LEFT JOIN installStatus i ON v.varName = i.varName AND i.profile = ?
          {}
          ORDER BY {} {}
          LIMIT ? OFFSET ?",
@@ -1149,13 +1841,11 @@ pub async fn load_missing_map(
 }
 
 pub async fn list_missing_links(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
 ) -> ApiResult<Json<MissingMapResponse>> {
-    let cfg = read_config(&state).map_err(internal_error)?;
-    let vampath = cfg
+    let vampath = vam_profile(&state)
+        .map_err(internal_error)?
         .vampath
-        .as_ref()
-        .map(PathBuf::from)
         .ok_or_else(|| ApiError::bad_request("vampath is required in config.json"))?;
     let root = crate::infra::paths::missing_links_dir(&vampath);
     if !root.exists() {
@@ -1198,7 +1888,7 @@ pub async fn list_missing_links(
 }
 
 pub async fn get_var_detail(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
     Path(name): Path<String>,
 ) -> ApiResult<Json<VarDetailResponse>> {
     let _cfg = read_config(&state).map_err(internal_error)?;
@@ -1210,11 +1900,12 @@ pub async fn get_var_detail(
                 v.look, v.subScene, v.appearance, v.dependencyCnt, v.fsize,
                 COALESCE(i.installed, 0), COALESCE(i.disabled, 0)
          FROM vars v
-         LEFT JOIN installStatus i ON v.varName = i.varName
+         LEFT JOIN installStatus i ON v.varName = i.varName AND i.profile = ?2
          WHERE v.varName = ?1
          LIMIT 1",
     )
     .bind(&name)
+    .bind(crate::infra::paths::profile_name(&state).map_err(internal_error)?)
     .fetch_optional(pool)
     .await
     .map_err(internal_error)?;
@@ -1271,7 +1962,7 @@ pub async fn get_var_detail(
 }
 
 pub async fn list_scenes(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
     Query(query): Query<ScenesQuery>,
 ) -> ApiResult<Json<ScenesListResponse>> {
     let _cfg = read_config(&state).map_err(internal_error)?;
@@ -1284,7 +1975,10 @@ pub async fn list_scenes(
         Text(String),
     }
     let mut conditions = Vec::new();
-    let mut params: Vec<BindValue> = Vec::new();
+    // The installStatus and HideFav joins bind the profile ahead of the WHERE params.
+    let profile = crate::infra::paths::profile_name(&state).map_err(internal_error)?;
+    let mut params: Vec<BindValue> =
+        vec![BindValue::Text(profile.clone()), BindValue::Text(profile)];
 
     if let Some(category) = query.category.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
         conditions.push("s.atomType = ?".to_string());
@@ -1341,8 +2035,9 @@ pub async fn list_scenes(
                 COALESCE(h.hide, 0), COALESCE(h.fav, 0)
          FROM scenes s
          LEFT JOIN vars v ON s.varName = v.varName
-         LEFT JOIN installStatus i ON s.varName = i.varName
-         LEFT JOIN HideFav h ON s.varName = h.varName AND s.scenePath = h.scenePath
+         LEFT JOIN installStatus i ON s.varName = i.varName AND i.profile = ?
+         LEFT JOIN HideFav h
+           ON s.varName = h.varName AND s.scenePath = h.scenePath AND h.profile = ?
          {}",
         where_clause
     );
@@ -1397,7 +2092,8 @@ pub async fn list_scenes(
             items.extend(load_save_scenes(&vampath));
         }
         if include_missing {
-            items.extend(load_missing_link_scenes(pool, &vampath).await);
+            let profile = crate::infra::paths::profile_name(&state).map_err(internal_error)?;
+            items.extend(load_missing_link_scenes(pool, &profile, &vampath).await);
         }
     }
 
@@ -1666,6 +2362,7 @@ fn load_save_scenes(vampath: &StdPath) -> Vec<SceneListItem> {
 
 async fn load_missing_link_scenes(
     pool: &SqlitePool,
+    profile: &str,
     vampath: &StdPath,
 ) -> Vec<SceneListItem> {
     let mut items = Vec::new();
@@ -1705,10 +2402,11 @@ async fn load_missing_link_scenes(
                 COALESCE(i.installed, 0), COALESCE(i.disabled, 0)
          FROM scenes s
          LEFT JOIN vars v ON s.varName = v.varName
-         LEFT JOIN installStatus i ON s.varName = i.varName
+         LEFT JOIN installStatus i ON s.varName = i.varName AND i.profile = ?2
          WHERE s.varName = ?1",
         )
         .bind(&var_name)
+        .bind(profile)
         .fetch_all(pool)
         .await
         {
@@ -1866,8 +2564,32 @@ pub async fn list_hub_options(
     Ok(Json(HubOptionsResponse { items, total }))
 }
 
+pub async fn get_hub_auth() -> Json<HubAuthStatus> {
+    Json(hub_auth::status())
+}
+
+/// Save Hub credentials and/or browser cookies. The values are kept even when
+/// the login they trigger fails, so a typo can be corrected field by field.
+pub async fn update_hub_auth(
+    Json(req): Json<HubAuthUpdate>,
+) -> ApiResult<Json<HubAuthStatus>> {
+    let status = hub_auth::update(req).await.map_err(|err| {
+        if hub_auth::is_auth_error(&err) {
+            ApiError::new(StatusCode::UNAUTHORIZED, err)
+        } else {
+            ApiError::new(StatusCode::BAD_GATEWAY, err)
+        }
+    })?;
+    Ok(Json(status))
+}
+
+pub async fn clear_hub_auth() -> ApiResult<Json<HubAuthStatus>> {
+    hub_auth::clear().map_err(internal_error)?;
+    Ok(Json(hub_auth::status()))
+}
+
 pub async fn list_packswitch(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
 ) -> ApiResult<Json<PackSwitchListResponse>> {
     let (_, vampath) = crate::infra::paths::config_paths(&state).map_err(internal_error)?;
     let vampath =
@@ -1980,7 +2702,7 @@ pub async fn list_var_dependencies(
 }
 
 pub async fn list_var_previews(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
     Json(req): Json<VarPreviewsRequest>,
 ) -> ApiResult<Json<VarPreviewsResponse>> {
     let mut names: Vec<String> = req
@@ -1996,14 +2718,16 @@ pub async fn list_var_previews(
     }
 
     let pool = &state.db_pool;
+    let profile = crate::infra::paths::profile_name(&state).map_err(internal_error)?;
 
     let mut builder = QueryBuilder::new(
         "SELECT s.varName, s.atomType, s.previewPic, s.scenePath, s.isPreset, s.isLoadable, \
                 COALESCE(i.installed, 0) \
          FROM scenes s \
-         LEFT JOIN installStatus i ON s.varName = i.varName \
-         WHERE s.varName IN (",
+         LEFT JOIN installStatus i ON s.varName = i.varName AND i.profile = ",
     );
+    builder.push_bind(profile);
+    builder.push(" WHERE s.varName IN (");
     let mut separated = builder.separated(", ");
     for name in &names {
         separated.push_bind(name);
@@ -2077,7 +2801,7 @@ pub async fn list_dependents(
 }
 
 pub async fn list_analysis_atoms(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
     Query(query): Query<AnalysisAtomsQuery>,
 ) -> ApiResult<Json<AnalysisAtomsResponse>> {
     let (atoms, person_atoms) =
@@ -2090,7 +2814,7 @@ pub async fn list_analysis_atoms(
 }
 
 pub async fn get_analysis_summary(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
     Query(query): Query<AnalysisAtomsQuery>,
 ) -> ApiResult<Json<scenes::AnalysisSummary>> {
     let summary = scenes::analysis_summary(&state, &query.var_name, &query.entry_name)
@@ -2100,7 +2824,7 @@ pub async fn get_analysis_summary(
 }
 
 pub async fn list_saves_tree(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
 ) -> ApiResult<Json<SavesTreeResponse>> {
     let (_, vampath) = crate::infra::paths::config_paths(&state).map_err(internal_error)?;
     let vampath = vampath
@@ -2175,7 +2899,7 @@ pub async fn list_saves_tree(
 }
 
 pub async fn get_stats(
-    State(state): State<AppState>,
+    ProfileState(state): ProfileState,
 ) -> ApiResult<Json<StatsResponse>> {
     let _cfg = read_config(&state).map_err(internal_error)?;
     let pool = &state.db_pool;
@@ -2184,15 +2908,18 @@ pub async fn get_stats(
         .fetch_one(pool)
         .await
         .map_err(internal_error)? as u64;
+    let profile = crate::infra::paths::profile_name(&state).map_err(internal_error)?;
     let vars_installed: u64 = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(1) FROM installStatus WHERE installed = 1",
+        "SELECT COUNT(1) FROM installStatus WHERE profile = ?1 AND installed = 1",
     )
+    .bind(&profile)
     .fetch_one(pool)
     .await
     .map_err(internal_error)? as u64;
     let vars_disabled: u64 = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(1) FROM installStatus WHERE disabled = 1",
+        "SELECT COUNT(1) FROM installStatus WHERE profile = ?1 AND disabled = 1",
     )
+    .bind(&profile)
     .fetch_one(pool)
     .await
     .map_err(internal_error)? as u64;
@@ -2219,8 +2946,232 @@ pub async fn get_stats(
     }))
 }
 
-pub async fn get_preview(
+pub async fn get_library_stats(
+    ProfileState(state): ProfileState,
+    Query(query): Query<LibraryStatsQuery>,
+) -> ApiResult<Json<LibraryStats>> {
+    let period = TrendPeriod::parse(query.period.as_deref()).map_err(ApiError::bad_request)?;
+    let top = query
+        .top
+        .unwrap_or(library_stats::DEFAULT_TOP)
+        .clamp(1, library_stats::MAX_TOP);
+    let profile = crate::infra::paths::profile_name(&state).map_err(internal_error)?;
+    let stats = library_stats::library_stats(&state.db_pool, &profile, top, period)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(stats))
+}
+
+pub async fn list_entries(
+    State(state): State<AppState>,
+    Query(query): Query<EntriesQuery>,
+) -> ApiResult<Json<EntriesListResponse>> {
+    let pool = &state.db_pool;
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(100).clamp(1, 1000);
+    let offset = ((page - 1) * per_page) as i64;
+
+    let mut count_builder = QueryBuilder::new("SELECT COUNT(1) FROM entries");
+    push_entry_filters(&mut count_builder, &query);
+    let total = count_builder
+        .build_query_scalar::<i64>()
+        .fetch_one(pool)
+        .await
+        .map_err(internal_error)? as u64;
+
+    let mut builder = QueryBuilder::new(
+        "SELECT varName, path, size, compressedSize, crc, contentType FROM entries",
+    );
+    push_entry_filters(&mut builder, &query);
+    builder.push(" ORDER BY varName, path LIMIT ");
+    builder.push_bind(per_page as i64);
+    builder.push(" OFFSET ");
+    builder.push_bind(offset);
+    let rows = builder
+        .build()
+        .fetch_all(pool)
+        .await
+        .map_err(internal_error)?;
+    let mut items = Vec::with_capacity(rows.len());
+    for row in rows {
+        items.push(EntryListItem {
+            var_name: row.try_get(0).map_err(internal_error)?,
+            path: row.try_get(1).map_err(internal_error)?,
+            size: row.try_get(2).map_err(internal_error)?,
+            compressed_size: row.try_get(3).map_err(internal_error)?,
+            crc: row.try_get(4).map_err(internal_error)?,
+            content_type: row.try_get(5).map_err(internal_error)?,
+        });
+    }
+    Ok(Json(EntriesListResponse {
+        items,
+        page,
+        per_page,
+        total,
+    }))
+}
+
+fn push_entry_filters(builder: &mut QueryBuilder<'_, sqlx::Sqlite>, query: &EntriesQuery) {
+    let mut first = true;
+    let mut and = |builder: &mut QueryBuilder<'_, sqlx::Sqlite>| {
+        builder.push(if first { " WHERE " } else { " AND " });
+        first = false;
+    };
+    if let Some(var_name) = query.var_name.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        and(builder);
+        builder.push("varName = ");
+        builder.push_bind(var_name.to_string());
+    }
+    if let Some(path) = query.path.as_deref().map(normalize_entry_path).filter(|s| !s.is_empty()) {
+        and(builder);
+        if path.ends_with('/') {
+            builder.push("path LIKE ");
+            builder.push_bind(format!("{}%", escape_like(&path)));
+            builder.push(" ESCAPE '\\'");
+        } else {
+            builder.push("path = ");
+            builder.push_bind(path);
+            builder.push(" COLLATE NOCASE");
+        }
+    }
+    if let Some(name) = query.file_name.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        and(builder);
+        builder.push("(path = ");
+        builder.push_bind(name.to_string());
+        builder.push(" COLLATE NOCASE OR path LIKE ");
+        builder.push_bind(format!("%/{}", escape_like(name)));
+        builder.push(" ESCAPE '\\')");
+    }
+    if let Some(content_type) = query
+        .content_type
+        .as_ref()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+    {
+        and(builder);
+        builder.push("contentType = ");
+        builder.push_bind(content_type.to_string());
+    }
+}
+
+/// Archive entry paths use `/` and no leading separator.
+fn normalize_entry_path(path: &str) -> String {
+    path.trim().replace('\\', "/").trim_start_matches('/').to_string()
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Packages that ship the given resource paths or the files a scene
+/// references. Large scenes can use the `resource_providers` job instead.
+pub async fn find_resource_providers(
     State(state): State<AppState>,
+    Json(req): Json<ProvidersRequest>,
+) -> ApiResult<Json<ProvidersReport>> {
+    let refs = gather_refs(&req.paths, req.scene.as_ref()).map_err(bad_request_error)?;
+    let mut lookups = Vec::with_capacity(refs.len());
+    for resource in &refs {
+        lookups.push(
+            find_providers(&state.db_pool, resource)
+                .await
+                .map_err(internal_error)?,
+        );
+    }
+    Ok(Json(summarize(lookups)))
+}
+
+const SEARCH_ENTRY_SAMPLES: usize = 3;
+
+pub async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<Json<SearchResponse>> {
+    let fts = db::fts_query(&query.q)
+        .ok_or_else(|| ApiError::bad_request("q must contain a word to search for"))?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 500) as usize;
+    let pool = &state.db_pool;
+
+    let var_hits = db::search_vars(pool, &fts, limit as i64)
+        .await
+        .map_err(internal_error)?;
+    // Entries are fetched past `limit` so vars matched only through their
+    // content still get grouped and ranked.
+    let entry_hits = db::search_entries(pool, &fts, (limit * 10) as i64)
+        .await
+        .map_err(internal_error)?;
+
+    let mut groups: Vec<SearchVarHit> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for hit in var_hits {
+        index.insert(hit.var_name.clone(), groups.len());
+        groups.push(search_var_hit(&hit.var_name, Some(hit.score)));
+    }
+    for hit in &entry_hits {
+        let idx = *index.entry(hit.var_name.clone()).or_insert_with(|| {
+            groups.push(search_var_hit(&hit.var_name, None));
+            groups.len() - 1
+        });
+        let group = &mut groups[idx];
+        group.entry_matches += 1;
+        group.entry_score = Some(group.entry_score.map_or(hit.score, |best| best.min(hit.score)));
+        if group.entries.len() < SEARCH_ENTRY_SAMPLES {
+            group.entries.push(hit.path.clone());
+        }
+    }
+    let rank = |group: &SearchVarHit| {
+        if group.name_match {
+            group.score
+        } else {
+            group.entry_score
+        }
+        .unwrap_or(f64::MAX)
+    };
+    groups.sort_by(|a, b| {
+        b.name_match
+            .cmp(&a.name_match)
+            .then(rank(a).total_cmp(&rank(b)))
+            .then(b.entry_matches.cmp(&a.entry_matches))
+    });
+    groups.truncate(limit);
+
+    let items = entry_hits
+        .into_iter()
+        .take(limit)
+        .map(|hit| SearchItemHit {
+            var_name: hit.var_name,
+            path: hit.path,
+            atom_type: hit.atom_type,
+            score: hit.score,
+        })
+        .collect();
+
+    Ok(Json(SearchResponse {
+        query: query.q,
+        vars: groups,
+        items,
+    }))
+}
+
+fn search_var_hit(var_name: &str, score: Option<f64>) -> SearchVarHit {
+    let mut parts = var_name.splitn(3, '.');
+    SearchVarHit {
+        var_name: var_name.to_string(),
+        creator_name: parts.next().unwrap_or_default().to_string(),
+        package_name: parts.next().unwrap_or_default().to_string(),
+        score,
+        name_match: score.is_some(),
+        entry_score: None,
+        entry_matches: 0,
+        entries: Vec::new(),
+    }
+}
+
+pub async fn get_preview(
+    ProfileState(state): ProfileState,
     Query(query): Query<PreviewQuery>,
 ) -> ApiResult<Response> {
     let source = parse_image_source(&state, query).map_err(bad_request_error)?;
@@ -2238,6 +3189,97 @@ pub async fn get_preview(
     Ok(resp)
 }
 
+/// Serve a library var by exact name (`Creator.Package.1.var`) to another
+/// varManager instance. Read-only, and only while the LAN cache is enabled
+/// and the caller is on its allow-list.
+pub async fn lan_get_var(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(file): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let cfg = read_config(&state).map_err(internal_error)?;
+    if !cfg.lan_cache.enabled {
+        return Err(ApiError::not_found("lan cache disabled"));
+    }
+    if !lan_cache::client_allowed(&cfg.lan_cache, peer.ip()) {
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "client not allowed"));
+    }
+    let var_name = file.strip_suffix(".var").unwrap_or(&file).to_string();
+    let valid = var_name.split('.').count() == 3
+        && !var_name.split('.').any(str::is_empty)
+        && !var_name.contains(['/', '\\']);
+    if !valid {
+        return Err(ApiError::not_found("var not found"));
+    }
+    let indexed = sqlx::query_scalar::<_, i64>("SELECT 1 FROM vars WHERE varName = ?1")
+        .bind(&var_name)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(internal_error)?;
+    if indexed.is_none() {
+        return Err(ApiError::not_found("var not found"));
+    }
+    let varspath = cfg
+        .varspath
+        .as_ref()
+        .map(PathBuf::from)
+        .ok_or_else(|| ApiError::not_found("varspath not set"))?;
+    let path = resolve_var_file_path(&varspath, &var_name).map_err(ApiError::not_found)?;
+
+    let checksum_path = path.clone();
+    let (len, sha256) = tokio::task::spawn_blocking(move || {
+        let len = std::fs::metadata(&checksum_path)
+            .map_err(|err| err.to_string())?
+            .len();
+        lan_cache::file_checksum(&checksum_path).map(|sha256| (len, sha256))
+    })
+    .await
+    .map_err(internal_error)?
+    .map_err(internal_error)?;
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let Ok(range) = lan_cache::parse_range(range, len) else {
+        let resp = Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(Body::empty())
+            .map_err(internal_error)?;
+        return Ok(resp);
+    };
+    let (start, end) = range.unwrap_or((0, len.saturating_sub(1)));
+    let body_len = if len == 0 { 0 } else { end - start + 1 };
+
+    let mut handle = tokio::fs::File::open(&path).await.map_err(internal_error)?;
+    if start > 0 {
+        handle
+            .seek(SeekFrom::Start(start))
+            .await
+            .map_err(internal_error)?;
+    }
+    let body = Body::from_stream(ReaderStream::new(handle.take(body_len)));
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, body_len)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, format!("\"{}\"", sha256))
+        .header(lan_cache::CHECKSUM_HEADER, &sha256)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.var\"", var_name),
+        );
+    builder = match range {
+        Some((start, end)) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len)),
+        None => builder.status(StatusCode::OK),
+    };
+    builder.body(body).map_err(internal_error)
+}
+
 fn safe_join(base: &StdPath, relative: &str) -> Result<PathBuf, String> {
     let rel = PathBuf::from(relative);
     for comp in rel.components() {
@@ -2321,14 +3363,23 @@ fn resolve_local_source(
             .as_ref()
             .map(PathBuf::from)
             .ok_or_else(|| "varspath not set".to_string()),
-        "vampath" => cfg
+        "vampath" => resolve_profile(&cfg, state.profile.as_deref())?
             .vampath
-            .as_ref()
-            .map(PathBuf::from)
             .ok_or_else(|| "vampath not set".to_string()),
         "cache" => Ok(data_dir().join("Cache")),
         _ => Err("invalid preview root".to_string()),
     }?;
+    // Read-only libraries keep extracted previews in the data dir.
+    let in_preview_dir = path
+        .trim_start_matches(['/', '\\'])
+        .split(['/', '\\'])
+        .next()
+        .is_some_and(|first| first.eq_ignore_ascii_case(PREVIEW_DIR));
+    let base = if root_normalized == "varspath" && cfg.read_only_library && in_preview_dir {
+        data_dir()
+    } else {
+        base
+    };
 
     let joined = safe_join(&base, path)?;
     Ok(ResolvedImageSource {
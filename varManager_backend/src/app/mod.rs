@@ -1,4 +1,6 @@
 use crate::jobs::job_channel::{JobEventSender, JobMap};
+use crate::jobs::job_locks::JobLocks;
+use crate::jobs::job_log_store::JobLogStore;
 use chrono::Local;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
@@ -21,6 +23,8 @@ use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
 pub const PARENT_PID_ENV: &str = "VARMANAGER_PARENT_PID";
+/// Name of the profile formed by the top-level `vampath` and `vam_exec`.
+pub const DEFAULT_PROFILE: &str = "default";
 pub const APP_VERSION: &str = match option_env!("APP_VERSION") {
     Some(value) => value,
     None => env!("CARGO_PKG_VERSION"),
@@ -72,6 +76,63 @@ impl ProxyConfig {
     }
 }
 
+/// A named VaM installation sharing the VAR library with the others.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VamProfile {
+    pub name: String,
+    pub vampath: String,
+    #[serde(default)]
+    pub vam_exec: Option<String>,
+}
+
+/// An additional VAR library folder. When a var exists in several roots the
+/// copy in the root with the highest `priority` is used; `varspath` has
+/// priority 0 unless it is listed here too.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LibraryRoot {
+    pub path: String,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Serve library vars to other varManager instances under `/lan/vars/`.
+/// The route gets its own listener on `listen_host:listen_port`, separate
+/// from the control API, so exposing it does not expose anything else. The
+/// other instance adds an `http` source with location
+/// `http://<host>:<port>/lan/vars`. Address changes apply after a restart.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LanCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_lan_cache_host")]
+    pub listen_host: String,
+    #[serde(default = "default_lan_cache_port")]
+    pub listen_port: u16,
+    /// Client addresses or `address/prefix` networks; loopback is always
+    /// allowed.
+    #[serde(default)]
+    pub allowed_clients: Vec<String>,
+}
+
+fn default_lan_cache_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_lan_cache_port() -> u16 {
+    57124
+}
+
+impl Default for LanCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_host: default_lan_cache_host(),
+            listen_port: default_lan_cache_port(),
+            allowed_clients: Vec::new(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ImageCacheConfig {
     pub disk_cache_size_mb: u32,
@@ -102,6 +163,60 @@ pub struct DownloadConfig {
     pub max_download_retries: u8,
     pub progress_tick_ms: u64,
     pub progress_db_flush_secs: u64,
+    /// Move finished downloads into the library and index them.
+    #[serde(default = "default_true")]
+    pub auto_import: bool,
+    /// Also install auto-imported vars with their dependencies.
+    #[serde(default)]
+    pub auto_install: bool,
+    /// Cap shared by all running downloads, in KiB/s. 0 means unlimited.
+    #[serde(default)]
+    pub speed_limit_kbps: u64,
+    /// Cap for each running download, in KiB/s. 0 means unlimited.
+    #[serde(default)]
+    pub per_download_speed_limit_kbps: u64,
+    /// Local time windows in which queued downloads start. Empty means any
+    /// time.
+    #[serde(default)]
+    pub windows: Vec<DownloadWindow>,
+    /// Where packages are fetched from, highest `priority` first. Empty means
+    /// Hub only.
+    #[serde(default)]
+    pub sources: Vec<PackageSource>,
+    /// Start interrupted downloads again when the backend starts, instead of
+    /// leaving them paused. Either way they continue from the last chunk.
+    #[serde(default)]
+    pub resume_on_startup: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageSourceKind {
+    Hub,
+    /// `location` is a base URL serving `<var name>.var`.
+    Http,
+    /// `location` is a folder (local or a UNC share) holding `.var` files.
+    Directory,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackageSource {
+    pub name: String,
+    pub kind: PackageSourceKind,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// `start`..`end` as `HH:MM`; a window whose end is not after its start runs
+/// past midnight.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DownloadWindow {
+    pub start: String,
+    pub end: String,
 }
 
 impl Default for DownloadConfig {
@@ -116,10 +231,21 @@ impl Default for DownloadConfig {
             max_download_retries: 3,
             progress_tick_ms: 800,
             progress_db_flush_secs: 2,
+            auto_import: true,
+            auto_install: false,
+            speed_limit_kbps: 0,
+            per_download_speed_limit_kbps: 0,
+            windows: Vec::new(),
+            sources: Vec::new(),
+            resume_on_startup: false,
         }
     }
 }
 
+fn default_true() -> bool {
+    true
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
     pub(crate) listen_host: String,
@@ -129,16 +255,29 @@ pub struct Config {
     #[serde(default)]
     pub(crate) varspath: Option<String>,
     #[serde(default)]
+    pub(crate) library_roots: Vec<LibraryRoot>,
+    #[serde(default)]
     pub(crate) vampath: Option<String>,
     #[serde(default)]
     pub(crate) vam_exec: Option<String>,
     #[serde(default)]
+    pub(crate) vam_profiles: Vec<VamProfile>,
+    /// Profile used when a request or job does not pick one.
+    #[serde(default)]
+    pub(crate) active_profile: Option<String>,
+    #[serde(default)]
     pub(crate) downloader_save_path: Option<String>,
+    /// Index vars where they are and keep previews in the data dir; jobs that
+    /// would move or delete files under varspath are refused.
+    #[serde(default)]
+    pub(crate) read_only_library: bool,
     #[serde(default)]
     pub(crate) image_cache: ImageCacheConfig,
     #[serde(default)]
     pub(crate) download: DownloadConfig,
     #[serde(default)]
+    pub(crate) lan_cache: LanCacheConfig,
+    #[serde(default)]
     pub(crate) proxy_mode: ProxyMode,
     #[serde(default)]
     pub(crate) proxy: ProxyConfig,
@@ -156,11 +295,16 @@ impl Default for Config {
             log_level: "info".to_string(),
             job_concurrency: 10,
             varspath: None,
+            library_roots: Vec::new(),
             vampath: None,
             vam_exec: Some("VaM (Desktop Mode).bat".to_string()),
+            vam_profiles: Vec::new(),
+            active_profile: None,
             downloader_save_path: None,
+            read_only_library: false,
             image_cache: ImageCacheConfig::default(),
             download: DownloadConfig::default(),
+            lan_cache: LanCacheConfig::default(),
             proxy_mode: ProxyMode::System,
             proxy: ProxyConfig::default(),
             ui_theme: None,
@@ -177,9 +321,22 @@ pub struct AppState {
     pub(crate) job_counter: Arc<AtomicU64>,
     pub(crate) job_semaphore: Arc<RwLock<Arc<Semaphore>>>,
     pub(crate) job_tx: JobEventSender,
+    pub(crate) job_locks: Arc<JobLocks>,
+    pub(crate) job_logs: Arc<JobLogStore>,
     pub(crate) db_pool: SqlitePool,
     pub(crate) image_cache: Arc<crate::services::image_cache::ImageCacheService>,
     pub(crate) download_manager: Arc<crate::infra::download_manager::DownloadManager>,
+    /// VaM profile selected for this request or job; `None` uses
+    /// `Config.active_profile`.
+    pub(crate) profile: Option<String>,
+}
+
+impl AppState {
+    pub fn with_profile(&self, profile: Option<String>) -> Self {
+        let mut state = self.clone();
+        state.profile = profile;
+        state
+    }
 }
 
 pub fn init_logging(
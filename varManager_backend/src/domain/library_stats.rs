@@ -0,0 +1,421 @@
+//! Library breakdowns behind `GET /stats/library`.
+//!
+//! Everything is derived from `vars`, `dependencies` and the selected
+//! profile's `installStatus` rows. A dependency counts towards the var it
+//! names exactly, or towards the newest version for `Creator.Package.latest`.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub const DEFAULT_TOP: usize = 20;
+pub const MAX_TOP: usize = 500;
+
+/// Bucket size for the additions-over-time series.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrendPeriod {
+    Day,
+    Month,
+    Year,
+}
+
+impl TrendPeriod {
+    pub fn parse(raw: Option<&str>) -> Result<Self, String> {
+        match raw.map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("month") => Ok(Self::Month),
+            Some("day") => Ok(Self::Day),
+            Some("year") => Ok(Self::Year),
+            Some(other) => Err(format!("unsupported period: {}", other)),
+        }
+    }
+
+    /// Length of the `YYYY-MM-DD ...` prefix that names the bucket.
+    fn prefix_len(self) -> usize {
+        match self {
+            Self::Day => 10,
+            Self::Month => 7,
+            Self::Year => 4,
+        }
+    }
+}
+
+/// One row of `vars` with the profile's install state.
+#[derive(Clone, Debug, Default)]
+pub struct VarStatRow {
+    pub var_name: String,
+    pub creator_name: String,
+    pub package_name: String,
+    pub version: String,
+    pub var_date: Option<String>,
+    /// MB, as stored in `vars.fsize`.
+    pub fsize: f64,
+    pub installed: bool,
+    pub disabled: bool,
+    /// Content flags in `CONTENT_TYPES` order.
+    pub contents: [bool; CONTENT_TYPES.len()],
+}
+
+/// Content types broken down, with the `vars` column each one is read from.
+pub const CONTENT_TYPES: [(&str, &str); 6] = [
+    ("scene", "scene"),
+    ("look", "look"),
+    ("clothing", "cloth"),
+    ("hair", "hair"),
+    ("morph", "morph"),
+    ("plugin", "plugin"),
+];
+
+#[derive(Clone, Debug, Default, Serialize, JsonSchema, PartialEq)]
+pub struct CountSize {
+    pub count: u64,
+    pub size_mb: f64,
+}
+
+impl CountSize {
+    fn add(&mut self, size_mb: f64) {
+        self.count += 1;
+        self.size_mb += size_mb;
+    }
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct CreatorStat {
+    pub creator_name: String,
+    #[serde(flatten)]
+    pub totals: CountSize,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ContentTypeStat {
+    pub content_type: String,
+    #[serde(flatten)]
+    pub totals: CountSize,
+}
+
+#[derive(Clone, Debug, Default, Serialize, JsonSchema)]
+pub struct InstallStateStats {
+    /// Installed and enabled.
+    pub installed: CountSize,
+    pub disabled: CountSize,
+    pub not_installed: CountSize,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct PackageStat {
+    pub var_name: String,
+    pub size_mb: f64,
+    pub dependents: u64,
+    pub installed: bool,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct OrphanStats {
+    #[serde(flatten)]
+    pub totals: CountSize,
+    /// Largest orphans first, at most `top`.
+    pub items: Vec<PackageStat>,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct TrendPoint {
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD` of `varDate`.
+    pub period: String,
+    #[serde(flatten)]
+    pub added: CountSize,
+    /// Vars dated up to and including this period.
+    pub cumulative_count: u64,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct LibraryStats {
+    pub profile: String,
+    #[serde(flatten)]
+    pub totals: CountSize,
+    /// Largest total size first.
+    pub creators: Vec<CreatorStat>,
+    pub content_types: Vec<ContentTypeStat>,
+    pub install_state: InstallStateStats,
+    pub largest: Vec<PackageStat>,
+    pub most_depended: Vec<PackageStat>,
+    /// Not installed and nothing in the library depends on them.
+    pub orphans: OrphanStats,
+    /// Oldest first; vars without a `varDate` are left out.
+    pub additions: Vec<TrendPoint>,
+}
+
+pub async fn library_stats(
+    pool: &SqlitePool,
+    profile: &str,
+    top: usize,
+    period: TrendPeriod,
+) -> Result<LibraryStats, String> {
+    let mut select = String::from(
+        "SELECT v.varName, v.creatorName, v.packageName, v.version, v.varDate, \
+                COALESCE(v.fsize, 0), COALESCE(i.installed, 0), COALESCE(i.disabled, 0)",
+    );
+    for (_, column) in CONTENT_TYPES {
+        select.push_str(&format!(", COALESCE(v.{}, 0)", column));
+    }
+    select.push_str(
+        " FROM vars v LEFT JOIN installStatus i ON v.varName = i.varName AND i.profile = ?1",
+    );
+    let rows = sqlx::query(&select)
+        .bind(profile)
+        .fetch_all(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut vars = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut contents = [false; CONTENT_TYPES.len()];
+        for (idx, flag) in contents.iter_mut().enumerate() {
+            *flag = row.try_get::<i64, _>(8 + idx).map_err(|err| err.to_string())? > 0;
+        }
+        vars.push(VarStatRow {
+            var_name: row.try_get(0).map_err(|err| err.to_string())?,
+            creator_name: row
+                .try_get::<Option<String>, _>(1)
+                .map_err(|err| err.to_string())?
+                .unwrap_or_default(),
+            package_name: row
+                .try_get::<Option<String>, _>(2)
+                .map_err(|err| err.to_string())?
+                .unwrap_or_default(),
+            version: row
+                .try_get::<Option<String>, _>(3)
+                .map_err(|err| err.to_string())?
+                .unwrap_or_default(),
+            var_date: row.try_get(4).map_err(|err| err.to_string())?,
+            fsize: row.try_get(5).map_err(|err| err.to_string())?,
+            installed: row.try_get::<i64, _>(6).map_err(|err| err.to_string())? != 0,
+            disabled: row.try_get::<i64, _>(7).map_err(|err| err.to_string())? != 0,
+            contents,
+        });
+    }
+
+    let rows = sqlx::query(
+        "SELECT varName, dependency FROM dependencies \
+         WHERE varName IS NOT NULL AND dependency IS NOT NULL",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|err| err.to_string())?;
+    let mut dependencies = Vec::with_capacity(rows.len());
+    for row in rows {
+        let var_name: String = row.try_get(0).map_err(|err| err.to_string())?;
+        let dependency: String = row.try_get(1).map_err(|err| err.to_string())?;
+        dependencies.push((var_name, dependency));
+    }
+
+    Ok(compute(profile, &vars, &dependencies, top, period))
+}
+
+pub fn compute(
+    profile: &str,
+    vars: &[VarStatRow],
+    dependencies: &[(String, String)],
+    top: usize,
+    period: TrendPeriod,
+) -> LibraryStats {
+    let dependents = count_dependents(vars, dependencies);
+
+    let mut totals = CountSize::default();
+    let mut creators: HashMap<&str, CountSize> = HashMap::new();
+    let mut content_types = vec![CountSize::default(); CONTENT_TYPES.len()];
+    let mut install_state = InstallStateStats::default();
+    let mut additions: BTreeMap<String, CountSize> = BTreeMap::new();
+    let mut packages = Vec::with_capacity(vars.len());
+
+    for var in vars {
+        totals.add(var.fsize);
+        creators.entry(var.creator_name.as_str()).or_default().add(var.fsize);
+        for (idx, present) in var.contents.iter().enumerate() {
+            if *present {
+                content_types[idx].add(var.fsize);
+            }
+        }
+        let state = if !var.installed {
+            &mut install_state.not_installed
+        } else if var.disabled {
+            &mut install_state.disabled
+        } else {
+            &mut install_state.installed
+        };
+        state.add(var.fsize);
+        if let Some(bucket) = var
+            .var_date
+            .as_deref()
+            .and_then(|date| date.get(..period.prefix_len()))
+        {
+            additions.entry(bucket.to_string()).or_default().add(var.fsize);
+        }
+        packages.push(PackageStat {
+            var_name: var.var_name.clone(),
+            size_mb: var.fsize,
+            dependents: dependents.get(var.var_name.as_str()).copied().unwrap_or(0),
+            installed: var.installed,
+        });
+    }
+
+    let mut creators: Vec<CreatorStat> = creators
+        .into_iter()
+        .map(|(creator_name, totals)| CreatorStat {
+            creator_name: creator_name.to_string(),
+            totals,
+        })
+        .collect();
+    creators.sort_by(|a, b| {
+        b.totals
+            .size_mb
+            .total_cmp(&a.totals.size_mb)
+            .then_with(|| a.creator_name.cmp(&b.creator_name))
+    });
+
+    let content_types = CONTENT_TYPES
+        .iter()
+        .zip(content_types)
+        .map(|((name, _), totals)| ContentTypeStat {
+            content_type: name.to_string(),
+            totals,
+        })
+        .collect();
+
+    let by_size = |a: &PackageStat, b: &PackageStat| {
+        b.size_mb.total_cmp(&a.size_mb).then_with(|| a.var_name.cmp(&b.var_name))
+    };
+    packages.sort_by(by_size);
+    let largest = packages.iter().take(top).cloned().collect();
+
+    let mut orphan_totals = CountSize::default();
+    let mut orphans = Vec::new();
+    for package in packages.iter().filter(|p| !p.installed && p.dependents == 0) {
+        orphan_totals.add(package.size_mb);
+        if orphans.len() < top {
+            orphans.push(package.clone());
+        }
+    }
+
+    packages.retain(|p| p.dependents > 0);
+    packages.sort_by(|a, b| b.dependents.cmp(&a.dependents).then_with(|| by_size(a, b)));
+    packages.truncate(top);
+
+    let mut cumulative_count = 0;
+    let additions = additions
+        .into_iter()
+        .map(|(period, added)| {
+            cumulative_count += added.count;
+            TrendPoint {
+                period,
+                added,
+                cumulative_count,
+            }
+        })
+        .collect();
+
+    LibraryStats {
+        profile: profile.to_string(),
+        totals,
+        creators,
+        content_types,
+        install_state,
+        largest,
+        most_depended: packages,
+        orphans: OrphanStats {
+            totals: orphan_totals,
+            items: orphans,
+        },
+        additions,
+    }
+}
+
+/// Distinct library vars depending on each var, keyed by var name.
+fn count_dependents<'a>(
+    vars: &'a [VarStatRow],
+    dependencies: &[(String, String)],
+) -> HashMap<&'a str, u64> {
+    let mut by_name: HashMap<String, &'a str> = HashMap::with_capacity(vars.len());
+    let mut latest: HashMap<String, (i64, &'a str)> = HashMap::new();
+    for var in vars {
+        by_name.insert(var.var_name.to_ascii_lowercase(), var.var_name.as_str());
+        let base = format!("{}.{}", var.creator_name, var.package_name).to_ascii_lowercase();
+        let version = var.version.parse::<i64>().unwrap_or(0);
+        let entry = latest.entry(base).or_insert((version, var.var_name.as_str()));
+        if version > entry.0 {
+            *entry = (version, var.var_name.as_str());
+        }
+    }
+
+    let mut seen: HashSet<(&str, String)> = HashSet::new();
+    let mut counts: HashMap<&'a str, u64> = HashMap::new();
+    for (dependent, dependency) in dependencies {
+        let key = dependency.trim().to_ascii_lowercase();
+        let target = match by_name.get(&key) {
+            Some(target) => Some(*target),
+            None => key
+                .strip_suffix(".latest")
+                .and_then(|base| latest.get(base))
+                .map(|(_, name)| *name),
+        };
+        let Some(target) = target else {
+            continue;
+        };
+        if target.eq_ignore_ascii_case(dependent) {
+            continue;
+        }
+        if seen.insert((target, dependent.to_ascii_lowercase())) {
+            *counts.entry(target).or_default() += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, fsize: f64, installed: bool, date: &str) -> VarStatRow {
+        let parts: Vec<&str> = name.split('.').collect();
+        VarStatRow {
+            var_name: name.to_string(),
+            creator_name: parts[0].to_string(),
+            package_name: parts[1].to_string(),
+            version: parts[2].to_string(),
+            var_date: Some(date.to_string()),
+            fsize,
+            installed,
+            contents: [true, false, false, false, false, false],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_dependents_orphans_and_trends() {
+        let vars = vec![
+            var("A.Base.1", 10.0, false, "2024-01-05 10:00:00"),
+            var("A.Base.2", 20.0, false, "2024-02-01 10:00:00"),
+            var("B.Scene.1", 5.0, true, "2024-02-09 10:00:00"),
+            var("C.Lonely.1", 1.0, false, "2024-03-01 10:00:00"),
+        ];
+        let deps = vec![
+            ("B.Scene.1".to_string(), "A.Base.latest".to_string()),
+            ("B.Scene.1".to_string(), "A.Base.2".to_string()),
+            ("C.Lonely.1".to_string(), "A.Base.1".to_string()),
+        ];
+        let stats = compute("default", &vars, &deps, 10, TrendPeriod::Month);
+
+        assert_eq!(stats.totals.count, 4);
+        assert_eq!(stats.install_state.installed.count, 1);
+        assert_eq!(stats.most_depended[0].var_name, "A.Base.2");
+        assert_eq!(stats.most_depended[0].dependents, 1);
+        let orphans: Vec<&str> =
+            stats.orphans.items.iter().map(|p| p.var_name.as_str()).collect();
+        assert_eq!(orphans, ["C.Lonely.1"]);
+        let periods: Vec<(&str, u64)> = stats
+            .additions
+            .iter()
+            .map(|p| (p.period.as_str(), p.cumulative_count))
+            .collect();
+        assert_eq!(periods, [("2024-01", 1), ("2024-02", 3), ("2024-03", 4)]);
+        assert_eq!(stats.content_types[0].totals.count, 4);
+    }
+}
@@ -1 +1,3 @@
+pub mod library_stats;
+pub mod providers;
 pub mod var_logic;
@@ -0,0 +1,292 @@
+//! Reverse lookup from a resource path to the packages that ship it.
+//!
+//! Scenes reference files as `Creator.Package.Version:/Custom/...`,
+//! `SELF:/Custom/...` or a bare `Custom/...` path. The lookup matches the path
+//! against the archive entry index and ranks the packages that contain it:
+//! the referenced var itself, then the version `resolve_var_exist_name` falls
+//! back to, then other versions of the same package, then anything else.
+
+use crate::domain::var_logic::resolve_var_exist_name;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{Row, SqlitePool};
+use std::collections::HashSet;
+
+const MAX_CANDIDATES: usize = 50;
+
+/// A file reference taken from a scene or given by the caller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceRef {
+    pub raw: String,
+    /// Package named before `:/`; `None` for `SELF:/` and bare paths.
+    pub var_ref: Option<String>,
+    /// Path inside the package, without a leading `/`.
+    pub path: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderMatch {
+    /// The referenced var itself.
+    Exact,
+    /// The var the reference resolves to (`latest` or the closest version).
+    Resolved,
+    /// Another version of the referenced package.
+    OtherVersion,
+    /// A package from another creator or with another name.
+    OtherPackage,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ProviderCandidate {
+    pub var_name: String,
+    #[serde(rename = "match")]
+    pub match_kind: ProviderMatch,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ProviderLookup {
+    pub reference: String,
+    pub var_ref: Option<String>,
+    pub path: String,
+    /// `resolve_var_exist_name` of `var_ref`: a var name, a name ending in
+    /// `$` for the closest version, or `missing`.
+    pub resolved: Option<String>,
+    pub candidates: Vec<ProviderCandidate>,
+}
+
+/// Lookups for a set of references.
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ProvidersReport {
+    pub total: usize,
+    /// References with at least one candidate.
+    pub found: usize,
+    pub missing: usize,
+    pub references: Vec<ProviderLookup>,
+}
+
+/// References from explicit paths followed by those found in a scene.
+/// Errors if neither yields a file reference.
+pub fn gather_refs(paths: &[String], scene: Option<&Value>) -> Result<Vec<ResourceRef>, String> {
+    let mut refs: Vec<ResourceRef> = Vec::new();
+    for path in paths {
+        let resource = parse_resource_ref(path)
+            .ok_or_else(|| format!("not a resource path: {}", path))?;
+        if !refs.contains(&resource) {
+            refs.push(resource);
+        }
+    }
+    if let Some(scene) = scene {
+        for resource in collect_scene_refs(scene) {
+            if !refs.contains(&resource) {
+                refs.push(resource);
+            }
+        }
+    }
+    if refs.is_empty() {
+        return Err("no resource paths to look up".to_string());
+    }
+    Ok(refs)
+}
+
+pub fn summarize(references: Vec<ProviderLookup>) -> ProvidersReport {
+    let found = references
+        .iter()
+        .filter(|lookup| !lookup.candidates.is_empty())
+        .count();
+    ProvidersReport {
+        total: references.len(),
+        found,
+        missing: references.len() - found,
+        references,
+    }
+}
+
+/// Parse one reference. Returns `None` for strings without a file path.
+pub fn parse_resource_ref(raw: &str) -> Option<ResourceRef> {
+    let trimmed = raw.trim();
+    let (var_ref, path) = match trimmed.split_once(":/") {
+        Some((prefix, path)) => {
+            let var_ref = if prefix.eq_ignore_ascii_case("SELF") {
+                None
+            } else if prefix.split('.').count() == 3 && !prefix.contains('/') {
+                Some(prefix.to_string())
+            } else {
+                return None;
+            };
+            (var_ref, path)
+        }
+        None => (None, trimmed),
+    };
+    let path = path.replace('\\', "/").trim_start_matches('/').to_string();
+    let file_name = path.rsplit('/').next().unwrap_or("");
+    if path.is_empty() || !file_name.contains('.') {
+        return None;
+    }
+    Some(ResourceRef {
+        raw: trimmed.to_string(),
+        var_ref,
+        path,
+    })
+}
+
+/// Every package or `Custom/` / `Saves/` file reference in a scene, in order
+/// of first appearance.
+pub fn collect_scene_refs(scene: &Value) -> Vec<ResourceRef> {
+    let mut refs = Vec::new();
+    let mut seen = HashSet::new();
+    walk_scene(scene, &mut refs, &mut seen);
+    refs
+}
+
+fn walk_scene(value: &Value, refs: &mut Vec<ResourceRef>, seen: &mut HashSet<String>) {
+    match value {
+        Value::String(text) => {
+            if !looks_like_resource(text) {
+                return;
+            }
+            if let Some(resource) = parse_resource_ref(text) {
+                if seen.insert(resource.raw.clone()) {
+                    refs.push(resource);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                walk_scene(item, refs, seen);
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values() {
+                walk_scene(item, refs, seen);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn looks_like_resource(text: &str) -> bool {
+    if text.contains(":/") {
+        return true;
+    }
+    let lower = text.trim_start_matches('/').to_ascii_lowercase();
+    lower.starts_with("custom/") || lower.starts_with("saves/")
+}
+
+/// Packages that contain `resource.path`, best match first.
+pub async fn find_providers(
+    pool: &SqlitePool,
+    resource: &ResourceRef,
+) -> Result<ProviderLookup, String> {
+    let resolved = match resource.var_ref.as_deref() {
+        Some(var_ref) => Some(resolve_var_exist_name(pool, var_ref).await?),
+        None => None,
+    };
+    let resolved_name = resolved
+        .as_deref()
+        .filter(|name| *name != "missing")
+        .map(|name| name.trim_end_matches('$'));
+    let package_key = resource
+        .var_ref
+        .as_deref()
+        .and_then(|var_ref| var_ref.rsplit_once('.'))
+        .map(|(key, _)| key.to_ascii_lowercase());
+
+    let rows = sqlx::query(
+        "SELECT DISTINCT varName FROM entries WHERE path = ?1 COLLATE NOCASE",
+    )
+    .bind(&resource.path)
+    .fetch_all(pool)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut candidates = Vec::with_capacity(rows.len());
+    for row in rows {
+        let var_name: String = row.try_get(0).map_err(|err| err.to_string())?;
+        let match_kind = if resource
+            .var_ref
+            .as_deref()
+            .is_some_and(|var_ref| var_ref.eq_ignore_ascii_case(&var_name))
+        {
+            ProviderMatch::Exact
+        } else if resolved_name.is_some_and(|name| name.eq_ignore_ascii_case(&var_name)) {
+            ProviderMatch::Resolved
+        } else if package_key.as_deref().is_some_and(|key| {
+            var_name
+                .rsplit_once('.')
+                .is_some_and(|(candidate, _)| candidate.eq_ignore_ascii_case(key))
+        }) {
+            ProviderMatch::OtherVersion
+        } else {
+            ProviderMatch::OtherPackage
+        };
+        candidates.push(ProviderCandidate {
+            var_name,
+            match_kind,
+        });
+    }
+    candidates.sort_by(|a, b| {
+        a.match_kind
+            .cmp(&b.match_kind)
+            .then_with(|| version_of(&b.var_name).cmp(&version_of(&a.var_name)))
+            .then_with(|| a.var_name.cmp(&b.var_name))
+    });
+    candidates.truncate(MAX_CANDIDATES);
+
+    Ok(ProviderLookup {
+        reference: resource.raw.clone(),
+        var_ref: resource.var_ref.clone(),
+        path: resource.path.clone(),
+        resolved,
+        candidates,
+    })
+}
+
+fn version_of(var_name: &str) -> i64 {
+    var_name
+        .rsplit_once('.')
+        .and_then(|(_, version)| version.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_reference_forms() {
+        let packaged = parse_resource_ref("Bob.Hair.3:/Custom/Hair/Female/Bob/wavy.vam").unwrap();
+        assert_eq!(packaged.var_ref.as_deref(), Some("Bob.Hair.3"));
+        assert_eq!(packaged.path, "Custom/Hair/Female/Bob/wavy.vam");
+
+        let own = parse_resource_ref("SELF:/Custom/Sounds/a.wav").unwrap();
+        assert_eq!(own.var_ref, None);
+        assert_eq!(own.path, "Custom/Sounds/a.wav");
+
+        assert!(parse_resource_ref("Custom/Atom/Person").is_none());
+        assert!(parse_resource_ref("http://example.com/a.jpg").is_none());
+    }
+
+    #[test]
+    fn collects_scene_refs_once() {
+        let scene = json!({
+            "atoms": [{
+                "id": "Person",
+                "storables": [
+                    { "id": "hair", "url": "Bob.Hair.3:/Custom/Hair/Female/Bob/wavy.vam" },
+                    { "id": "skin", "texture": "Custom/Atom/Person/Textures/a.jpg" },
+                    { "id": "again", "url": "Bob.Hair.3:/Custom/Hair/Female/Bob/wavy.vam" },
+                    { "id": "name", "value": "Custom" },
+                ],
+            }],
+        });
+        let refs = collect_scene_refs(&scene);
+        let paths: Vec<&str> = refs.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["Custom/Hair/Female/Bob/wavy.vam", "Custom/Atom/Person/Textures/a.jpg"]
+        );
+    }
+}
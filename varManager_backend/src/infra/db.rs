@@ -1,12 +1,14 @@
 use crate::app::data_dir;
+use crate::infra::migrations;
 use sqlx::{
     sqlite::{SqliteConnectOptions, SqlitePoolOptions},
     Row, Sqlite, SqlitePool, Transaction,
 };
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct VarRecord {
     pub var_name: String,
     pub creator_name: Option<String>,
@@ -30,6 +32,10 @@ pub struct VarRecord {
     pub appearance: Option<i64>,
     pub dependency_cnt: Option<i64>,
     pub fsize: Option<f64>,
+    /// Library root holding the file.
+    pub var_root: Option<String>,
+    /// File location relative to `var_root`.
+    pub var_path: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -37,6 +43,8 @@ pub struct VarScanInfo {
     pub var_name: String,
     pub var_date: Option<String>,
     pub fsize: Option<f64>,
+    pub var_root: Option<String>,
+    pub var_path: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -78,142 +86,7 @@ pub async fn open_default_pool() -> Result<SqlitePool, String> {
 }
 
 pub async fn ensure_schema(pool: &SqlitePool) -> Result<(), String> {
-    let hide_fav_needs_migration = {
-        let rows = sqlx::query("PRAGMA table_info(HideFav)")
-            .fetch_all(pool)
-            .await
-            .map_err(|err| err.to_string())?;
-        if rows.is_empty() {
-            false
-        } else {
-            !rows.iter().any(|row| {
-                let name: String = row.try_get("name").unwrap_or_default();
-                name.eq_ignore_ascii_case("scenePath")
-            })
-        }
-    };
-    if hide_fav_needs_migration {
-        sqlx::query("DROP TABLE IF EXISTS HideFav")
-            .execute(pool)
-            .await
-            .map_err(|err| err.to_string())?;
-    }
-
-    sqlx::query(
-        r#"
-                CREATE TABLE IF NOT EXISTS dependencies (
-                    ID INTEGER PRIMARY KEY AUTOINCREMENT,
-                    varName TEXT,
-                    dependency TEXT
-                );
-                CREATE TABLE IF NOT EXISTS HideFav (
-                    varName TEXT NOT NULL,
-                    scenePath TEXT NOT NULL,
-                    hide INTEGER NOT NULL,
-                    fav INTEGER NOT NULL,
-                    PRIMARY KEY (varName, scenePath)
-                );
-                CREATE TABLE IF NOT EXISTS installStatus (
-                    varName TEXT PRIMARY KEY,
-                    installed INTEGER NOT NULL,
-                    disabled INTEGER NOT NULL
-                );
-                CREATE TABLE IF NOT EXISTS savedepens (
-                    ID INTEGER PRIMARY KEY AUTOINCREMENT,
-                    varName TEXT,
-                    dependency TEXT,
-                    SavePath TEXT,
-                    ModiDate TEXT
-                );
-                CREATE TABLE IF NOT EXISTS scenes (
-                    ID INTEGER PRIMARY KEY AUTOINCREMENT,
-                    varName TEXT,
-                    atomType TEXT,
-                    previewPic TEXT,
-                    scenePath TEXT,
-                    isPreset INTEGER NOT NULL,
-                    isLoadable INTEGER NOT NULL
-                );
-                CREATE TABLE IF NOT EXISTS vars (
-                    varName TEXT PRIMARY KEY,
-                    creatorName TEXT,
-                    packageName TEXT,
-                    metaDate TEXT,
-                    varDate TEXT,
-                    version TEXT,
-                    description TEXT,
-                    morph INTEGER,
-                    cloth INTEGER,
-                    hair INTEGER,
-                    skin INTEGER,
-                    pose INTEGER,
-                    scene INTEGER,
-                    script INTEGER,
-                    plugin INTEGER,
-                    asset INTEGER,
-                    texture INTEGER,
-                    look INTEGER,
-                    subScene INTEGER,
-                    appearance INTEGER,
-                    dependencyCnt INTEGER,
-                    fsize REAL
-                );
-                CREATE TABLE IF NOT EXISTS image_cache_entries (
-                    cache_key TEXT PRIMARY KEY,
-                    file_name TEXT NOT NULL,
-                    source_type TEXT NOT NULL,
-                    source_url TEXT,
-                    source_root TEXT,
-                    source_path TEXT,
-                    size_bytes INTEGER NOT NULL,
-                    content_type TEXT NOT NULL,
-                    created_at INTEGER NOT NULL,
-                    last_accessed INTEGER NOT NULL,
-                    access_count INTEGER NOT NULL
-                );
-                CREATE TABLE IF NOT EXISTS downloads (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    url TEXT NOT NULL,
-                    name TEXT,
-                    status TEXT NOT NULL,
-                    downloaded_bytes INTEGER NOT NULL DEFAULT 0,
-                    total_bytes INTEGER,
-                    speed_bytes INTEGER NOT NULL DEFAULT 0,
-                    error TEXT,
-                    save_path TEXT,
-                    temp_path TEXT,
-                    created_at INTEGER NOT NULL,
-                    updated_at INTEGER NOT NULL
-                );
-
-                CREATE INDEX IF NOT EXISTS idx_vars_creatorName ON vars(creatorName);
-                CREATE INDEX IF NOT EXISTS idx_vars_packageName ON vars(packageName);
-                CREATE INDEX IF NOT EXISTS idx_vars_metaDate ON vars(metaDate);
-                CREATE INDEX IF NOT EXISTS idx_vars_varDate ON vars(varDate);
-                CREATE INDEX IF NOT EXISTS idx_vars_fsize ON vars(fsize);
-                CREATE INDEX IF NOT EXISTS idx_vars_dependencyCnt ON vars(dependencyCnt);
-                CREATE INDEX IF NOT EXISTS idx_scenes_varName ON scenes(varName);
-                CREATE INDEX IF NOT EXISTS idx_scenes_atomType ON scenes(atomType);
-                CREATE INDEX IF NOT EXISTS idx_dependencies_varName ON dependencies(varName);
-                CREATE INDEX IF NOT EXISTS idx_dependencies_dependency ON dependencies(dependency);
-                CREATE INDEX IF NOT EXISTS idx_savedepens_dependency ON savedepens(dependency);
-                CREATE INDEX IF NOT EXISTS idx_image_cache_last_accessed ON image_cache_entries(last_accessed);
-                CREATE INDEX IF NOT EXISTS idx_downloads_status ON downloads(status);
-                CREATE INDEX IF NOT EXISTS idx_downloads_created_at ON downloads(created_at);
-                "#
-    )
-    .execute(pool)
-    .await
-    .map_err(|err| err.to_string())?;
-
-    let _ = sqlx::query("ALTER TABLE vars ADD COLUMN fsize REAL")
-        .execute(pool)
-        .await;
-    let _ = sqlx::query("ALTER TABLE downloads ADD COLUMN temp_path TEXT")
-        .execute(pool)
-        .await;
-
-    Ok(())
+    migrations::migrate(pool, &data_dir().join(migrations::DB_BACKUP_DIR)).await
 }
 
 pub async fn var_exists_conn(pool: &SqlitePool, var_name: &str) -> Result<bool, String> {
@@ -266,12 +139,14 @@ pub async fn list_dependencies_all(pool: &SqlitePool) -> Result<Vec<String>, Str
 
 pub async fn list_dependencies_for_installed(
     pool: &SqlitePool,
+    profile: &str,
 ) -> Result<Vec<String>, String> {
     let rows = sqlx::query(
         "SELECT d.dependency FROM dependencies d \
-             JOIN installStatus i ON d.varName = i.varName \
+             JOIN installStatus i ON d.varName = i.varName AND i.profile = ?1 \
              WHERE i.installed = 1",
     )
+    .bind(profile)
     .fetch_all(pool)
     .await
     .map_err(|err| err.to_string())?;
@@ -317,13 +192,16 @@ pub async fn list_dependencies_for_vars(
 
 pub async fn upsert_install_status(
     pool: &SqlitePool,
+    profile: &str,
     var_name: &str,
     installed: bool,
     disabled: bool,
 ) -> Result<(), String> {
     sqlx::query(
-        "INSERT OR REPLACE INTO installStatus (varName, installed, disabled) VALUES (?1, ?2, ?3)",
+        "INSERT OR REPLACE INTO installStatus (profile, varName, installed, disabled) \
+         VALUES (?1, ?2, ?3, ?4)",
     )
+    .bind(profile)
     .bind(var_name)
     .bind(installed as i64)
     .bind(disabled as i64)
@@ -333,21 +211,47 @@ pub async fn upsert_install_status(
     Ok(())
 }
 
+/// Insert or update a var row. An update keeps the row's rowid, which is
+/// also the rowid of its `vars_fts` row.
 pub async fn upsert_var(
     tx: &mut Transaction<'_, Sqlite>,
     record: &VarRecord,
 ) -> Result<(), String> {
     sqlx::query(
         r#"
-        INSERT OR REPLACE INTO vars (
+        INSERT INTO vars (
             varName, creatorName, packageName, metaDate, varDate, version, description,
             morph, cloth, hair, skin, pose, scene, script, plugin, asset, texture,
-            look, subScene, appearance, dependencyCnt, fsize
+            look, subScene, appearance, dependencyCnt, fsize, varRoot, varPath
         ) VALUES (
             ?1, ?2, ?3, ?4, ?5, ?6, ?7,
             ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
-            ?18, ?19, ?20, ?21, ?22
+            ?18, ?19, ?20, ?21, ?22, ?23, ?24
         )
+        ON CONFLICT(varName) DO UPDATE SET
+            creatorName = excluded.creatorName,
+            packageName = excluded.packageName,
+            metaDate = excluded.metaDate,
+            varDate = excluded.varDate,
+            version = excluded.version,
+            description = excluded.description,
+            morph = excluded.morph,
+            cloth = excluded.cloth,
+            hair = excluded.hair,
+            skin = excluded.skin,
+            pose = excluded.pose,
+            scene = excluded.scene,
+            script = excluded.script,
+            plugin = excluded.plugin,
+            asset = excluded.asset,
+            texture = excluded.texture,
+            look = excluded.look,
+            subScene = excluded.subScene,
+            appearance = excluded.appearance,
+            dependencyCnt = excluded.dependencyCnt,
+            fsize = excluded.fsize,
+            varRoot = excluded.varRoot,
+            varPath = excluded.varPath
         "#,
     )
     .bind(&record.var_name)
@@ -372,6 +276,8 @@ pub async fn upsert_var(
     .bind(record.appearance)
     .bind(record.dependency_cnt)
     .bind(record.fsize)
+    .bind(&record.var_root)
+    .bind(&record.var_path)
     .execute(tx.as_mut())
     .await
     .map_err(|err| err.to_string())?;
@@ -435,10 +341,12 @@ pub async fn replace_scenes(
 
 pub async fn replace_hide_fav(
     tx: &mut Transaction<'_, Sqlite>,
+    profile: &str,
     var_name: &str,
     entries: &[HideFavRecord],
 ) -> Result<(), String> {
-    sqlx::query("DELETE FROM HideFav WHERE varName = ?1")
+    sqlx::query("DELETE FROM HideFav WHERE profile = ?1 AND varName = ?2")
+        .bind(profile)
         .bind(var_name)
         .execute(tx.as_mut())
         .await
@@ -448,8 +356,10 @@ pub async fn replace_hide_fav(
             continue;
         }
         sqlx::query(
-            "INSERT INTO HideFav (varName, scenePath, hide, fav) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO HideFav (profile, varName, scenePath, hide, fav) \
+             VALUES (?1, ?2, ?3, ?4, ?5)",
         )
+        .bind(profile)
         .bind(var_name)
         .bind(&entry.scene_path)
         .bind(if entry.hide { 1 } else { 0 })
@@ -476,7 +386,7 @@ pub async fn list_vars(tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<String>,
 pub async fn list_var_scan_info(
     tx: &mut Transaction<'_, Sqlite>,
 ) -> Result<Vec<VarScanInfo>, String> {
-    let rows = sqlx::query("SELECT varName, varDate, fsize FROM vars")
+    let rows = sqlx::query("SELECT varName, varDate, fsize, varRoot, varPath FROM vars")
         .fetch_all(tx.as_mut())
         .await
         .map_err(|err| err.to_string())?;
@@ -486,11 +396,50 @@ pub async fn list_var_scan_info(
             var_name: row.try_get(0).map_err(|err| err.to_string())?,
             var_date: row.try_get(1).map_err(|err| err.to_string())?,
             fsize: row.try_get(2).map_err(|err| err.to_string())?,
+            var_root: row.try_get(3).map_err(|err| err.to_string())?,
+            var_path: row.try_get(4).map_err(|err| err.to_string())?,
         });
     }
     Ok(infos)
 }
 
+pub async fn set_var_location(
+    tx: &mut Transaction<'_, Sqlite>,
+    var_name: &str,
+    var_root: &str,
+    var_path: &str,
+) -> Result<(), String> {
+    sqlx::query("UPDATE vars SET varRoot = ?2, varPath = ?3 WHERE varName = ?1")
+        .bind(var_name)
+        .bind(var_root)
+        .bind(var_path)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Recorded file locations keyed by var name. Rows without a root are
+/// relative to varspath.
+pub async fn list_var_locations(pool: &SqlitePool) -> Result<HashMap<String, PathBuf>, String> {
+    let rows = sqlx::query("SELECT varName, varRoot, varPath FROM vars WHERE varPath IS NOT NULL")
+        .fetch_all(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut locations = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let var_name: String = row.try_get(0).map_err(|err| err.to_string())?;
+        let var_root: Option<String> = row.try_get(1).map_err(|err| err.to_string())?;
+        let var_path: String = row.try_get(2).map_err(|err| err.to_string())?;
+        let location = match var_root {
+            Some(root) => PathBuf::from(root).join(var_path),
+            None => PathBuf::from(var_path),
+        };
+        locations.insert(var_name, location);
+    }
+    Ok(locations)
+}
+
 pub async fn list_scenes_for_var(
     tx: &mut Transaction<'_, Sqlite>,
     var_name: &str,
@@ -520,6 +469,7 @@ pub async fn delete_var_related(
     tx: &mut Transaction<'_, Sqlite>,
     var_name: &str,
 ) -> Result<(), String> {
+    delete_search_index(tx, var_name).await?;
     sqlx::query("DELETE FROM dependencies WHERE varName = ?1")
         .bind(var_name)
         .execute(tx.as_mut())
@@ -542,6 +492,16 @@ pub async fn delete_var_related_conn(
     pool: &SqlitePool,
     var_name: &str,
 ) -> Result<(), String> {
+    sqlx::query(VARS_FTS_DELETE)
+        .bind(var_name)
+        .execute(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    sqlx::query("DELETE FROM entries WHERE varName = ?1")
+        .bind(var_name)
+        .execute(pool)
+        .await
+        .map_err(|err| err.to_string())?;
     sqlx::query("DELETE FROM dependencies WHERE varName = ?1")
         .bind(var_name)
         .execute(pool)
@@ -559,3 +519,318 @@ pub async fn delete_var_related_conn(
         .map_err(|err| err.to_string())?;
     Ok(())
 }
+
+/// Vars whose search index and entry rows are complete. Entries written
+/// before sizes were recorded do not count.
+pub async fn list_search_indexed(
+    tx: &mut Transaction<'_, Sqlite>,
+) -> Result<HashSet<String>, String> {
+    let rows = sqlx::query(
+        "SELECT varName FROM vars_fts \
+         WHERE varName NOT IN (SELECT DISTINCT varName FROM entries WHERE size IS NULL)",
+    )
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut names = HashSet::new();
+    for row in rows {
+        names.insert(row.try_get::<String, _>(0).map_err(|err| err.to_string())?);
+    }
+    Ok(names)
+}
+
+/// `vars_fts` rows share the rowid of their `vars` row, so they can be
+/// found without scanning the FTS table. Run before the `vars` row goes.
+const VARS_FTS_DELETE: &str =
+    "DELETE FROM vars_fts WHERE rowid IN (SELECT rowid FROM vars WHERE varName = ?1)";
+
+/// Needs the var's `vars` row; see [`upsert_var`].
+pub async fn replace_search_index(
+    tx: &mut Transaction<'_, Sqlite>,
+    record: &VarRecord,
+    tags: &str,
+) -> Result<(), String> {
+    sqlx::query(VARS_FTS_DELETE)
+        .bind(&record.var_name)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|err| err.to_string())?;
+    sqlx::query(
+        "INSERT INTO vars_fts (rowid, varName, creatorName, packageName, description, tags) \
+         SELECT rowid, ?1, ?2, ?3, ?4, ?5 FROM vars WHERE varName = ?1",
+    )
+    .bind(&record.var_name)
+    .bind(&record.creator_name)
+    .bind(&record.package_name)
+    .bind(&record.description)
+    .bind(tags)
+    .execute(tx.as_mut())
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+pub async fn replace_entries(
+    tx: &mut Transaction<'_, Sqlite>,
+    var_name: &str,
+    entries: &[EntryRecord],
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM entries WHERE varName = ?1")
+        .bind(var_name)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|err| err.to_string())?;
+    for entry in entries {
+        sqlx::query(
+            "INSERT INTO entries (varName, path, size, compressedSize, crc, contentType) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(var_name)
+        .bind(&entry.path)
+        .bind(entry.size)
+        .bind(entry.compressed_size)
+        .bind(entry.crc)
+        .bind(&entry.content_type)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+async fn delete_search_index(
+    tx: &mut Transaction<'_, Sqlite>,
+    var_name: &str,
+) -> Result<(), String> {
+    sqlx::query(VARS_FTS_DELETE)
+        .bind(var_name)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|err| err.to_string())?;
+    sqlx::query("DELETE FROM entries WHERE varName = ?1")
+        .bind(var_name)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
+pub struct EntryRecord {
+    pub path: String,
+    pub size: i64,
+    pub compressed_size: i64,
+    pub crc: i64,
+    pub content_type: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct VarSearchHit {
+    pub var_name: String,
+    pub score: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct EntrySearchHit {
+    pub var_name: String,
+    pub path: String,
+    pub atom_type: Option<String>,
+    pub score: f64,
+}
+
+/// Turn user input into an FTS5 query: every whitespace-separated term must
+/// match, punctuation inside a term (`Custom/Atom`) becomes a phrase, and the
+/// last token of each term matches as a prefix.
+pub fn fts_query(raw: &str) -> Option<String> {
+    let terms: Vec<String> = raw
+        .split_whitespace()
+        .filter_map(|term| {
+            let tokens: Vec<&str> = term
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|token| !token.is_empty())
+                .collect();
+            if tokens.is_empty() {
+                None
+            } else {
+                Some(format!("\"{}\"*", tokens.join(" ")))
+            }
+        })
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Var-level hits, best first. Creator and package weigh more than the
+/// description and tags.
+pub async fn search_vars(
+    pool: &SqlitePool,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<VarSearchHit>, String> {
+    let rows = sqlx::query(
+        "SELECT varName, bm25(vars_fts, 0.0, 10.0, 10.0, 2.0, 4.0) AS score \
+         FROM vars_fts WHERE vars_fts MATCH ?1 ORDER BY score LIMIT ?2",
+    )
+    .bind(query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|err| err.to_string())?;
+    let mut hits = Vec::with_capacity(rows.len());
+    for row in rows {
+        hits.push(VarSearchHit {
+            var_name: row.try_get(0).map_err(|err| err.to_string())?,
+            score: row.try_get(1).map_err(|err| err.to_string())?,
+        });
+    }
+    Ok(hits)
+}
+
+/// Archive entry hits, best first, with the scene record type when the entry
+/// is a scene or preset.
+pub async fn search_entries(
+    pool: &SqlitePool,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<EntrySearchHit>, String> {
+    let rows = sqlx::query(
+        "SELECT e.varName, e.path, s.atomType, bm25(entries_fts) AS score \
+         FROM entries_fts \
+         JOIN entries e ON e.id = entries_fts.rowid \
+         LEFT JOIN scenes s ON s.varName = e.varName AND s.scenePath = e.path \
+         WHERE entries_fts MATCH ?1 ORDER BY score LIMIT ?2",
+    )
+    .bind(query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|err| err.to_string())?;
+    let mut hits = Vec::with_capacity(rows.len());
+    for row in rows {
+        hits.push(EntrySearchHit {
+            var_name: row.try_get(0).map_err(|err| err.to_string())?,
+            path: row.try_get(1).map_err(|err| err.to_string())?,
+            atom_type: row.try_get(2).map_err(|err| err.to_string())?,
+            score: row.try_get(3).map_err(|err| err.to_string())?,
+        });
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fts_query_quotes_every_term() {
+        assert_eq!(fts_query(""), None);
+        assert_eq!(fts_query("  -- / * \"\" "), None);
+        assert_eq!(fts_query("chair"), Some("\"chair\"*".to_string()));
+        assert_eq!(
+            fts_query("Custom/Atom  wood-chair"),
+            Some("\"Custom Atom\"* \"wood chair\"*".to_string())
+        );
+        assert_eq!(
+            fts_query("\"red dress\""),
+            Some("\"red\"* \"dress\"*".to_string())
+        );
+        assert_eq!(
+            fts_query("a AND b OR NOT c"),
+            Some("\"a\"* \"AND\"* \"b\"* \"OR\"* \"NOT\"* \"c\"*".to_string())
+        );
+        assert_eq!(
+            fts_query("NEAR(hair skin, 2) tag:* x^"),
+            Some("\"NEAR hair\"* \"skin\"* \"2\"* \"tag\"* \"x\"*".to_string())
+        );
+        assert_eq!(fts_query("Künstler.Haar"), Some("\"Künstler Haar\"*".to_string()));
+    }
+
+    #[tokio::test]
+    async fn search_rows_follow_their_var_row() {
+        let dir = std::env::temp_dir().join(format!("vm_fts_rowid_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.join("varManager.db"))
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        migrations::migrate(&pool, &dir.join(migrations::DB_BACKUP_DIR))
+            .await
+            .unwrap();
+
+        let mut record = VarRecord {
+            var_name: "Acme.Chair.1".to_string(),
+            creator_name: Some("Acme".to_string()),
+            package_name: Some("Chair".to_string()),
+            description: Some("oak".to_string()),
+            ..VarRecord::default()
+        };
+        for description in ["oak", "walnut"] {
+            record.description = Some(description.to_string());
+            let mut tx = pool.begin().await.unwrap();
+            upsert_var(&mut tx, &record).await.unwrap();
+            replace_search_index(&mut tx, &record, "").await.unwrap();
+            tx.commit().await.unwrap();
+        }
+        let rows: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT f.rowid, f.description FROM vars_fts f \
+             JOIN vars v ON v.rowid = f.rowid AND v.varName = f.varName",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].1, "walnut");
+        let hits = search_vars(&pool, &fts_query("oak").unwrap(), 10).await.unwrap();
+        assert!(hits.is_empty());
+
+        let mut tx = pool.begin().await.unwrap();
+        delete_var_related(&mut tx, "Acme.Chair.1").await.unwrap();
+        tx.commit().await.unwrap();
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(1) FROM vars_fts")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+        pool.close().await;
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn fts_queries_are_valid_match_syntax() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE VIRTUAL TABLE t USING fts5(body);
+             INSERT INTO t (body) VALUES ('Acme wood chair Custom/Atom AND');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        for raw in ["wood ch", "a AND b", "NEAR(x y)", "x OR", "\"unbalanced", "atom/cust"] {
+            let query = fts_query(raw).unwrap();
+            let found: Result<Vec<i64>, _> =
+                sqlx::query_scalar("SELECT rowid FROM t WHERE t MATCH ?1")
+                    .bind(&query)
+                    .fetch_all(&pool)
+                    .await;
+            assert!(found.is_ok(), "{} -> {}", raw, query);
+        }
+        let hits: Vec<i64> = sqlx::query_scalar("SELECT rowid FROM t WHERE t MATCH ?1")
+            .bind(fts_query("custom/at wood").unwrap())
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(hits, vec![1]);
+    }
+}
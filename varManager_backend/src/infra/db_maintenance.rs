@@ -0,0 +1,340 @@
+//! Backup, restore and integrity checks for `varManager.db`.
+//!
+//! Backups and restores go through SQLite's online backup API on one of the
+//! pool's connections, so the live database is never copied or replaced at
+//! the file level while the pool has it open. Backups land in
+//! `data_dir/db_backups/` next to the ones taken before migrations.
+
+use crate::app::data_dir;
+use crate::infra::migrations::{self, DB_BACKUP_DIR};
+use libsqlite3_sys as ffi;
+use schemars::JsonSchema;
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Row, SqlitePool};
+use std::ffi::{CStr, CString, OsStr};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+/// Pages copied per backup step.
+const BACKUP_STEP_PAGES: i32 = 256;
+/// How often a step may hit a locked database before the copy gives up.
+const BACKUP_BUSY_RETRIES: u32 = 200;
+const BACKUP_BUSY_SLEEP_MS: i32 = 50;
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct DbBackupInfo {
+    pub file: String,
+    pub size_bytes: u64,
+    /// Unix seconds.
+    pub modified: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct DbCheckReport {
+    pub ok: bool,
+    /// `PRAGMA integrity_check` output; `["ok"]` when the file is sound.
+    pub integrity: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+    pub schema_version: i64,
+}
+
+pub fn backup_dir() -> PathBuf {
+    data_dir().join(DB_BACKUP_DIR)
+}
+
+/// Backups in `backup_dir()`, newest first.
+pub fn list_backups() -> Result<Vec<DbBackupInfo>, String> {
+    let dir = backup_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|err| err.to_string())? {
+        let entry = entry.map_err(|err| err.to_string())?;
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("db") {
+            continue;
+        }
+        let meta = entry.metadata().map_err(|err| err.to_string())?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64);
+        backups.push(DbBackupInfo {
+            file: entry.file_name().to_string_lossy().to_string(),
+            size_bytes: meta.len(),
+            modified,
+        });
+    }
+    backups.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| b.file.cmp(&a.file)));
+    Ok(backups)
+}
+
+/// Path of a backup chosen by file name. Only plain `[A-Za-z0-9._-]` names
+/// inside `backup_dir()` are accepted, so no prefix (`C:x.db`) or separator
+/// can point elsewhere.
+pub fn resolve_backup(file: &str) -> Result<PathBuf, String> {
+    let file = file.trim();
+    let plain = file
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !plain
+        || Path::new(file).file_name() != Some(OsStr::new(file))
+        || !file.to_ascii_lowercase().ends_with(".db")
+    {
+        return Err(format!("invalid backup name: {}", file));
+    }
+    let path = backup_dir().join(file);
+    if !path.is_file() {
+        return Err(format!("backup not found: {}", file));
+    }
+    Ok(path)
+}
+
+/// New backup file name in `dir`; `label` is reduced to `[A-Za-z0-9_-]`.
+/// Names carry milliseconds, and a `-2`, `-3`, ... suffix if a file with the
+/// same name is already there.
+pub fn backup_file_name(dir: &Path, label: Option<&str>) -> String {
+    let label: String = label
+        .unwrap_or("manual")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .take(40)
+        .collect();
+    let label = if label.is_empty() { "manual".to_string() } else { label };
+    let stem = format!(
+        "varManager-{}-{}",
+        label,
+        chrono::Local::now().format("%Y%m%d-%H%M%S-%3f")
+    );
+    let mut file = format!("{}.db", stem);
+    let mut n = 2;
+    while dir.join(&file).exists() {
+        file = format!("{}-{}.db", stem, n);
+        n += 1;
+    }
+    file
+}
+
+/// Copy the live database into a new file at `dest`.
+pub async fn backup_to(pool: &SqlitePool, dest: &Path) -> Result<(), String> {
+    if dest.exists() {
+        return Err(format!("{} already exists", dest.display()));
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    online_copy(pool, dest, CopyDirection::IntoFile).await
+}
+
+/// Replace the live database contents with `src`, then bring the restored
+/// schema up to date. `src` must pass `PRAGMA integrity_check` first.
+pub async fn restore_from(pool: &SqlitePool, src: &Path) -> Result<(), String> {
+    let mut conn = SqliteConnectOptions::new()
+        .filename(src)
+        .read_only(true)
+        .connect()
+        .await
+        .map_err(|err| format!("open {} failed: {}", src.display(), err))?;
+    let integrity: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await
+        .map_err(|err| err.to_string())?;
+    drop(conn);
+    if integrity != ["ok"] {
+        return Err(format!(
+            "{} failed integrity check: {}",
+            src.display(),
+            integrity.join("; ")
+        ));
+    }
+    online_copy(pool, src, CopyDirection::FromFile).await?;
+    migrations::migrate(pool, &backup_dir()).await
+}
+
+pub async fn check(pool: &SqlitePool) -> Result<DbCheckReport, String> {
+    let integrity: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    let rows = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut foreign_key_violations = Vec::with_capacity(rows.len());
+    for row in rows {
+        foreign_key_violations.push(ForeignKeyViolation {
+            table: row.try_get(0).map_err(|err| err.to_string())?,
+            rowid: row.try_get(1).map_err(|err| err.to_string())?,
+            parent: row.try_get(2).map_err(|err| err.to_string())?,
+        });
+    }
+    let schema_version = migrations::current_version(pool).await?;
+    Ok(DbCheckReport {
+        ok: integrity == ["ok"] && foreign_key_violations.is_empty(),
+        integrity,
+        foreign_key_violations,
+        schema_version,
+    })
+}
+
+#[derive(Clone, Copy)]
+enum CopyDirection {
+    IntoFile,
+    FromFile,
+}
+
+async fn online_copy(
+    pool: &SqlitePool,
+    path: &Path,
+    direction: CopyDirection,
+) -> Result<(), String> {
+    let c_path =
+        CString::new(path.to_string_lossy().as_bytes()).map_err(|err| err.to_string())?;
+    let mut conn = pool.acquire().await.map_err(|err| err.to_string())?;
+    let mut handle = conn.lock_handle().await.map_err(|err| err.to_string())?;
+    let main = handle.as_raw_handle().as_ptr();
+    // SAFETY: `main` stays valid while `handle` holds the connection lock,
+    // and the file handle opened here is closed before returning.
+    unsafe { copy_with_backup_api(main, &c_path, direction) }
+}
+
+unsafe fn copy_with_backup_api(
+    main: *mut ffi::sqlite3,
+    path: &CStr,
+    direction: CopyDirection,
+) -> Result<(), String> {
+    let flags = match direction {
+        CopyDirection::IntoFile => ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+        CopyDirection::FromFile => ffi::SQLITE_OPEN_READONLY,
+    };
+    let mut file: *mut ffi::sqlite3 = ptr::null_mut();
+    let rc = ffi::sqlite3_open_v2(path.as_ptr(), &mut file, flags, ptr::null());
+    if rc != ffi::SQLITE_OK {
+        let err = error_message(file, rc);
+        ffi::sqlite3_close(file);
+        return Err(err);
+    }
+    let (dest, src) = match direction {
+        CopyDirection::IntoFile => (file, main),
+        CopyDirection::FromFile => (main, file),
+    };
+    let schema = c"main";
+    let backup = ffi::sqlite3_backup_init(dest, schema.as_ptr(), src, schema.as_ptr());
+    if backup.is_null() {
+        let err = error_message(dest, ffi::sqlite3_errcode(dest));
+        ffi::sqlite3_close(file);
+        return Err(err);
+    }
+
+    let mut busy = 0;
+    let result = loop {
+        match ffi::sqlite3_backup_step(backup, BACKUP_STEP_PAGES) {
+            ffi::SQLITE_DONE => break Ok(()),
+            ffi::SQLITE_OK => {}
+            rc @ (ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED) => {
+                busy += 1;
+                if busy > BACKUP_BUSY_RETRIES {
+                    break Err(error_message(dest, rc));
+                }
+                ffi::sqlite3_sleep(BACKUP_BUSY_SLEEP_MS);
+            }
+            rc => break Err(error_message(dest, rc)),
+        }
+    };
+    let finish = ffi::sqlite3_backup_finish(backup);
+    let finish = if finish == ffi::SQLITE_OK {
+        Ok(())
+    } else {
+        Err(error_message(dest, finish))
+    };
+    ffi::sqlite3_close(file);
+    result.and(finish)
+}
+
+unsafe fn error_message(db: *mut ffi::sqlite3, rc: i32) -> String {
+    let text = if db.is_null() {
+        ffi::sqlite3_errstr(rc)
+    } else {
+        ffi::sqlite3_errmsg(db)
+    };
+    if text.is_null() {
+        format!("sqlite error {}", rc)
+    } else {
+        CStr::from_ptr(text).to_string_lossy().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn file_pool(path: &Path) -> SqlitePool {
+        let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        SqlitePoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn backup_and_restore_round_trip() {
+        let dir = std::env::temp_dir().join(format!("vm_db_backup_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let pool = file_pool(&dir.join("live.db")).await;
+        sqlx::query("CREATE TABLE t (v INTEGER); INSERT INTO t VALUES (1);")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let backup = dir.join("copy.db");
+        backup_to(&pool, &backup).await.unwrap();
+        sqlx::query("UPDATE t SET v = 2").execute(&pool).await.unwrap();
+        online_copy(&pool, &backup, CopyDirection::FromFile).await.unwrap();
+
+        let v: i64 = sqlx::query_scalar("SELECT v FROM t").fetch_one(&pool).await.unwrap();
+        assert_eq!(v, 1);
+        pool.close().await;
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backup_names_do_not_repeat() {
+        let dir = std::env::temp_dir().join(format!("vm_db_backup_names_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let first = backup_file_name(&dir, Some("before update!"));
+        assert!(first.starts_with("varManager-beforeupdate-"), "{}", first);
+        assert!(first.ends_with(".db"), "{}", first);
+        fs::write(dir.join(&first), b"").unwrap();
+        let mut names = vec![first];
+        for _ in 0..3 {
+            let name = backup_file_name(&dir, Some("before update!"));
+            assert!(!names.contains(&name), "{}", name);
+            fs::write(dir.join(&name), b"").unwrap();
+            names.push(name);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backup_names_stay_in_the_backup_dir() {
+        for name in ["", "..", "../x.db", "sub\\x.db", "C:x.db", "x .db", "x.txt"] {
+            let err = resolve_backup(name).unwrap_err();
+            assert!(err.starts_with("invalid backup name"), "{}: {}", name, err);
+        }
+    }
+}
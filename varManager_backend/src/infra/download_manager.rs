@@ -1,26 +1,46 @@
-use crate::app::Config;
+use crate::app::{Config, DownloadWindow};
+use crate::infra::package_sources::{
+    self, SourceCandidate, SourceStatus, SourceTracker, VAR_URL_PREFIX,
+};
+use crate::infra::fs_util::sha256_file;
+use crate::infra::hub_auth;
 use crate::infra::downloader::{
-    ensure_dir, finalize_download, is_retryable_error, resolve_download_save_path_config,
-    resolve_file_info, resolve_final_url_with_retry,
+    ensure_dir, finalize_temp_file, is_retryable_error, resolve_download_save_path_config,
+    resolve_file_info, resolve_final_url_with_retry, verify_var_download,
 };
+use chrono::{Local, NaiveTime};
 use http_downloader::{
-    speed_limiter::DownloadSpeedLimiterExtension,
+    breakpoint_resume::{
+        DownloadBreakpointResumeExtension, DownloadDataArchiver, DownloadDataArchiverBuilder,
+    },
+    speed_limiter::{DefaultSpeedLimiter, DownloadSpeedLimiterExtension, SpeedLimiter},
     speed_tracker::DownloadSpeedTrackerExtension,
     status_tracker::DownloadStatusTrackerExtension,
-    DownloadingEndCause, HttpDownloaderBuilder,
+    DownloadArchiveData, DownloadingEndCause, HttpDownloadConfig, HttpDownloaderBuilder,
 };
 use dashmap::DashMap;
 use headers::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Client;
 use serde::Serialize;
 use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::future::Future;
 use std::num::{NonZeroU8, NonZeroUsize};
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use tokio::sync::{watch, Semaphore};
+use tokio::sync::{broadcast, mpsc, watch, Mutex, Notify, OwnedSemaphorePermit, Semaphore};
 use tokio::time::{interval, timeout, Duration, Instant};
 use url::Url;
 
+/// How often queued downloads are checked against the download windows.
+const SCHEDULE_TICK: Duration = Duration::from_secs(30);
+/// Events a slow subscriber may fall behind by before it is told to resync.
+const EVENT_BUFFER: usize = 512;
+/// How often `suspend` checks whether the cancelled downloads have stopped.
+const SUSPEND_POLL: Duration = Duration::from_millis(50);
+
 struct DownloadRuntimeConfig {
     concurrency: usize,
     connection_count: NonZeroU8,
@@ -39,6 +59,8 @@ pub enum DownloadAction {
     Resume,
     Remove,
     Delete,
+    MoveTop,
+    MoveBottom,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -52,6 +74,8 @@ pub struct DownloadItemView {
     pub total_bytes: Option<u64>,
     pub speed_bytes: u64,
     pub error: Option<String>,
+    pub priority: i64,
+    pub position: i64,
     pub save_path: Option<String>,
     pub temp_path: Option<String>,
     pub created_at: i64,
@@ -66,6 +90,9 @@ pub struct DownloadSummary {
     pub downloading: usize,
     pub paused: usize,
     pub failed: usize,
+    pub corrupt: usize,
+    /// Failed because the Hub wants a login; resume after saving one.
+    pub auth_failed: usize,
     pub completed: usize,
     pub downloaded_bytes: u64,
     pub total_bytes: u64,
@@ -78,6 +105,90 @@ pub struct DownloadListResponse {
     pub summary: DownloadSummary,
 }
 
+/// A change pushed to `/downloads/events` subscribers. Progress is sent on
+/// every ticker tick, ahead of the periodic database flush.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DownloadEvent {
+    /// `status` is `removed` once the row is gone.
+    Status {
+        id: i64,
+        status: String,
+        error: Option<String>,
+    },
+    Progress {
+        id: i64,
+        downloaded_bytes: u64,
+        total_bytes: Option<u64>,
+        speed_bytes: u64,
+    },
+    /// Sum over all running downloads.
+    Throughput { active: usize, speed_bytes: u64 },
+    /// The download list was replaced wholesale; refetch it.
+    Reloaded,
+}
+
+impl DownloadEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DownloadEvent::Status { .. } => "status",
+            DownloadEvent::Progress { .. } => "progress",
+            DownloadEvent::Throughput { .. } => "throughput",
+            DownloadEvent::Reloaded => "reloaded",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct DownloadEvents {
+    tx: broadcast::Sender<DownloadEvent>,
+    /// Last reported speed of each running download.
+    speeds: Arc<DashMap<i64, u64>>,
+}
+
+impl DownloadEvents {
+    fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            tx,
+            speeds: Arc::new(DashMap::new()),
+        }
+    }
+
+    fn status(&self, id: i64, status: &str, error: Option<String>) {
+        let _ = self.tx.send(DownloadEvent::Status {
+            id,
+            status: status.to_string(),
+            error,
+        });
+    }
+
+    fn progress(&self, id: i64, downloaded: u64, total: Option<u64>, speed: u64) {
+        let _ = self.tx.send(DownloadEvent::Progress {
+            id,
+            downloaded_bytes: downloaded,
+            total_bytes: total,
+            speed_bytes: speed,
+        });
+        self.speeds.insert(id, speed);
+        self.throughput();
+    }
+
+    fn finished(&self, id: i64) {
+        if self.speeds.remove(&id).is_some() {
+            self.throughput();
+        }
+    }
+
+    fn throughput(&self) {
+        let speed_bytes = self.speeds.iter().map(|entry| *entry.value()).sum();
+        let _ = self.tx.send(DownloadEvent::Throughput {
+            active: self.speeds.len(),
+            speed_bytes,
+        });
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DownloadEnqueueItem {
     pub url: String,
@@ -85,6 +196,80 @@ pub struct DownloadEnqueueItem {
     pub size: Option<u64>,
 }
 
+/// Why an enqueue request did not add a download.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The same URL is already queued, running or paused.
+    AlreadyQueued,
+    /// The library has the package at an equal or newer version.
+    InLibrary,
+    /// The save folder already holds an equal or newer version.
+    InSaveDir,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SkippedDownload {
+    pub url: String,
+    pub name: Option<String>,
+    pub reason: SkipReason,
+    /// The var or file that makes the download unnecessary.
+    pub existing: Option<String>,
+}
+
+impl SkippedDownload {
+    pub fn message(&self) -> String {
+        let existing = self.existing.as_deref().unwrap_or("package");
+        match self.reason {
+            SkipReason::AlreadyQueued => "already queued".to_string(),
+            SkipReason::InLibrary => format!("{} is in the library", existing),
+            SkipReason::InSaveDir => format!("{} is in the save folder", existing),
+        }
+    }
+}
+
+pub enum EnqueueOutcome {
+    Queued,
+    Skipped(SkippedDownload),
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EnqueueReport {
+    pub added: usize,
+    pub skipped: Vec<SkippedDownload>,
+}
+
+/// What [`DownloadManager::skip_index`] found for one enqueue batch.
+#[derive(Default)]
+struct SkipIndex {
+    /// Library var names by lowercase `creator.package`.
+    library: HashMap<String, Vec<String>>,
+    /// `.var` file names in the save folder.
+    save_dir: Vec<String>,
+}
+
+/// Lowercase `creator.package` of a var name.
+fn package_key(var_name: &str) -> Option<String> {
+    let mut parts = var_name.split('.');
+    let (Some(creator), Some(package)) = (parts.next(), parts.next()) else {
+        return None;
+    };
+    Some(format!("{}.{}", creator, package).to_ascii_lowercase())
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DownloadHistoryEntry {
+    pub id: i64,
+    /// Queue row it came from; the row may since have been removed.
+    pub download_id: Option<i64>,
+    pub url: String,
+    pub var_name: Option<String>,
+    pub source: Option<String>,
+    pub save_path: Option<String>,
+    pub size_bytes: Option<u64>,
+    pub completed_at: i64,
+}
+
 #[derive(Clone)]
 pub struct DownloadManager {
     db_pool: SqlitePool,
@@ -92,15 +277,29 @@ pub struct DownloadManager {
     client: Arc<Client>,
     semaphore: Arc<Semaphore>,
     active: Arc<DashMap<i64, DownloadHandle>>,
+    /// Receives the id of every download that finishes.
+    completed: mpsc::UnboundedSender<i64>,
+    /// Wakes the scheduler when a slot frees up.
+    wake: Arc<Notify>,
+    events: DownloadEvents,
+    sources: Arc<SourceTracker>,
+    dispatch_lock: Arc<Mutex<()>>,
+    /// Set while the database is being restored: nothing starts or changes.
+    suspended: Arc<AtomicBool>,
 }
 
 #[derive(Clone)]
 struct DownloadHandle {
     cancel: watch::Sender<bool>,
+    limiter: Arc<DefaultSpeedLimiter>,
 }
 
 impl DownloadManager {
-    pub fn new(db_pool: SqlitePool, config: Arc<RwLock<Config>>) -> Self {
+    pub fn new(
+        db_pool: SqlitePool,
+        config: Arc<RwLock<Config>>,
+        completed: mpsc::UnboundedSender<i64>,
+    ) -> Self {
         let runtime = read_runtime_config(&config);
         let client = Arc::new(
             Client::builder()
@@ -115,18 +314,264 @@ impl DownloadManager {
             client,
             semaphore: Arc::new(Semaphore::new(runtime.concurrency)),
             active: Arc::new(DashMap::new()),
+            completed,
+            wake: Arc::new(Notify::new()),
+            dispatch_lock: Arc::new(Mutex::new(())),
+            suspended: Arc::new(AtomicBool::new(false)),
+            events: DownloadEvents::new(),
+            sources: Arc::new(SourceTracker::default()),
+        }
+    }
+
+    /// Configured package sources with their recent health.
+    pub fn source_statuses(&self) -> Vec<SourceStatus> {
+        let sources = self
+            .config
+            .read()
+            .map(|cfg| package_sources::active_sources(&cfg.download))
+            .unwrap_or_default();
+        self.sources.statuses(&sources)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DownloadEvent> {
+        self.events.tx.subscribe()
+    }
+
+    /// No download is running.
+    pub fn is_idle(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// `save_path` of each of `ids` that has completed.
+    pub async fn completed_paths(&self, ids: &[i64]) -> Result<HashMap<i64, PathBuf>, String> {
+        let mut paths = HashMap::new();
+        for id in ids {
+            let path: Option<Option<String>> = sqlx::query_scalar(
+                "SELECT save_path FROM downloads WHERE id = ?1 AND status = 'completed'",
+            )
+            .bind(id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|err| err.to_string())?;
+            if let Some(path) = path.flatten().filter(|path| !path.is_empty()) {
+                paths.insert(*id, PathBuf::from(path));
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Start queued downloads whenever a slot frees up or a download window
+    /// opens.
+    pub fn spawn_scheduler(self: &Arc<Self>) {
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = interval(SCHEDULE_TICK);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = manager.wake.notified() => {}
+                }
+                if let Err(err) = manager.dispatch().await {
+                    tracing::warn!(error = %err, "download scheduler failed");
+                }
+            }
+        });
+    }
+
+    /// Stop every running download and hold the queue until `resume`, so the
+    /// `downloads` table can be swapped out underneath. Running downloads
+    /// write their progress and breakpoint data before they stop.
+    pub async fn suspend(&self) {
+        let _guard = self.dispatch_lock.lock().await;
+        self.suspended.store(true, Ordering::SeqCst);
+        for handle in self.active.iter() {
+            let _ = handle.cancel.send(true);
+        }
+        while !self.active.is_empty() {
+            tokio::time::sleep(SUSPEND_POLL).await;
+        }
+    }
+
+    /// Pick the queue up again after `suspend`, from whatever the `downloads`
+    /// table now holds. Rows left `downloading` are settled the way a restart
+    /// settles them.
+    pub async fn resume(&self) -> Result<(), String> {
+        let resume = self
+            .config
+            .read()
+            .map(|cfg| cfg.download.resume_on_startup)
+            .unwrap_or(false);
+        let settled = self.recover_incomplete(resume).await;
+        self.suspended.store(false, Ordering::SeqCst);
+        let _ = self.events.tx.send(DownloadEvent::Reloaded);
+        settled?;
+        self.dispatch().await
+    }
+
+    fn ensure_running(&self) -> Result<(), String> {
+        if self.suspended.load(Ordering::SeqCst) {
+            return Err("downloads are on hold while the database is restored".to_string());
+        }
+        Ok(())
+    }
+
+    /// Re-apply speed limits and windows after the download config changed.
+    pub async fn config_changed(&self) -> Result<(), String> {
+        apply_speed_limits(&self.config, &self.active).await;
+        self.dispatch().await
+    }
+
+    /// Start queued downloads in queue order (priority, then position) until
+    /// every slot is taken. Nothing starts outside the download windows.
+    async fn dispatch(&self) -> Result<(), String> {
+        let _guard = self.dispatch_lock.lock().await;
+        if self.suspended.load(Ordering::SeqCst) || !window_open(&self.config) {
+            return Ok(());
+        }
+        while let Ok(permit) = Arc::clone(&self.semaphore).try_acquire_owned() {
+            let next: Option<i64> = sqlx::query_scalar(
+                r#"
+                SELECT id FROM downloads
+                WHERE status = 'queued'
+                ORDER BY priority DESC, position, id
+                LIMIT 1
+                "#,
+            )
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|err| err.to_string())?;
+            let Some(id) = next else {
+                break;
+            };
+            update_status(&self.db_pool, &self.events, id, "downloading", None).await?;
+            self.start_download(id, permit).await?;
+        }
+        Ok(())
+    }
+
+    /// Move `ids` to the front (or back) of their priority group, keeping
+    /// their relative order.
+    pub async fn move_ids(&self, ids: &[i64], to_top: bool) -> Result<(), String> {
+        let mut tx = self.db_pool.begin().await.map_err(|err| err.to_string())?;
+        let (bound, ordered): (i64, Vec<i64>) = if to_top {
+            let min: Option<i64> = sqlx::query_scalar("SELECT MIN(position) FROM downloads")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|err| err.to_string())?;
+            (min.unwrap_or(0), ids.iter().rev().copied().collect())
+        } else {
+            let max: Option<i64> = sqlx::query_scalar("SELECT MAX(position) FROM downloads")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|err| err.to_string())?;
+            (max.unwrap_or(0), ids.to_vec())
+        };
+        let step = if to_top { -1 } else { 1 };
+        for (index, id) in ordered.iter().enumerate() {
+            let position = bound + step * (index as i64 + 1);
+            sqlx::query("UPDATE downloads SET position = ?1, updated_at = ?2 WHERE id = ?3")
+                .bind(position)
+                .bind(now_ts())
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|err| err.to_string())?;
         }
+        tx.commit().await.map_err(|err| err.to_string())?;
+        Ok(())
     }
 
-    pub async fn pause_incomplete(&self) -> Result<(), String> {
+    /// Higher priorities start first; the default is 0.
+    pub async fn set_priority(&self, ids: &[i64], priority: i64) -> Result<(), String> {
+        self.ensure_running()?;
         let now = now_ts();
+        for id in ids {
+            sqlx::query("UPDATE downloads SET priority = ?1, updated_at = ?2 WHERE id = ?3")
+                .bind(priority)
+                .bind(now)
+                .bind(id)
+                .execute(&self.db_pool)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Move the waiting downloads whose name matches one of `var_names` to
+    /// the top of the queue, raising their priority to the highest one
+    /// waiting so no other priority group starts first. `.latest` / `.min`
+    /// references match any version of the package. Returns the ids that
+    /// were moved.
+    pub async fn prioritize_vars(&self, var_names: &[String]) -> Result<Vec<i64>, String> {
+        self.ensure_running()?;
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, save_path FROM downloads
+            WHERE status IN ('queued', 'paused')
+            ORDER BY priority DESC, position, id
+            "#,
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+        let mut ids = Vec::new();
+        for row in rows {
+            let name: Option<String> = row.try_get("name").ok();
+            let save_path: Option<String> = row.try_get("save_path").ok();
+            let candidate = name.filter(|name| !name.trim().is_empty()).or_else(|| {
+                save_path.and_then(|path| {
+                    Path::new(&path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                })
+            });
+            let Some(candidate) = candidate else {
+                continue;
+            };
+            if var_names.iter().any(|wanted| download_matches_var(&candidate, wanted)) {
+                ids.push(row.try_get("id").unwrap_or_default());
+            }
+        }
+        if ids.is_empty() {
+            return Ok(ids);
+        }
+        let top: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(priority) FROM downloads WHERE status IN ('queued', 'paused')",
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+        let top = top.unwrap_or(0);
+        let now = now_ts();
+        for id in &ids {
+            sqlx::query(
+                "UPDATE downloads SET priority = MAX(priority, ?1), updated_at = ?2 WHERE id = ?3",
+            )
+            .bind(top)
+            .bind(now)
+            .bind(id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|err| err.to_string())?;
+        }
+        self.move_ids(&ids, true).await?;
+        Ok(ids)
+    }
+
+    /// Settle downloads left running by the last shutdown or crash: paused,
+    /// or queued again when `resume` is set. Either way their breakpoint data
+    /// lets them continue from the last finished chunk.
+    pub async fn recover_incomplete(&self, resume: bool) -> Result<(), String> {
+        let now = now_ts();
+        let status = if resume { "queued" } else { "paused" };
         sqlx::query(
             r#"
             UPDATE downloads
-            SET status = 'paused', speed_bytes = 0, updated_at = ?1
+            SET status = ?1, speed_bytes = 0, updated_at = ?2
             WHERE status IN ('queued', 'downloading')
             "#,
         )
+        .bind(status)
         .bind(now)
         .execute(&self.db_pool)
         .await
@@ -135,7 +580,7 @@ impl DownloadManager {
     }
 
     #[allow(dead_code)]
-    pub async fn enqueue_urls(&self, urls: Vec<String>) -> Result<usize, String> {
+    pub async fn enqueue_urls(&self, urls: Vec<String>) -> Result<EnqueueReport, String> {
         let items = urls
             .into_iter()
             .filter_map(|raw| {
@@ -156,42 +601,122 @@ impl DownloadManager {
         self.enqueue_items(items).await
     }
 
-    pub async fn enqueue_items(&self, items: Vec<DownloadEnqueueItem>) -> Result<usize, String> {
-        if items.is_empty() {
-            return Ok(0);
+    pub async fn enqueue_items(
+        &self,
+        items: Vec<DownloadEnqueueItem>,
+    ) -> Result<EnqueueReport, String> {
+        let mut report = EnqueueReport::default();
+        for outcome in self.enqueue_each(items).await? {
+            match outcome? {
+                EnqueueOutcome::Queued => report.added += 1,
+                EnqueueOutcome::Skipped(skipped) => report.skipped.push(skipped),
+            }
         }
-        let mut added = 0usize;
+        Ok(report)
+    }
+
+    /// Queue each of `items`, unless its URL is already queued or the
+    /// package is already in the library or the save folder. Outcomes are in
+    /// `items` order. The library and save folder are looked up once for the
+    /// whole batch, and the scheduler is woken once at the end.
+    pub async fn enqueue_each(
+        &self,
+        items: Vec<DownloadEnqueueItem>,
+    ) -> Result<Vec<Result<EnqueueOutcome, String>>, String> {
+        self.ensure_running()?;
+        let index = self.skip_index(&items).await?;
+        let mut outcomes = Vec::with_capacity(items.len());
+        let mut added = false;
         for item in items {
-            if self.is_duplicate(&item.url).await? {
-                continue;
-            }
-            let now = now_ts();
-            let result = sqlx::query(
-                r#"
-                INSERT INTO downloads (url, name, status, downloaded_bytes, total_bytes, speed_bytes, error, created_at, updated_at)
-                VALUES (?1, ?2, 'queued', 0, ?3, 0, NULL, ?4, ?4)
-                "#,
-            )
-            .bind(&item.url)
-            .bind(&item.name)
-            .bind(item.size.map(|v| v as i64))
-            .bind(now)
-            .execute(&self.db_pool)
-            .await
-            .map_err(|err| err.to_string())?;
-            let id = result.last_insert_rowid();
-            self.start_download(id).await?;
-            added += 1;
+            let outcome = self.enqueue_one(&index, item).await;
+            added |= matches!(outcome, Ok(EnqueueOutcome::Queued));
+            outcomes.push(outcome);
+        }
+        if added {
+            self.dispatch().await?;
+        }
+        Ok(outcomes)
+    }
+
+    async fn enqueue_one(
+        &self,
+        index: &SkipIndex,
+        item: DownloadEnqueueItem,
+    ) -> Result<EnqueueOutcome, String> {
+        if let Some((reason, existing)) = self.skip_reason(index, &item).await? {
+            return Ok(EnqueueOutcome::Skipped(SkippedDownload {
+                url: item.url,
+                name: item.name,
+                reason,
+                existing,
+            }));
         }
-        Ok(added)
+        let now = now_ts();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO downloads (url, name, status, downloaded_bytes, total_bytes, speed_bytes,
+                                   error, position, created_at, updated_at)
+            VALUES (?1, ?2, 'queued', 0, ?3, 0, NULL,
+                    (SELECT COALESCE(MAX(position), 0) + 1 FROM downloads), ?4, ?4)
+            "#,
+        )
+        .bind(&item.url)
+        .bind(&item.name)
+        .bind(item.size.map(|v| v as i64))
+        .bind(now)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+        let id = result.last_insert_rowid();
+        self.events.status(id, "queued", None);
+        Ok(EnqueueOutcome::Queued)
+    }
+
+    /// Completed downloads, newest first.
+    pub async fn history(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DownloadHistoryEntry>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, download_id, url, var_name, source, save_path, size_bytes, completed_at
+            FROM download_history
+            ORDER BY completed_at DESC, id DESC
+            LIMIT ?1 OFFSET ?2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+        Ok(rows
+            .into_iter()
+            .map(|row| DownloadHistoryEntry {
+                id: row.try_get("id").unwrap_or_default(),
+                download_id: row.try_get("download_id").ok(),
+                url: row.try_get("url").unwrap_or_default(),
+                var_name: row.try_get("var_name").ok(),
+                source: row.try_get("source").ok(),
+                save_path: row.try_get("save_path").ok(),
+                size_bytes: row
+                    .try_get::<Option<i64>, _>("size_bytes")
+                    .ok()
+                    .flatten()
+                    .map(|v| v as u64),
+                completed_at: row.try_get("completed_at").unwrap_or_default(),
+            })
+            .collect())
     }
 
     pub async fn list_downloads(&self) -> Result<DownloadListResponse, String> {
         let rows = sqlx::query(
             r#"
-            SELECT id, url, name, status, downloaded_bytes, total_bytes, speed_bytes, error, save_path, temp_path, created_at, updated_at
+            SELECT id, url, name, status, downloaded_bytes, total_bytes, speed_bytes, error,
+                   priority, position, save_path, temp_path, created_at, updated_at
             FROM downloads
-            ORDER BY created_at DESC
+            ORDER BY priority DESC, position, id
             "#,
         )
         .fetch_all(&self.db_pool)
@@ -205,6 +730,8 @@ impl DownloadManager {
             downloading: 0,
             paused: 0,
             failed: 0,
+            corrupt: 0,
+            auth_failed: 0,
             completed: 0,
             downloaded_bytes: 0,
             total_bytes: 0,
@@ -228,6 +755,8 @@ impl DownloadManager {
                 "downloading" => summary.downloading += 1,
                 "paused" => summary.paused += 1,
                 "failed" => summary.failed += 1,
+                "corrupt" => summary.corrupt += 1,
+                "auth_failed" => summary.auth_failed += 1,
                 "completed" => summary.completed += 1,
                 _ => {}
             }
@@ -240,6 +769,8 @@ impl DownloadManager {
                 total_bytes: total_bytes.and_then(|v| if v > 0 { Some(v as u64) } else { None }),
                 speed_bytes: speed_bytes.max(0) as u64,
                 error: row.try_get("error").ok(),
+                priority: row.try_get("priority").unwrap_or(0),
+                position: row.try_get("position").unwrap_or(0),
                 save_path: row.try_get("save_path").ok(),
                 temp_path: row.try_get("temp_path").ok(),
                 created_at: row.try_get("created_at").unwrap_or(0),
@@ -251,11 +782,14 @@ impl DownloadManager {
     }
 
     pub async fn apply_action(&self, action: DownloadAction, ids: Vec<i64>) -> Result<(), String> {
+        self.ensure_running()?;
         match action {
             DownloadAction::Pause => self.pause_ids(ids).await,
             DownloadAction::Resume => self.resume_ids(ids).await,
             DownloadAction::Remove => self.remove_ids(ids).await,
             DownloadAction::Delete => self.delete_ids(ids).await,
+            DownloadAction::MoveTop => self.move_ids(&ids, true).await,
+            DownloadAction::MoveBottom => self.move_ids(&ids, false).await,
         }
     }
 
@@ -273,6 +807,7 @@ impl DownloadManager {
             .execute(&self.db_pool)
             .await
             .map_err(|err| err.to_string())?;
+            self.events.status(id, "paused", None);
             if let Some(handle) = self.active.get(&id) {
                 let _ = handle.cancel.send(true);
             }
@@ -283,6 +818,9 @@ impl DownloadManager {
 
     async fn resume_ids(&self, ids: Vec<i64>) -> Result<(), String> {
         for id in ids {
+            if self.active.contains_key(&id) {
+                continue;
+            }
             let now = now_ts();
             sqlx::query(
                 "UPDATE downloads SET status = 'queued', error = NULL, updated_at = ?1 WHERE id = ?2",
@@ -292,9 +830,9 @@ impl DownloadManager {
             .execute(&self.db_pool)
             .await
             .map_err(|err| err.to_string())?;
-            self.start_download(id).await?;
+            self.events.status(id, "queued", None);
         }
-        Ok(())
+        self.dispatch().await
     }
 
     async fn remove_ids(&self, ids: Vec<i64>) -> Result<(), String> {
@@ -305,6 +843,7 @@ impl DownloadManager {
                 .execute(&self.db_pool)
                 .await
                 .map_err(|err| err.to_string())?;
+            self.events.status(id, "removed", None);
         }
         Ok(())
     }
@@ -334,6 +873,7 @@ impl DownloadManager {
                 .execute(&self.db_pool)
                 .await
                 .map_err(|err| err.to_string())?;
+            self.events.status(id, "removed", None);
         }
         Ok(())
     }
@@ -345,6 +885,91 @@ impl DownloadManager {
         self.active.remove(&id);
     }
 
+    /// Why `item` needs no download, with the var or file already covering it.
+    /// Library vars and save-folder files that could make any of `items`
+    /// unnecessary.
+    async fn skip_index(&self, items: &[DownloadEnqueueItem]) -> Result<SkipIndex, String> {
+        let mut index = SkipIndex::default();
+        let mut packages: Vec<String> = items
+            .iter()
+            .filter_map(|item| {
+                package_sources::download_var_name(&item.url, item.name.as_deref())
+            })
+            .filter_map(|var_name| package_key(&var_name))
+            .collect();
+        packages.sort();
+        packages.dedup();
+        if packages.is_empty() {
+            return Ok(index);
+        }
+        let packages = serde_json::to_string(&packages).map_err(|err| err.to_string())?;
+        let rows = sqlx::query(
+            "SELECT varName FROM vars
+             WHERE lower(creatorName || '.' || packageName) IN (SELECT value FROM json_each(?1))",
+        )
+        .bind(packages)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+        for row in rows {
+            let var_name: String = row.try_get("varName").unwrap_or_default();
+            if let Some(key) = package_key(&var_name) {
+                index.library.entry(key).or_default().push(var_name);
+            }
+        }
+
+        let save_dir = self
+            .config
+            .read()
+            .ok()
+            .and_then(|cfg| resolve_download_save_path_config(&cfg).ok());
+        if let Some(save_dir) = save_dir {
+            index.save_dir = tokio::task::spawn_blocking(move || {
+                std::fs::read_dir(&save_dir)
+                    .map(|entries| {
+                        entries
+                            .flatten()
+                            .map(|entry| entry.file_name().to_string_lossy().to_string())
+                            .filter(|file| file.to_ascii_lowercase().ends_with(".var"))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+            .await
+            .map_err(|err| err.to_string())?;
+        }
+        Ok(index)
+    }
+
+    async fn skip_reason(
+        &self,
+        index: &SkipIndex,
+        item: &DownloadEnqueueItem,
+    ) -> Result<Option<(SkipReason, Option<String>)>, String> {
+        if self.is_duplicate(&item.url).await? {
+            return Ok(Some((SkipReason::AlreadyQueued, None)));
+        }
+        let Some(var_name) = package_sources::download_var_name(&item.url, item.name.as_deref())
+        else {
+            return Ok(None);
+        };
+        let Some(key) = package_key(&var_name) else {
+            return Ok(None);
+        };
+        let in_library = index
+            .library
+            .get(&key)
+            .and_then(|names| names.iter().find(|name| covers_version(name, &var_name)));
+        if let Some(existing) = in_library {
+            return Ok(Some((SkipReason::InLibrary, Some(existing.clone()))));
+        }
+        Ok(index
+            .save_dir
+            .iter()
+            .find(|file| covers_version(file, &var_name))
+            .map(|file| (SkipReason::InSaveDir, Some(file.clone()))))
+    }
+
     async fn is_duplicate(&self, url: &str) -> Result<bool, String> {
         let row = sqlx::query(
             "SELECT 1 FROM downloads WHERE url = ?1 AND status IN ('queued', 'downloading', 'paused') LIMIT 1",
@@ -356,10 +981,7 @@ impl DownloadManager {
         Ok(row.is_some())
     }
 
-    async fn start_download(&self, id: i64) -> Result<(), String> {
-        if self.active.contains_key(&id) {
-            return Ok(());
-        }
+    async fn start_download(&self, id: i64, permit: OwnedSemaphorePermit) -> Result<(), String> {
         let row = sqlx::query(
             "SELECT url, name FROM downloads WHERE id = ?1",
         )
@@ -373,60 +995,191 @@ impl DownloadManager {
         let url: String = row.try_get("url").unwrap_or_default();
         let name: Option<String> = row.try_get("name").ok();
         if url.is_empty() {
-            return Ok(());
+            let error = Some("download url is empty".to_string());
+            return update_status(&self.db_pool, &self.events, id, "failed", error).await;
         }
         let (cancel_tx, cancel_rx) = watch::channel(false);
+        let limiter = Arc::new(DefaultSpeedLimiter::new(None));
         self.active.insert(
             id,
             DownloadHandle {
                 cancel: cancel_tx,
+                limiter: Arc::clone(&limiter),
             },
         );
-        let db_pool = self.db_pool.clone();
-        let client = Arc::clone(&self.client);
-        let config = Arc::clone(&self.config);
-        let semaphore = Arc::clone(&self.semaphore);
+        let ctx = DownloadContext {
+            db_pool: self.db_pool.clone(),
+            events: self.events.clone(),
+            config: Arc::clone(&self.config),
+            client: Arc::clone(&self.client),
+            id,
+            name_hint: name,
+            cancel_rx,
+            limiter,
+        };
+        let sources = Arc::clone(&self.sources);
         let active = Arc::clone(&self.active);
+        let completed = self.completed.clone();
+        let wake = Arc::clone(&self.wake);
 
         tokio::spawn(async move {
-            let _permit = match semaphore.acquire().await {
-                Ok(permit) => permit,
-                Err(_) => {
-                    let _ = update_status(&db_pool, id, "failed", Some("failed to acquire download slot".to_string())).await;
-                    active.remove(&id);
-                    return;
+            apply_speed_limits(&ctx.config, &active).await;
+            match download_from_sources(&ctx, &sources, &url).await {
+                Ok(Attempt::Finished) => {
+                    let _ = completed.send(id);
+                }
+                Ok(Attempt::Stopped) => {}
+                Ok(Attempt::Corrupt(err)) => {
+                    let _ =
+                        update_status(&ctx.db_pool, &ctx.events, id, "corrupt", Some(err)).await;
+                }
+                Err(err) => {
+                    let status = if hub_auth::is_auth_error(&err) {
+                        "auth_failed"
+                    } else {
+                        "failed"
+                    };
+                    let _ = update_status(&ctx.db_pool, &ctx.events, id, status, Some(err)).await;
                 }
-            };
-            let _ = update_status(&db_pool, id, "downloading", None).await;
-            let result = download_with_progress(
-                &db_pool,
-                &config,
-                &client,
-                id,
-                &url,
-                name.as_deref(),
-                cancel_rx,
-            )
-            .await;
-            if let Err(err) = result {
-                let _ = update_status(&db_pool, id, "failed", Some(err)).await;
             }
+            ctx.events.finished(id);
+            drop(permit);
+            wake.notify_one();
             active.remove(&id);
+            apply_speed_limits(&ctx.config, &active).await;
         });
 
         Ok(())
     }
 }
 
-async fn download_with_progress(
-    db_pool: &SqlitePool,
-    config: &Arc<RwLock<Config>>,
-    client: &Client,
+/// What a running download needs, whichever source it is fetched from.
+struct DownloadContext {
+    db_pool: SqlitePool,
+    events: DownloadEvents,
+    config: Arc<RwLock<Config>>,
+    client: Arc<Client>,
     id: i64,
-    url: &str,
-    name_hint: Option<&str>,
+    name_hint: Option<String>,
     cancel_rx: watch::Receiver<bool>,
-) -> Result<(), String> {
+    limiter: Arc<DefaultSpeedLimiter>,
+}
+
+enum Attempt {
+    /// Downloaded, verified and marked completed.
+    Finished,
+    /// Paused or cancelled; the status is already recorded.
+    Stopped,
+    /// Still failed verification after the last retry.
+    Corrupt(String),
+}
+
+/// Try each source that has the package, in order, until one delivers a
+/// file that passes verification.
+async fn download_from_sources(
+    ctx: &DownloadContext,
+    tracker: &SourceTracker,
+    url: &str,
+) -> Result<Attempt, String> {
+    let var_name = package_sources::download_var_name(url, ctx.name_hint.as_deref());
+    let sources = {
+        let cfg = ctx
+            .config
+            .read()
+            .map_err(|_| "config lock poisoned".to_string())?;
+        package_sources::active_sources(&cfg.download)
+    };
+    let candidates =
+        package_sources::candidates(tracker, &sources, &ctx.client, url, var_name.as_deref())
+            .await;
+    if candidates.is_empty() {
+        let name = var_name.unwrap_or_else(|| url.trim_start_matches(VAR_URL_PREFIX).to_string());
+        return Err(format!("no package source has {}", name));
+    }
+
+    let mut last = Err(String::new());
+    for candidate in candidates {
+        if *ctx.cancel_rx.borrow() {
+            update_status(&ctx.db_pool, &ctx.events, ctx.id, "paused", None).await?;
+            return Ok(Attempt::Stopped);
+        }
+        let result = match &candidate {
+            SourceCandidate::Url { url, sha256, .. } => {
+                download_with_progress(ctx, url, sha256.as_deref()).await
+            }
+            SourceCandidate::File { path, .. } => copy_from_directory(ctx, path).await,
+        };
+        let error = match &result {
+            Ok(Attempt::Finished) => {
+                tracker.record_success(candidate.source());
+                if let Err(err) = record_history(ctx, candidate.source()).await {
+                    tracing::warn!(id = ctx.id, error = %err, "download history not recorded");
+                }
+                return result;
+            }
+            Ok(Attempt::Stopped) => return result,
+            Ok(Attempt::Corrupt(err)) | Err(err) => format!("{}: {}", candidate.source(), err),
+        };
+        // A missing login says nothing about the source's health.
+        if !hub_auth::is_auth_error(&error) {
+            tracker.record_failure(candidate.source(), &error);
+        }
+        last = match result {
+            Ok(Attempt::Corrupt(_)) => Ok(Attempt::Corrupt(error)),
+            _ => Err(error),
+        };
+    }
+    last
+}
+
+/// Copy a package from a directory source into the download folder.
+async fn copy_from_directory(ctx: &DownloadContext, source: &Path) -> Result<Attempt, String> {
+    let save_dir = {
+        let cfg = ctx
+            .config
+            .read()
+            .map_err(|_| "config lock poisoned".to_string())?;
+        resolve_download_save_path_config(&cfg)?
+    };
+    ensure_dir(&save_dir)?;
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| format!("invalid source file: {}", source.display()))?;
+    let save_path = save_dir.join(file_name);
+    let _ = update_paths(&ctx.db_pool, ctx.id, &save_path, &save_path).await;
+
+    let from = source.to_path_buf();
+    let to = save_path.clone();
+    let copied = tokio::task::spawn_blocking(move || -> Result<u64, String> {
+        let size = std::fs::copy(&from, &to).map_err(|err| err.to_string())?;
+        verify_var_download(&to, Some(size))?;
+        Ok(size)
+    })
+    .await
+    .map_err(|err| err.to_string())?;
+    let size = match copied {
+        Ok(size) => size,
+        Err(err) => {
+            let _ = std::fs::remove_file(&save_path);
+            return Ok(Attempt::Corrupt(err));
+        }
+    };
+    ctx.events.progress(ctx.id, size, Some(size), 0);
+    let _ = update_progress(&ctx.db_pool, ctx.id, size, Some(size), 0).await;
+    update_status(&ctx.db_pool, &ctx.events, ctx.id, "completed", None).await?;
+    Ok(Attempt::Finished)
+}
+
+async fn download_with_progress(
+    ctx: &DownloadContext,
+    url: &str,
+    sha256: Option<&str>,
+) -> Result<Attempt, String> {
+    let (db_pool, events, config, client, id) =
+        (&ctx.db_pool, &ctx.events, &ctx.config, ctx.client.as_ref(), ctx.id);
+    let name_hint = ctx.name_hint.as_deref();
+    let cancel_rx = ctx.cancel_rx.clone();
+    let limiter = &ctx.limiter;
     let runtime = read_runtime_config(config);
     let save_dir = {
         let cfg = config
@@ -458,7 +1211,22 @@ async fn download_with_progress(
         filename
     };
     let url_obj = Url::parse(&final_url).map_err(|err| err.to_string())?;
-    let temp_path = download_temp_path(&url_obj, &save_dir);
+    let temp_path = match resumable_temp_path(db_pool, id, &save_dir).await? {
+        Some(path) => path,
+        None => {
+            // Without breakpoint data a leftover partial file is useless, and
+            // the downloader would write over it without truncating.
+            let path = download_temp_path(id, &url_obj, &save_dir);
+            if path.exists() {
+                std::fs::remove_file(&path).map_err(|err| err.to_string())?;
+            }
+            clear_resume_data(db_pool, id).await?;
+            path
+        }
+    };
+    let temp_name = temp_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
     let save_path = save_dir.join(&final_name);
     let _ = update_paths(db_pool, id, &save_path, &temp_path).await;
     if let Some(size) = head_size {
@@ -468,21 +1236,26 @@ async fn download_with_progress(
     let mut attempt: u8 = 0;
     loop {
         if *cancel_rx.borrow() {
-            let _ = update_status(db_pool, id, "paused", None).await;
-            return Ok(());
+            let _ = update_status(db_pool, events, id, "paused", None).await;
+            return Ok(Attempt::Stopped);
         }
         attempt += 1;
         let mut cancel_rx = cancel_rx.clone();
-        let (mut downloader, (_status_state, speed_state, _speed_limiter)) =
+        let (mut downloader, (_status_state, speed_state, _speed_limiter, _resume_state)) =
             HttpDownloaderBuilder::new(url_obj.clone(), save_dir.clone())
+                .file_name(temp_name.clone())
                 .chunk_size(runtime.chunk_size)
                 .download_connection_count(runtime.connection_count)
                 .request_retry_count(runtime.max_get_retries)
-                .header_map(hub_headers_compat())
+                .header_map(hub_headers_compat(&url_obj))
                 .build((
                     DownloadStatusTrackerExtension { log: false },
                     DownloadSpeedTrackerExtension { log: false },
-                    DownloadSpeedLimiterExtension::new(None),
+                    DownloadSpeedLimiterExtension::from_limiter(Arc::clone(limiter)),
+                    DownloadBreakpointResumeExtension::new(RowArchiverBuilder {
+                        db_pool: db_pool.clone(),
+                        id,
+                    }),
                 ));
 
         let download_future = downloader
@@ -513,10 +1286,13 @@ async fn download_with_progress(
                                 .map(|v| v.get())
                                 .or(head_size);
                             let speed = speed_state.download_speed();
-                            if downloaded != last_downloaded || speed != last_speed || last_flush.elapsed() >= runtime.progress_db_flush {
-                                let _ = update_progress(db_pool, id, downloaded, total, speed).await;
+                            if downloaded != last_downloaded || speed != last_speed {
+                                events.progress(id, downloaded, total, speed);
                                 last_downloaded = downloaded;
                                 last_speed = speed;
+                            }
+                            if last_flush.elapsed() >= runtime.progress_db_flush {
+                                let _ = update_progress(db_pool, id, downloaded, total, speed).await;
                                 last_flush = Instant::now();
                             }
                         }
@@ -538,19 +1314,42 @@ async fn download_with_progress(
 
         match download_result {
             Ok(Ok(DownloadingEndCause::DownloadFinished)) => {
-                finalize_download(&url_obj, &save_dir, &final_name)?;
+                finalize_temp_file(&temp_path, &save_path)?;
+                clear_resume_data(db_pool, id).await?;
                 let total = downloader
                     .current_total_size()
                     .map(|v| v.get())
                     .or(head_size);
                 let downloaded = downloader.downloaded_len();
+                events.progress(id, downloaded, total, 0);
                 let _ = update_progress(db_pool, id, downloaded, total, 0).await;
-                let _ = update_status(db_pool, id, "completed", None).await;
-                return Ok(());
+                let check_path = save_path.clone();
+                let expected = sha256.map(str::to_string);
+                let verified = tokio::task::spawn_blocking(move || {
+                    verify_var_download(&check_path, total)?;
+                    match expected {
+                        Some(expected) if sha256_file(&check_path)? != expected => {
+                            Err("checksum mismatch".to_string())
+                        }
+                        _ => Ok(()),
+                    }
+                })
+                .await
+                .map_err(|err| err.to_string())?;
+                if let Err(err) = verified {
+                    let _ = std::fs::remove_file(&save_path);
+                    if attempt <= runtime.max_download_retries {
+                        tokio::time::sleep(Duration::from_secs(2)).await;
+                        continue;
+                    }
+                    return Ok(Attempt::Corrupt(err));
+                }
+                let _ = update_status(db_pool, events, id, "completed", None).await;
+                return Ok(Attempt::Finished);
             }
             Ok(Ok(DownloadingEndCause::Cancelled)) => {
-                let _ = update_status(db_pool, id, "paused", None).await;
-                return Ok(());
+                let _ = update_status(db_pool, events, id, "paused", None).await;
+                return Ok(Attempt::Stopped);
             }
             Ok(Err(err)) => {
                 if attempt <= runtime.max_download_retries && is_retryable_error(&err) {
@@ -570,12 +1369,253 @@ async fn download_with_progress(
     }
 }
 
-fn download_temp_path(url: &Url, save_dir: &Path) -> PathBuf {
+/// Give every running download the smaller of the per-download cap and an
+/// equal share of the global cap.
+async fn apply_speed_limits(config: &Arc<RwLock<Config>>, active: &DashMap<i64, DownloadHandle>) {
+    let (global, per_download) = config
+        .read()
+        .map(|cfg| (cfg.download.speed_limit_kbps, cfg.download.per_download_speed_limit_kbps))
+        .unwrap_or((0, 0));
+    let limiters: Vec<Arc<DefaultSpeedLimiter>> = active
+        .iter()
+        .map(|entry| Arc::clone(&entry.limiter))
+        .collect();
+    if limiters.is_empty() {
+        return;
+    }
+    let share = (global > 0).then(|| (global / limiters.len() as u64).max(1));
+    let per_download = (per_download > 0).then_some(per_download);
+    let limit = match (share, per_download) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    let bytes = limit.map(|kbps| kbps.saturating_mul(1024).min(usize::MAX as u64) as usize);
+    for limiter in limiters {
+        limiter.change(bytes).await;
+    }
+}
+
+/// Remember a completed download, so it outlives the queue row.
+async fn record_history(ctx: &DownloadContext, source: &str) -> Result<(), String> {
+    let row = sqlx::query(
+        "SELECT url, name, save_path, total_bytes, downloaded_bytes FROM downloads WHERE id = ?1",
+    )
+    .bind(ctx.id)
+    .fetch_one(&ctx.db_pool)
+    .await
+    .map_err(|err| err.to_string())?;
+    let url: String = row.try_get("url").unwrap_or_default();
+    let name: Option<String> = row.try_get("name").ok();
+    let save_path: Option<String> = row.try_get("save_path").ok();
+    let size: Option<i64> = row
+        .try_get::<Option<i64>, _>("total_bytes")
+        .ok()
+        .flatten()
+        .or_else(|| row.try_get("downloaded_bytes").ok());
+    // The saved file is named after the package it turned out to be.
+    let var_name = save_path
+        .as_deref()
+        .and_then(|path| Path::new(path).file_name())
+        .and_then(|file| package_sources::download_var_name("", file.to_str()))
+        .or_else(|| package_sources::download_var_name(&url, name.as_deref()));
+    sqlx::query(
+        r#"
+        INSERT INTO download_history (download_id, url, var_name, source, save_path,
+                                      size_bytes, completed_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(ctx.id)
+    .bind(&url)
+    .bind(var_name)
+    .bind(source)
+    .bind(save_path)
+    .bind(size)
+    .bind(now_ts())
+    .execute(&ctx.db_pool)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Whether `existing` (with or without `.var`) is the package `wanted` at an
+/// equal or newer version. A `latest` or `min` request is never covered, since
+/// a newer version may exist upstream.
+fn covers_version(existing: &str, wanted: &str) -> bool {
+    let existing = existing.trim();
+    let existing = existing
+        .strip_suffix(".var")
+        .or_else(|| existing.strip_suffix(".VAR"))
+        .unwrap_or(existing);
+    let (Some((have_base, have)), Some((want_base, want))) =
+        (existing.rsplit_once('.'), wanted.rsplit_once('.'))
+    else {
+        return false;
+    };
+    if !have_base.eq_ignore_ascii_case(want_base) {
+        return false;
+    }
+    match (have.parse::<u64>(), want.parse::<u64>()) {
+        (Ok(have), Ok(want)) => have >= want,
+        _ => false,
+    }
+}
+
+/// Whether a download named `download_name` (with or without `.var`)
+/// satisfies the dependency reference `var_name`.
+fn download_matches_var(download_name: &str, var_name: &str) -> bool {
+    let name = download_name.trim();
+    let name = name
+        .strip_suffix(".var")
+        .or_else(|| name.strip_suffix(".VAR"))
+        .unwrap_or(name);
+    let Some((base, version)) = var_name.rsplit_once('.') else {
+        return false;
+    };
+    if version.eq_ignore_ascii_case("latest") || version.eq_ignore_ascii_case("min") {
+        return name
+            .rsplit_once('.')
+            .map(|(name_base, _)| name_base.eq_ignore_ascii_case(base))
+            .unwrap_or(false);
+    }
+    name.eq_ignore_ascii_case(var_name)
+}
+
+fn window_open(config: &Arc<RwLock<Config>>) -> bool {
+    let windows = config
+        .read()
+        .map(|cfg| cfg.download.windows.clone())
+        .unwrap_or_default();
+    in_download_window(&windows, Local::now().time())
+}
+
+pub(crate) fn parse_window_time(raw: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(raw.trim(), "%H:%M")
+        .map_err(|_| format!("invalid download window time '{}', expected HH:MM", raw))
+}
+
+fn in_download_window(windows: &[DownloadWindow], now: NaiveTime) -> bool {
+    if windows.is_empty() {
+        return true;
+    }
+    windows.iter().any(|window| {
+        let start = parse_window_time(&window.start);
+        let end = parse_window_time(&window.end);
+        let (Ok(start), Ok(end)) = (start, end) else {
+            return false;
+        };
+        if start < end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    })
+}
+
+/// The partial file of an interrupted download, when it can be continued:
+/// the row still has breakpoint data and the file is still in `save_dir`.
+async fn resumable_temp_path(
+    db_pool: &SqlitePool,
+    id: i64,
+    save_dir: &Path,
+) -> Result<Option<PathBuf>, String> {
+    let row = sqlx::query("SELECT temp_path, resume_data FROM downloads WHERE id = ?1")
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let temp_path: Option<String> = row.try_get("temp_path").ok().flatten();
+    let resume_data: Option<String> = row.try_get("resume_data").ok().flatten();
+    Ok(temp_path
+        .filter(|_| resume_data.is_some())
+        .map(PathBuf::from)
+        .filter(|path| path.parent() == Some(save_dir) && path.is_file()))
+}
+
+async fn clear_resume_data(db_pool: &SqlitePool, id: i64) -> Result<(), String> {
+    sqlx::query("UPDATE downloads SET resume_data = NULL WHERE id = ?1")
+        .bind(id)
+        .execute(db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+type ArchiveFuture<T> = Pin<Box<dyn Future<Output = anyhow::Result<T>> + Send>>;
+
+/// Keeps a download's chunk breakpoint data in its `downloads` row, next to
+/// `temp_path`, so a resume after a restart or crash continues from the last
+/// finished chunk.
+struct RowArchiverBuilder {
+    db_pool: SqlitePool,
+    id: i64,
+}
+
+struct RowArchiver {
+    db_pool: SqlitePool,
+    id: i64,
+    file_path: PathBuf,
+}
+
+impl DownloadDataArchiverBuilder for RowArchiverBuilder {
+    type DownloadDataArchiver = RowArchiver;
+
+    fn build(self, config: &HttpDownloadConfig) -> RowArchiver {
+        RowArchiver {
+            db_pool: self.db_pool,
+            id: self.id,
+            file_path: config.file_path(),
+        }
+    }
+}
+
+impl DownloadDataArchiver for RowArchiver {
+    fn save(&self, data: Box<DownloadArchiveData>) -> ArchiveFuture<()> {
+        let (db_pool, id) = (self.db_pool.clone(), self.id);
+        Box::pin(async move {
+            let json = serde_json::to_string(&*data)?;
+            sqlx::query("UPDATE downloads SET resume_data = ?1 WHERE id = ?2")
+                .bind(json)
+                .bind(id)
+                .execute(&db_pool)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn load(&self) -> ArchiveFuture<Option<Box<DownloadArchiveData>>> {
+        let (db_pool, id) = (self.db_pool.clone(), self.id);
+        let file_exists = self.file_path.is_file();
+        Box::pin(async move {
+            // The data describes the partial file; without it, start over.
+            if !file_exists {
+                return Ok(None);
+            }
+            let raw: Option<String> =
+                sqlx::query_scalar("SELECT resume_data FROM downloads WHERE id = ?1")
+                    .bind(id)
+                    .fetch_optional(&db_pool)
+                    .await?
+                    .flatten();
+            Ok(raw
+                .and_then(|raw| serde_json::from_str::<DownloadArchiveData>(&raw).ok())
+                .map(Box::new))
+        })
+    }
+}
+
+/// Partial file for download `id`. The id keeps two rows with the same URL
+/// file name from writing to, or clearing, each other's partial file.
+fn download_temp_path(id: i64, url: &Url, save_dir: &Path) -> PathBuf {
     let filename = url
         .path_segments()
         .and_then(|mut s| s.next_back())
-        .unwrap_or("unknown_temp_file");
-    save_dir.join(filename)
+        .filter(|segment| !segment.is_empty())
+        .unwrap_or("download");
+    save_dir.join(format!("{}_{}.part", id, filename))
 }
 
 fn read_runtime_config(config: &Arc<RwLock<Config>>) -> DownloadRuntimeConfig {
@@ -627,7 +1667,7 @@ fn read_runtime_config(config: &Arc<RwLock<Config>>) -> DownloadRuntimeConfig {
     }
 }
 
-fn hub_headers_compat() -> HeaderMap {
+fn hub_headers_compat(url: &Url) -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert(
         HeaderName::from_static("accept"),
@@ -643,10 +1683,9 @@ fn hub_headers_compat() -> HeaderMap {
         HeaderName::from_static("accept-language"),
         HeaderValue::from_static("en-US,en;q=0.9"),
     );
-    headers.insert(
-        HeaderName::from_static("cookie"),
-        HeaderValue::from_static("vamhubconsent=yes"),
-    );
+    if let Ok(cookie) = HeaderValue::from_str(&hub_auth::cookie_header(url.as_str())) {
+        headers.insert(HeaderName::from_static("cookie"), cookie);
+    }
     headers.insert(HeaderName::from_static("dnt"), HeaderValue::from_static("1"));
     headers.insert(
         HeaderName::from_static("sec-ch-ua"),
@@ -693,6 +1732,7 @@ fn hub_headers_compat() -> HeaderMap {
 
 async fn update_status(
     db_pool: &SqlitePool,
+    events: &DownloadEvents,
     id: i64,
     status: &str,
     error: Option<String>,
@@ -702,12 +1742,13 @@ async fn update_status(
         "UPDATE downloads SET status = ?1, error = ?2, updated_at = ?3 WHERE id = ?4",
     )
     .bind(status)
-    .bind(error)
+    .bind(&error)
     .bind(now)
     .bind(id)
     .execute(db_pool)
     .await
     .map_err(|err| err.to_string())?;
+    events.status(id, status, error);
     Ok(())
 }
 
@@ -782,3 +1823,310 @@ fn delete_file(path: &str) -> Result<(), String> {
 fn now_ts() -> i64 {
     chrono::Local::now().timestamp()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(start: &str, end: &str) -> DownloadWindow {
+        DownloadWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert!(in_download_window(&[], at(12, 0)));
+
+        let night = [window("23:00", "07:00")];
+        assert!(in_download_window(&night, at(23, 30)));
+        assert!(in_download_window(&night, at(1, 0)));
+        assert!(!in_download_window(&night, at(7, 0)));
+        assert!(!in_download_window(&night, at(12, 0)));
+
+        let early = [window("01:00", "07:00")];
+        assert!(in_download_window(&early, at(1, 0)));
+        assert!(!in_download_window(&early, at(0, 59)));
+    }
+
+    async fn test_manager(name: &str) -> (DownloadManager, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("vm_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(dir.join("varManager.db"))
+            .create_if_missing(true);
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        crate::infra::migrations::migrate(&pool, &dir.join("db_backups"))
+            .await
+            .unwrap();
+        let config = Arc::new(RwLock::new(Config::default()));
+        let (completed, _) = mpsc::unbounded_channel();
+        (DownloadManager::new(pool, config, completed), dir)
+    }
+
+    async fn insert_download(pool: &SqlitePool, id: i64, status: &str) {
+        sqlx::query(
+            "INSERT INTO downloads (id, url, status, priority, position, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 0, ?1, 0, 0)",
+        )
+        .bind(id)
+        .bind(format!("https://example.com/{}.var", id))
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn status_of(pool: &SqlitePool, id: i64) -> String {
+        sqlx::query_scalar("SELECT status FROM downloads WHERE id = ?1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn breakpoint_data_survives_restart_only_with_its_file() {
+        let (manager, dir) = test_manager("resume").await;
+        let pool = manager.db_pool.clone();
+        insert_download(&pool, 1, "downloading").await;
+        insert_download(&pool, 2, "queued").await;
+        insert_download(&pool, 3, "failed").await;
+
+        let url = Url::parse("https://hub.virtamate.com/resources/1/download").unwrap();
+        let temp = download_temp_path(1, &url, &dir);
+        assert_ne!(temp, download_temp_path(2, &url, &dir));
+        update_paths(&pool, 1, &dir.join("a.b.1.var"), &temp).await.unwrap();
+        let archiver = RowArchiver {
+            db_pool: pool.clone(),
+            id: 1,
+            file_path: temp.clone(),
+        };
+        let data = DownloadArchiveData {
+            downloaded_len: 4096,
+            downloading_duration: 7,
+            chunk_data: None,
+        };
+        archiver.save(Box::new(data)).await.unwrap();
+
+        // No partial file yet: the saved data is ignored.
+        assert!(archiver.load().await.unwrap().is_none());
+        assert_eq!(resumable_temp_path(&pool, 1, &dir).await.unwrap(), None);
+
+        std::fs::write(&temp, [0u8; 16]).unwrap();
+        let loaded = archiver.load().await.unwrap().unwrap();
+        assert_eq!(loaded.downloaded_len, 4096);
+        assert_eq!(loaded.downloading_duration, 7);
+        assert_eq!(resumable_temp_path(&pool, 1, &dir).await.unwrap(), Some(temp.clone()));
+        let elsewhere = dir.join("other");
+        assert_eq!(resumable_temp_path(&pool, 1, &elsewhere).await.unwrap(), None);
+
+        clear_resume_data(&pool, 1).await.unwrap();
+        assert_eq!(resumable_temp_path(&pool, 1, &dir).await.unwrap(), None);
+
+        manager.recover_incomplete(false).await.unwrap();
+        assert_eq!(status_of(&pool, 1).await, "paused");
+        assert_eq!(status_of(&pool, 2).await, "paused");
+        assert_eq!(status_of(&pool, 3).await, "failed");
+        sqlx::query("UPDATE downloads SET status = 'downloading' WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        manager.recover_incomplete(true).await.unwrap();
+        assert_eq!(status_of(&pool, 1).await, "queued");
+        assert_eq!(status_of(&pool, 2).await, "paused");
+        assert_eq!(status_of(&pool, 3).await, "failed");
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn events_report_status_progress_and_throughput() {
+        let (manager, dir) = test_manager("events").await;
+        let pool = manager.db_pool.clone();
+        insert_download(&pool, 1, "queued").await;
+        let mut rx = manager.subscribe();
+
+        update_status(&pool, &manager.events, 1, "downloading", None).await.unwrap();
+        manager.events.progress(1, 100, Some(1000), 40);
+        manager.events.progress(2, 10, None, 20);
+        manager.events.finished(1);
+        manager.events.finished(1);
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(serde_json::json!({ "name": event.name(), "data": event }));
+        }
+        let throughput = |active, speed_bytes| {
+            serde_json::json!({
+                "name": "throughput",
+                "data": { "type": "throughput", "active": active, "speed_bytes": speed_bytes },
+            })
+        };
+        assert_eq!(
+            events,
+            vec![
+                serde_json::json!({
+                    "name": "status",
+                    "data": { "type": "status", "id": 1, "status": "downloading", "error": null },
+                }),
+                serde_json::json!({
+                    "name": "progress",
+                    "data": {
+                        "type": "progress",
+                        "id": 1,
+                        "downloaded_bytes": 100,
+                        "total_bytes": 1000,
+                        "speed_bytes": 40,
+                    },
+                }),
+                throughput(1, 40),
+                serde_json::json!({
+                    "name": "progress",
+                    "data": {
+                        "type": "progress",
+                        "id": 2,
+                        "downloaded_bytes": 10,
+                        "total_bytes": null,
+                        "speed_bytes": 20,
+                    },
+                }),
+                throughput(2, 60),
+                // Finishing takes the download out of the sum, once.
+                throughput(1, 20),
+            ]
+        );
+        assert_eq!(status_of(&pool, 1).await, "downloading");
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn download_names_match_var_references() {
+        assert!(download_matches_var("Acme.Chair.3.var", "Acme.Chair.3"));
+        assert!(download_matches_var(" acme.chair.3.VAR ", "Acme.Chair.3"));
+        assert!(!download_matches_var("Acme.Chair.3.var", "Acme.Chair.2"));
+        assert!(download_matches_var("Acme.Chair.7.var", "Acme.Chair.latest"));
+        assert!(download_matches_var("Acme.Chair.1", "acme.chair.min"));
+        assert!(!download_matches_var("Acme.Chairs.1.var", "Acme.Chair.latest"));
+        assert!(!download_matches_var("Acme.Chair.3.var", "Chair"));
+    }
+
+    async fn queue_order(pool: &SqlitePool) -> Vec<(i64, i64)> {
+        sqlx::query_as(
+            "SELECT id, priority FROM downloads WHERE status = 'queued'
+             ORDER BY priority DESC, position, id",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn moves_and_prioritizes_keep_relative_order() {
+        let (manager, dir) = test_manager("queue_order").await;
+        let pool = manager.db_pool.clone();
+        for id in 1..=5 {
+            insert_download(&pool, id, "queued").await;
+        }
+        let ids = |order: Vec<(i64, i64)>| order.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+        manager.move_ids(&[4, 2], true).await.unwrap();
+        assert_eq!(ids(queue_order(&pool).await), vec![4, 2, 1, 3, 5]);
+        manager.move_ids(&[4, 1], false).await.unwrap();
+        assert_eq!(ids(queue_order(&pool).await), vec![2, 3, 5, 4, 1]);
+
+        manager.set_priority(&[5], 3).await.unwrap();
+        sqlx::query("UPDATE downloads SET name = 'Acme.Chair.2.var' WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE downloads SET name = 'Acme.Lamp.1.var' WHERE id = 3")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let names = ["Acme.Chair.latest".to_string(), "Acme.Lamp.1".to_string()];
+        let moved = manager.prioritize_vars(&names).await.unwrap();
+        assert_eq!(moved, vec![3, 1]);
+        assert_eq!(
+            queue_order(&pool).await,
+            vec![(3, 3), (1, 3), (5, 3), (2, 0), (4, 0)]
+        );
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn batch_enqueue_skips_what_is_already_there() {
+        let (manager, dir) = test_manager("enqueue").await;
+        let pool = manager.db_pool.clone();
+        manager.config.write().unwrap().downloader_save_path =
+            Some(dir.to_string_lossy().into_owned());
+        // Holding every slot keeps the queue from starting a download.
+        let slots = manager.semaphore.available_permits() as u32;
+        let _slots = Arc::clone(&manager.semaphore).acquire_many_owned(slots).await.unwrap();
+        sqlx::query(
+            "INSERT INTO vars (varName, creatorName, packageName)
+             VALUES ('Acme.Chair.3', 'Acme', 'Chair')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        std::fs::write(dir.join("Acme.Lamp.2.var"), b"").unwrap();
+        insert_download(&pool, 1, "paused").await;
+
+        let item = |url: &str, name: &str| DownloadEnqueueItem {
+            url: url.to_string(),
+            name: Some(name.to_string()),
+            size: None,
+        };
+        let outcomes = manager
+            .enqueue_each(vec![
+                item("https://example.com/1.var", "Acme.Table.1.var"),
+                item("https://hub.virtamate.com/a", "acme.chair.2.var"),
+                item("https://hub.virtamate.com/b", "Acme.Lamp.1.var"),
+                item("https://hub.virtamate.com/c", "Acme.Chair.4.var"),
+            ])
+            .await
+            .unwrap();
+        let reasons: Vec<Option<SkipReason>> = outcomes
+            .into_iter()
+            .map(|outcome| match outcome.unwrap() {
+                EnqueueOutcome::Queued => None,
+                EnqueueOutcome::Skipped(skipped) => Some(skipped.reason),
+            })
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                Some(SkipReason::AlreadyQueued),
+                Some(SkipReason::InLibrary),
+                Some(SkipReason::InSaveDir),
+                None,
+            ]
+        );
+        assert_eq!(status_of(&pool, 2).await, "queued");
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn library_versions_cover_equal_or_older_requests() {
+        assert!(covers_version("Acme.Chair.3", "Acme.Chair.3"));
+        assert!(covers_version("acme.chair.5.var", "Acme.Chair.3"));
+        assert!(!covers_version("Acme.Chair.2", "Acme.Chair.3"));
+        assert!(!covers_version("Acme.Table.9", "Acme.Chair.3"));
+        assert!(!covers_version("Acme.Chair.9", "Acme.Chair.latest"));
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use crate::app::{app_root, AppState};
+use crate::infra::hub_auth;
 use crate::infra::paths::addon_packages_dir;
 use crate::jobs::job_channel::JobReporter;
 use http_downloader::{
@@ -15,11 +16,12 @@ use reqwest::{header, Client, StatusCode};
 use serde::Serialize;
 use std::collections::HashSet;
 use std::fs;
+use std::io::Read;
 use std::num::{NonZeroU8, NonZeroUsize};
 use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
-    Arc,
+    Arc, OnceLock,
 };
 use std::time::Duration;
 use tokio::sync::Semaphore;
@@ -225,7 +227,7 @@ async fn download_one(
     client: Arc<Client>,
 ) -> Result<String, String> {
     let final_url = resolve_final_url(&url_to_download, &client).await?;
-    let filename = resolve_filename(&final_url, &client).await?;
+    let (filename, head_size) = resolve_file_info(&final_url, &client).await?;
     let download_url_obj = Url::parse(&final_url).map_err(|err| err.to_string())?;
 
     let mut attempt: u8 = 0;
@@ -254,7 +256,21 @@ async fn download_one(
         .await;
 
         match result {
-            Ok(Ok(_dec)) => break,
+            Ok(Ok(_dec)) => {
+                finalize_download(&download_url_obj, &save_dir, &filename)?;
+                let saved = save_dir.join(&filename);
+                match verify_var_download(&saved, head_size) {
+                    Ok(()) => return Ok(filename),
+                    Err(err) => {
+                        let _ = fs::remove_file(&saved);
+                        if attempt <= MAX_DOWNLOAD_RETRIES {
+                            tokio::time::sleep(Duration::from_secs(2)).await;
+                            continue;
+                        }
+                        return Err(format!("corrupt download: {}", err));
+                    }
+                }
+            }
             Ok(Err(err)) => {
                 if attempt <= MAX_DOWNLOAD_RETRIES && is_retryable_error(&err) {
                     tokio::time::sleep(Duration::from_secs(2)).await;
@@ -274,9 +290,6 @@ async fn download_one(
             }
         }
     }
-
-    finalize_download(&download_url_obj, &save_dir, &filename)?;
-    Ok(filename)
 }
 
 pub(crate) async fn resolve_final_url(url: &str, client: &Client) -> Result<String, String> {
@@ -293,15 +306,25 @@ pub(crate) async fn resolve_final_url_with_retry(
     }
 
     let mut attempt: u8 = 0;
+    let mut relogged = false;
     loop {
         attempt += 1;
         let result = async {
             let response = client
                 .get(url)
-                .headers(hub_headers())
+                .headers(hub_headers(url))
                 .send()
                 .await
                 .map_err(|err| err.to_string())?;
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            if hub_auth::is_auth_response(url, response.status(), content_type) {
+                let status = response.status();
+                return Err(format!("{}: GET {} returned {}", hub_auth::AUTH_ERROR, url, status));
+            }
             if response.status() == StatusCode::SEE_OTHER {
                 if let Some(location) = response.headers().get(header::LOCATION) {
                     let location_str = location.to_str().map_err(|err| err.to_string())?;
@@ -320,6 +343,13 @@ pub(crate) async fn resolve_final_url_with_retry(
         match result {
             Ok(url) => return Ok(url),
             Err(err) => {
+                // One fresh login per resolve, in case the session expired.
+                if hub_auth::is_auth_error(&err) && !relogged && hub_auth::can_refresh() {
+                    relogged = true;
+                    if hub_auth::refresh_session().await.is_ok() {
+                        continue;
+                    }
+                }
                 if attempt <= max_retries && is_retryable_error(&err) {
                     tokio::time::sleep(Duration::from_secs(2)).await;
                     continue;
@@ -341,7 +371,7 @@ pub(crate) async fn resolve_file_info(
 ) -> Result<(String, Option<u64>), String> {
     let response = client
         .head(url)
-        .headers(hub_headers())
+        .headers(hub_headers(url))
         .send()
         .await
         .map_err(|err| err.to_string())?;
@@ -396,21 +426,28 @@ pub(crate) fn finalize_download(url: &Url, save_dir: &Path, filename: &str) -> R
             .and_then(|mut s| s.next_back())
             .unwrap_or("unknown_temp_file"),
     );
-    let new_file_path = save_dir.join(filename);
+    finalize_temp_file(&downloaded_file_path, &save_dir.join(filename))
+}
 
+/// Move a finished download from the file it was written to onto its final
+/// name.
+pub(crate) fn finalize_temp_file(
+    downloaded_file_path: &Path,
+    new_file_path: &Path,
+) -> Result<(), String> {
     if downloaded_file_path == new_file_path {
-        verify_file_size(&new_file_path)?;
+        verify_file_size(new_file_path)?;
         return Ok(());
     }
 
     if downloaded_file_path.exists() {
-        verify_file_size(&downloaded_file_path)?;
-        fs::rename(&downloaded_file_path, &new_file_path).map_err(|err| err.to_string())?;
+        verify_file_size(downloaded_file_path)?;
+        fs::rename(downloaded_file_path, new_file_path).map_err(|err| err.to_string())?;
         return Ok(());
     }
 
     if new_file_path.exists() {
-        verify_file_size(&new_file_path)?;
+        verify_file_size(new_file_path)?;
         return Ok(());
     }
 
@@ -428,6 +465,73 @@ fn verify_file_size(path: &Path) -> Result<(), String> {
     Ok(())
 }
 
+/// Check a finished download before it is handed to the library: the size
+/// must match what the server announced, and a `.var` must open as a zip with
+/// a readable meta.json whose creator/package agree with the filename. Hub
+/// answers some failures with an HTML page or a truncated archive that still
+/// carries a `.var` name.
+pub(crate) fn verify_var_download(path: &Path, expected_size: Option<u64>) -> Result<(), String> {
+    let len = fs::metadata(path).map_err(|err| err.to_string())?.len();
+    if let Some(expected) = expected_size {
+        if len != expected {
+            return Err(format!("size mismatch: expected {} bytes, got {}", expected, len));
+        }
+    }
+    let is_var = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("var"))
+        .unwrap_or(false);
+    if !is_var {
+        return Ok(());
+    }
+    verify_var_package(path)
+}
+
+/// Check that `path` opens as a zip archive with a readable meta.json. Its
+/// contents are not parsed strictly: many published packages carry meta.json
+/// files that only VaM's lenient reader accepts, so only the creator/package
+/// fields are looked up, and only when present.
+pub(crate) fn verify_var_package(path: &Path) -> Result<(), String> {
+    let file = fs::File::open(path).map_err(|err| err.to_string())?;
+    let mut zip = zip::ZipArchive::new(std::io::BufReader::new(file))
+        .map_err(|err| format!("zip open failed: {}", err))?;
+    let mut meta = String::new();
+    zip.by_name("meta.json")
+        .map_err(|_| "meta.json not found".to_string())?
+        .read_to_string(&mut meta)
+        .map_err(|err| format!("meta.json unreadable: {}", err))?;
+
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return Ok(());
+    };
+    let parts: Vec<&str> = stem.split('.').collect();
+    if parts.len() != 3 {
+        return Ok(());
+    }
+    for (field, expected) in [("creatorName", parts[0]), ("packageName", parts[1])] {
+        if let Some(actual) = meta_string_field(&meta, field) {
+            if !actual.is_empty() && !actual.eq_ignore_ascii_case(expected) {
+                return Err(format!(
+                    "meta.json {} '{}' does not match filename '{}'",
+                    field, actual, expected
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn meta_string_field(meta: &str, field: &str) -> Option<String> {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    let re = FIELD.get_or_init(|| {
+        Regex::new(r#""(creatorName|packageName)"\s*:\s*"([^"]*)""#).expect("valid meta regex")
+    });
+    re.captures_iter(meta)
+        .find(|caps| &caps[1] == field)
+        .map(|caps| caps[2].trim().to_string())
+}
+
 pub(crate) fn is_retryable_error(err: &str) -> bool {
     let lower = err.to_lowercase();
     lower.contains("error sending request")
@@ -436,7 +540,9 @@ pub(crate) fn is_retryable_error(err: &str) -> bool {
         || lower.contains("dns")
 }
 
-pub(crate) fn hub_headers() -> header::HeaderMap {
+/// Browser-like headers for `url`, with the Hub session when `url` is on the
+/// Hub.
+pub(crate) fn hub_headers(url: &str) -> header::HeaderMap {
     let mut headers = header::HeaderMap::new();
     headers.insert(
         header::ACCEPT,
@@ -452,7 +558,9 @@ pub(crate) fn hub_headers() -> header::HeaderMap {
         header::ACCEPT_LANGUAGE,
         "en-US,en;q=0.9".parse().unwrap(),
     );
-    headers.insert(header::COOKIE, "vamhubconsent=yes".parse().unwrap());
+    if let Ok(cookie) = header::HeaderValue::from_str(&hub_auth::cookie_header(url)) {
+        headers.insert(header::COOKIE, cookie);
+    }
     headers.insert(header::DNT, "1".parse().unwrap());
     headers.insert(
         header::HeaderName::from_static("sec-ch-ua"),
@@ -528,3 +636,59 @@ pub(crate) fn resolve_download_save_path_config(cfg: &crate::app::Config) -> Res
         .ok_or_else(|| "vampath is required in config.json".to_string())?;
     Ok(addon_packages_dir(&vampath))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_var(path: &Path, meta: Option<&str>) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        if let Some(meta) = meta {
+            zip.start_file("meta.json", options).unwrap();
+            zip.write_all(meta.as_bytes()).unwrap();
+        }
+        zip.start_file("Saves/scene/a.json", options).unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn rejects_downloads_that_are_not_the_package() {
+        let dir = std::env::temp_dir().join(format!("vm_verify_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let meta = r#"{ "creatorName" : "Acme", "packageName":"Chair", "licenseType": "CC BY" }"#;
+
+        let good = dir.join("Acme.Chair.1.var");
+        write_var(&good, Some(meta));
+        let len = fs::metadata(&good).unwrap().len();
+        assert_eq!(verify_var_download(&good, Some(len)), Ok(()));
+        assert_eq!(verify_var_download(&good, None), Ok(()));
+
+        let err = verify_var_download(&good, Some(len + 1)).unwrap_err();
+        assert!(err.starts_with("size mismatch"), "{}", err);
+
+        let html = dir.join("Acme.Html.1.var");
+        fs::write(&html, "<!DOCTYPE html><html><body>Log in</body></html>").unwrap();
+        let err = verify_var_download(&html, None).unwrap_err();
+        assert!(err.starts_with("zip open failed"), "{}", err);
+
+        let truncated = dir.join("Acme.Cut.1.var");
+        fs::write(&truncated, &fs::read(&good).unwrap()[..len as usize / 2]).unwrap();
+        let err = verify_var_download(&truncated, None).unwrap_err();
+        assert!(err.starts_with("zip open failed"), "{}", err);
+
+        let no_meta = dir.join("Acme.Bare.1.var");
+        write_var(&no_meta, None);
+        assert_eq!(verify_var_download(&no_meta, None), Err("meta.json not found".to_string()));
+
+        let renamed = dir.join("Other.Chair.1.var");
+        fs::copy(&good, &renamed).unwrap();
+        let err = verify_var_download(&renamed, None).unwrap_err();
+        assert!(err.contains("creatorName 'Acme'"), "{}", err);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,314 @@
+//! Hub login for paid and early-access resources. Credentials, pasted browser
+//! cookies and the session obtained by logging in are kept in the data dir,
+//! encrypted for the current Windows user. The session is only ever sent to
+//! the Hub itself, never to the CDN a download redirects to or to mirrors.
+
+use crate::app::data_dir;
+use crate::infra::winfs::{protect_data, unprotect_data};
+use regex::Regex;
+use reqwest::{header, redirect, Client};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use url::Url;
+
+const HUB_AUTH_FILE: &str = "hub_auth.bin";
+const HUB_HOST: &str = "hub.virtamate.com";
+const HUB_LOGIN_PAGE: &str = "https://hub.virtamate.com/login/";
+const HUB_LOGIN_POST: &str = "https://hub.virtamate.com/login/login";
+const CONSENT_COOKIE: &str = "vamhubconsent=yes";
+/// Cookie XenForo sets once a login is accepted.
+const LOGIN_COOKIE: &str = "xf_user";
+/// Marks errors caused by a missing or rejected Hub login; see
+/// [`is_auth_error`].
+pub const AUTH_ERROR: &str = "hub login required";
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct HubAuth {
+    username: Option<String>,
+    password: Option<String>,
+    /// `Cookie` header value copied from a logged-in browser.
+    cookies: Option<String>,
+    /// Cookies from the last successful login.
+    session: Option<String>,
+}
+
+/// What `GET /hub/auth` reports; secrets are never echoed back.
+#[derive(Clone, Debug, Serialize)]
+pub struct HubAuthStatus {
+    pub username: Option<String>,
+    pub has_password: bool,
+    pub has_cookies: bool,
+    pub has_session: bool,
+}
+
+/// A change to the saved login. `None` keeps a field, an empty string clears
+/// it.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct HubAuthUpdate {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub cookies: Option<String>,
+}
+
+fn auth_path() -> PathBuf {
+    data_dir().join(HUB_AUTH_FILE)
+}
+
+fn auth_state() -> &'static RwLock<HubAuth> {
+    static STATE: OnceLock<RwLock<HubAuth>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        let auth = load().unwrap_or_else(|err| {
+            tracing::warn!(error = %err, "hub login could not be read; starting logged out");
+            HubAuth::default()
+        });
+        RwLock::new(auth)
+    })
+}
+
+fn load() -> Result<HubAuth, String> {
+    let path = auth_path();
+    if !path.exists() {
+        return Ok(HubAuth::default());
+    }
+    let encrypted = fs::read(&path).map_err(|err| err.to_string())?;
+    let plain = unprotect_data(&encrypted)?;
+    serde_json::from_slice(&plain).map_err(|err| err.to_string())
+}
+
+fn store(auth: HubAuth) -> Result<(), String> {
+    let path = auth_path();
+    let empty = auth.username.is_none()
+        && auth.password.is_none()
+        && auth.cookies.is_none()
+        && auth.session.is_none();
+    if empty {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|err| err.to_string())?;
+        }
+    } else {
+        let plain = serde_json::to_vec(&auth).map_err(|err| err.to_string())?;
+        fs::write(&path, protect_data(&plain)?).map_err(|err| err.to_string())?;
+    }
+    *auth_state()
+        .write()
+        .map_err(|_| "hub auth lock poisoned".to_string())? = auth;
+    Ok(())
+}
+
+fn current() -> HubAuth {
+    auth_state()
+        .read()
+        .map(|auth| auth.clone())
+        .unwrap_or_default()
+}
+
+pub fn status() -> HubAuthStatus {
+    let auth = current();
+    HubAuthStatus {
+        username: auth.username,
+        has_password: auth.password.is_some(),
+        has_cookies: auth.cookies.is_some(),
+        has_session: auth.session.is_some(),
+    }
+}
+
+/// Save `update` and, when a username and password are set, log in with them
+/// so the session is ready for the next download.
+pub async fn update(update: HubAuthUpdate) -> Result<HubAuthStatus, String> {
+    let mut auth = current();
+    let normalize = |value: String| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+    let credentials_changed = update.username.is_some() || update.password.is_some();
+    if let Some(username) = update.username {
+        auth.username = normalize(username);
+    }
+    if let Some(password) = update.password {
+        auth.password = Some(password).filter(|v| !v.is_empty());
+    }
+    if let Some(cookies) = update.cookies {
+        let cookies = normalize(cookies);
+        if let Some(cookies) = cookies.as_deref() {
+            header::HeaderValue::from_str(cookies)
+                .map_err(|_| "cookies contain characters not allowed in a header".to_string())?;
+        }
+        auth.cookies = cookies;
+    }
+    if credentials_changed {
+        auth.session = None;
+    }
+    store(auth)?;
+    if credentials_changed && has_credentials() {
+        refresh_session().await?;
+    }
+    Ok(status())
+}
+
+pub fn clear() -> Result<(), String> {
+    store(HubAuth::default())
+}
+
+fn has_credentials() -> bool {
+    let auth = current();
+    auth.username.is_some() && auth.password.is_some()
+}
+
+/// Log in again with the saved username and password, replacing the session.
+pub async fn refresh_session() -> Result<(), String> {
+    let auth = current();
+    let (Some(username), Some(password)) = (auth.username.clone(), auth.password.clone()) else {
+        return Err(format!("{}: no Hub username and password saved", AUTH_ERROR));
+    };
+    let session = login(&username, &password).await?;
+    store(HubAuth {
+        session: Some(session),
+        ..auth
+    })
+}
+
+async fn login(username: &str, password: &str) -> Result<String, String> {
+    let client = Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|err| err.to_string())?;
+    let page = client
+        .get(HUB_LOGIN_PAGE)
+        .header(header::COOKIE, CONSENT_COOKIE)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut jar = Vec::new();
+    collect_cookies(page.headers(), &mut jar);
+    let html = page.text().await.map_err(|err| err.to_string())?;
+    let token = login_token(&html).ok_or_else(|| "hub login page has no form token".to_string())?;
+
+    let form = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("login", username)
+        .append_pair("password", password)
+        .append_pair("remember", "1")
+        .append_pair("_xfToken", &token)
+        .finish();
+    let response = client
+        .post(HUB_LOGIN_POST)
+        .header(header::COOKIE, join_cookies(&jar))
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(form)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    collect_cookies(response.headers(), &mut jar);
+    if !jar.iter().any(|(name, _)| name == LOGIN_COOKIE) {
+        return Err(format!("{}: Hub rejected the username or password", AUTH_ERROR));
+    }
+    Ok(jar
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+fn login_token(html: &str) -> Option<String> {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    let re = TOKEN.get_or_init(|| {
+        Regex::new(r#"name="_xfToken"\s+value="([^"]+)""#).expect("valid token regex")
+    });
+    re.captures(html).map(|caps| caps[1].to_string())
+}
+
+fn collect_cookies(headers: &header::HeaderMap, jar: &mut Vec<(String, String)>) {
+    for value in headers.get_all(header::SET_COOKIE) {
+        let Some((name, value)) = value
+            .to_str()
+            .ok()
+            .and_then(|raw| raw.split(';').next())
+            .and_then(|pair| pair.split_once('='))
+        else {
+            continue;
+        };
+        let (name, value) = (name.trim().to_string(), value.trim().to_string());
+        jar.retain(|(existing, _)| *existing != name);
+        if !value.is_empty() && value != "deleted" {
+            jar.push((name, value));
+        }
+    }
+}
+
+fn join_cookies(jar: &[(String, String)]) -> String {
+    std::iter::once(CONSENT_COOKIE.to_string())
+        .chain(jar.iter().map(|(name, value)| format!("{}={}", name, value)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// `url` is on the Hub over https, so the session never travels in clear.
+fn is_hub_url(url: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "https")
+        .and_then(|url| url.host_str().map(|host| host.eq_ignore_ascii_case(HUB_HOST)))
+        .unwrap_or(false)
+}
+
+/// `Cookie` header for a request to `url`: the consent cookie, plus the saved
+/// cookies and session when `url` is on the Hub.
+pub fn cookie_header(url: &str) -> String {
+    if !is_hub_url(url) {
+        return CONSENT_COOKIE.to_string();
+    }
+    let auth = current();
+    std::iter::once(CONSENT_COOKIE)
+        .chain(auth.cookies.as_deref())
+        .chain(auth.session.as_deref())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Whether the Hub answered `url` as if we were logged out: 401/403, or a
+/// page instead of the redirect to the file.
+pub fn is_auth_response(url: &str, status: reqwest::StatusCode, content_type: &str) -> bool {
+    if !is_hub_url(url) {
+        return false;
+    }
+    status == reqwest::StatusCode::UNAUTHORIZED
+        || status == reqwest::StatusCode::FORBIDDEN
+        || (status.is_success() && content_type.starts_with("text/html"))
+}
+
+pub fn is_auth_error(err: &str) -> bool {
+    err.contains(AUTH_ERROR)
+}
+
+/// Whether a failed download is worth retrying after logging in again.
+pub fn can_refresh() -> bool {
+    has_credentials()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_only_goes_to_the_hub() {
+        let mut jar = Vec::new();
+        let mut headers = header::HeaderMap::new();
+        headers.append(header::SET_COOKIE, "xf_csrf=abc; path=/".parse().unwrap());
+        headers.append(header::SET_COOKIE, "xf_user=42%2Cdef; path=/; secure".parse().unwrap());
+        collect_cookies(&headers, &mut jar);
+        assert_eq!(join_cookies(&jar), "vamhubconsent=yes; xf_csrf=abc; xf_user=42%2Cdef");
+
+        let html = r#"<input type="hidden" name="_xfToken" value="1700000000,abc" />"#;
+        assert_eq!(login_token(html).as_deref(), Some("1700000000,abc"));
+
+        assert!(is_hub_url("https://hub.virtamate.com/resources/1/download"));
+        assert!(!is_hub_url("https://s3cdn.virtamate.com/data/a.var"));
+        assert!(!is_hub_url("http://hub.virtamate.com/resources/1/download"));
+        assert_eq!(cookie_header("http://192.168.1.2:57123/lan/vars/a.b.1.var"), CONSENT_COOKIE);
+        assert!(is_auth_response(
+            "https://hub.virtamate.com/resources/1/download",
+            reqwest::StatusCode::OK,
+            "text/html; charset=utf-8",
+        ));
+    }
+}
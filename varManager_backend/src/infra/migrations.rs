@@ -0,0 +1,631 @@
+//! Versioned schema migrations.
+//!
+//! Migrations run in order, each in its own transaction, and the applied
+//! versions are recorded in `schema_version`. Databases created before this
+//! table existed start from version 0: every step is written so that running
+//! it against a schema that already has the change is a no-op.
+//!
+//! Before the first pending migration runs on a database that already has
+//! tables, a copy is written to `db_backups/` with `VACUUM INTO`.
+
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use std::path::Path;
+
+pub const DB_BACKUP_DIR: &str = "db_backups";
+
+enum Step {
+    Sql(&'static str),
+    /// Add a column unless the table already has it.
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        decl: &'static str,
+    },
+    /// Move a table whose layout predates `column` out of the way, keeping
+    /// its rows, so the current layout can be created under the old name.
+    RenameIfMissingColumn {
+        table: &'static str,
+        column: &'static str,
+        rename_to: &'static str,
+    },
+}
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    steps: &'static [Step],
+}
+
+const INITIAL_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS dependencies (
+    ID INTEGER PRIMARY KEY AUTOINCREMENT,
+    varName TEXT,
+    dependency TEXT
+);
+CREATE TABLE IF NOT EXISTS HideFav (
+    varName TEXT NOT NULL,
+    scenePath TEXT NOT NULL,
+    hide INTEGER NOT NULL,
+    fav INTEGER NOT NULL,
+    PRIMARY KEY (varName, scenePath)
+);
+CREATE TABLE IF NOT EXISTS installStatus (
+    varName TEXT PRIMARY KEY,
+    installed INTEGER NOT NULL,
+    disabled INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS savedepens (
+    ID INTEGER PRIMARY KEY AUTOINCREMENT,
+    varName TEXT,
+    dependency TEXT,
+    SavePath TEXT,
+    ModiDate TEXT
+);
+CREATE TABLE IF NOT EXISTS scenes (
+    ID INTEGER PRIMARY KEY AUTOINCREMENT,
+    varName TEXT,
+    atomType TEXT,
+    previewPic TEXT,
+    scenePath TEXT,
+    isPreset INTEGER NOT NULL,
+    isLoadable INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS vars (
+    varName TEXT PRIMARY KEY,
+    creatorName TEXT,
+    packageName TEXT,
+    metaDate TEXT,
+    varDate TEXT,
+    version TEXT,
+    description TEXT,
+    morph INTEGER,
+    cloth INTEGER,
+    hair INTEGER,
+    skin INTEGER,
+    pose INTEGER,
+    scene INTEGER,
+    script INTEGER,
+    plugin INTEGER,
+    asset INTEGER,
+    texture INTEGER,
+    look INTEGER,
+    subScene INTEGER,
+    appearance INTEGER,
+    dependencyCnt INTEGER,
+    fsize REAL
+);
+CREATE TABLE IF NOT EXISTS image_cache_entries (
+    cache_key TEXT PRIMARY KEY,
+    file_name TEXT NOT NULL,
+    source_type TEXT NOT NULL,
+    source_url TEXT,
+    source_root TEXT,
+    source_path TEXT,
+    size_bytes INTEGER NOT NULL,
+    content_type TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    last_accessed INTEGER NOT NULL,
+    access_count INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS downloads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    name TEXT,
+    status TEXT NOT NULL,
+    downloaded_bytes INTEGER NOT NULL DEFAULT 0,
+    total_bytes INTEGER,
+    speed_bytes INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    save_path TEXT,
+    temp_path TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+"#;
+
+const INITIAL_INDEXES: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_vars_creatorName ON vars(creatorName);
+CREATE INDEX IF NOT EXISTS idx_vars_packageName ON vars(packageName);
+CREATE INDEX IF NOT EXISTS idx_vars_metaDate ON vars(metaDate);
+CREATE INDEX IF NOT EXISTS idx_vars_varDate ON vars(varDate);
+CREATE INDEX IF NOT EXISTS idx_vars_fsize ON vars(fsize);
+CREATE INDEX IF NOT EXISTS idx_vars_dependencyCnt ON vars(dependencyCnt);
+CREATE INDEX IF NOT EXISTS idx_scenes_varName ON scenes(varName);
+CREATE INDEX IF NOT EXISTS idx_scenes_atomType ON scenes(atomType);
+CREATE INDEX IF NOT EXISTS idx_dependencies_varName ON dependencies(varName);
+CREATE INDEX IF NOT EXISTS idx_dependencies_dependency ON dependencies(dependency);
+CREATE INDEX IF NOT EXISTS idx_savedepens_dependency ON savedepens(dependency);
+CREATE INDEX IF NOT EXISTS idx_image_cache_last_accessed ON image_cache_entries(last_accessed);
+CREATE INDEX IF NOT EXISTS idx_downloads_status ON downloads(status);
+CREATE INDEX IF NOT EXISTS idx_downloads_created_at ON downloads(created_at);
+"#;
+
+const SEARCH_INDEX: &str = r#"
+CREATE TABLE IF NOT EXISTS entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    varName TEXT NOT NULL,
+    path TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_entries_varName ON entries(varName);
+CREATE VIRTUAL TABLE IF NOT EXISTS vars_fts USING fts5(
+    varName UNINDEXED,
+    creatorName,
+    packageName,
+    description,
+    tags,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS entries_fts USING fts5(
+    path,
+    content = 'entries',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+CREATE TRIGGER IF NOT EXISTS entries_fts_insert AFTER INSERT ON entries BEGIN
+    INSERT INTO entries_fts(rowid, path) VALUES (new.id, new.path);
+END;
+CREATE TRIGGER IF NOT EXISTS entries_fts_delete AFTER DELETE ON entries BEGIN
+    INSERT INTO entries_fts(entries_fts, rowid, path) VALUES ('delete', old.id, old.path);
+END;
+"#;
+
+/// Key `vars_fts` rows by the rowid of their `vars` row instead of looking
+/// them up by the unindexed `varName` column. Rows without a var are dropped.
+const VARS_FTS_ROWID: &str = r#"
+CREATE TEMP TABLE vars_fts_rekey AS
+    SELECT v.rowid AS rid, f.varName, f.creatorName, f.packageName, f.description, f.tags
+    FROM vars_fts f JOIN vars v ON v.varName = f.varName
+    GROUP BY v.rowid;
+DELETE FROM vars_fts;
+INSERT INTO vars_fts (rowid, varName, creatorName, packageName, description, tags)
+    SELECT rid, varName, creatorName, packageName, description, tags FROM vars_fts_rekey;
+DROP TABLE vars_fts_rekey;
+"#;
+
+/// Install status and hide/fav flags describe one VaM installation, so they
+/// are keyed by profile. Existing rows belong to `default`.
+const VAM_PROFILES: &str = r#"
+CREATE TABLE installStatus_profiled (
+    profile TEXT NOT NULL,
+    varName TEXT NOT NULL,
+    installed INTEGER NOT NULL,
+    disabled INTEGER NOT NULL,
+    PRIMARY KEY (profile, varName)
+);
+INSERT INTO installStatus_profiled (profile, varName, installed, disabled)
+    SELECT 'default', varName, installed, disabled FROM installStatus;
+DROP TABLE installStatus;
+ALTER TABLE installStatus_profiled RENAME TO installStatus;
+CREATE TABLE HideFav_profiled (
+    profile TEXT NOT NULL,
+    varName TEXT NOT NULL,
+    scenePath TEXT NOT NULL,
+    hide INTEGER NOT NULL,
+    fav INTEGER NOT NULL,
+    PRIMARY KEY (profile, varName, scenePath)
+);
+INSERT INTO HideFav_profiled (profile, varName, scenePath, hide, fav)
+    SELECT 'default', varName, scenePath, hide, fav FROM HideFav;
+DROP TABLE HideFav;
+ALTER TABLE HideFav_profiled RENAME TO HideFav;
+"#;
+
+/// Completed downloads, kept after their queue rows are removed.
+const DOWNLOAD_HISTORY: &str = r#"
+CREATE TABLE IF NOT EXISTS download_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    download_id INTEGER,
+    url TEXT NOT NULL,
+    var_name TEXT,
+    source TEXT,
+    save_path TEXT,
+    size_bytes INTEGER,
+    completed_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_download_history_var_name ON download_history(var_name);
+CREATE INDEX IF NOT EXISTS idx_download_history_completed_at
+    ON download_history(completed_at);
+"#;
+
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "hide_fav_per_scene",
+        steps: &[Step::RenameIfMissingColumn {
+            table: "HideFav",
+            column: "scenePath",
+            rename_to: "HideFav_legacy",
+        }],
+    },
+    Migration {
+        version: 2,
+        name: "initial_schema",
+        steps: &[Step::Sql(INITIAL_SCHEMA)],
+    },
+    Migration {
+        version: 3,
+        name: "initial_indexes",
+        steps: &[
+            Step::AddColumn {
+                table: "vars",
+                column: "fsize",
+                decl: "REAL",
+            },
+            Step::AddColumn {
+                table: "downloads",
+                column: "temp_path",
+                decl: "TEXT",
+            },
+            Step::Sql(INITIAL_INDEXES),
+        ],
+    },
+    Migration {
+        version: 4,
+        name: "search_index",
+        steps: &[Step::Sql(SEARCH_INDEX)],
+    },
+    Migration {
+        version: 5,
+        name: "entry_metadata",
+        steps: &[
+            Step::AddColumn {
+                table: "entries",
+                column: "size",
+                decl: "INTEGER",
+            },
+            Step::AddColumn {
+                table: "entries",
+                column: "compressedSize",
+                decl: "INTEGER",
+            },
+            Step::AddColumn {
+                table: "entries",
+                column: "crc",
+                decl: "INTEGER",
+            },
+            Step::AddColumn {
+                table: "entries",
+                column: "contentType",
+                decl: "TEXT",
+            },
+            Step::Sql(
+                "CREATE INDEX IF NOT EXISTS idx_entries_path ON entries(path COLLATE NOCASE);
+                 CREATE INDEX IF NOT EXISTS idx_entries_contentType ON entries(contentType);",
+            ),
+        ],
+    },
+    Migration {
+        version: 6,
+        name: "var_location",
+        steps: &[Step::AddColumn {
+            table: "vars",
+            column: "varPath",
+            decl: "TEXT",
+        }],
+    },
+    Migration {
+        version: 7,
+        name: "var_root",
+        steps: &[Step::AddColumn {
+            table: "vars",
+            column: "varRoot",
+            decl: "TEXT",
+        }],
+    },
+    Migration {
+        version: 8,
+        name: "vam_profiles",
+        steps: &[Step::Sql(VAM_PROFILES)],
+    },
+    Migration {
+        version: 9,
+        name: "download_queue_order",
+        steps: &[
+            Step::AddColumn {
+                table: "downloads",
+                column: "priority",
+                decl: "INTEGER NOT NULL DEFAULT 0",
+            },
+            Step::AddColumn {
+                table: "downloads",
+                column: "position",
+                decl: "INTEGER NOT NULL DEFAULT 0",
+            },
+            Step::Sql(
+                "UPDATE downloads SET position = id;
+                 CREATE INDEX IF NOT EXISTS idx_downloads_queue
+                     ON downloads(status, priority DESC, position);",
+            ),
+        ],
+    },
+    Migration {
+        version: 10,
+        name: "download_history",
+        steps: &[Step::Sql(DOWNLOAD_HISTORY)],
+    },
+    Migration {
+        version: 11,
+        name: "download_resume_data",
+        steps: &[Step::AddColumn {
+            table: "downloads",
+            column: "resume_data",
+            decl: "TEXT",
+        }],
+    },
+    Migration {
+        version: 12,
+        name: "vars_fts_rowid",
+        steps: &[Step::Sql(VARS_FTS_ROWID)],
+    },
+];
+
+pub async fn current_version(pool: &SqlitePool) -> Result<i64, String> {
+    sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
+        .await
+        .map(|version| version.unwrap_or(0))
+        .map_err(|err| err.to_string())
+}
+
+/// Apply pending migrations. `backup_dir` receives a copy of the database
+/// before the first one runs.
+pub async fn migrate(pool: &SqlitePool, backup_dir: &Path) -> Result<(), String> {
+    migrate_to(pool, backup_dir, i64::MAX).await
+}
+
+async fn migrate_to(pool: &SqlitePool, backup_dir: &Path, target: i64) -> Result<(), String> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+    )
+    .execute(pool)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let current = current_version(pool).await?;
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current && m.version <= target)
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+    if has_user_tables(pool).await? {
+        backup_before_migrate(pool, backup_dir, current).await?;
+    }
+
+    for migration in pending {
+        let mut tx = pool.begin().await.map_err(|err| err.to_string())?;
+        for step in migration.steps {
+            apply_step(&mut tx, step).await.map_err(|err| {
+                format!(
+                    "migration {} ({}) failed: {}",
+                    migration.version, migration.name, err
+                )
+            })?;
+        }
+        sqlx::query("INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| err.to_string())?;
+        tx.commit().await.map_err(|err| err.to_string())?;
+        tracing::info!(version = migration.version, name = migration.name, "schema migrated");
+    }
+    Ok(())
+}
+
+async fn apply_step(conn: &mut SqliteConnection, step: &Step) -> Result<(), String> {
+    match step {
+        Step::Sql(sql) => {
+            sqlx::query(sql)
+                .execute(&mut *conn)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        Step::AddColumn {
+            table,
+            column,
+            decl,
+        } => {
+            if !has_column(conn, table, column).await? {
+                let sql = format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl);
+                sqlx::query(&sql)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        Step::RenameIfMissingColumn {
+            table,
+            column,
+            rename_to,
+        } => {
+            if table_exists(conn, table).await? && !has_column(conn, table, column).await? {
+                let sql = format!("ALTER TABLE {} RENAME TO {}", table, rename_to);
+                sqlx::query(&sql)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|err| err.to_string())?;
+                tracing::warn!(table, rename_to, "kept outdated table under a new name");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn table_exists(conn: &mut SqliteConnection, table: &str) -> Result<bool, String> {
+    let found = sqlx::query_scalar::<_, i64>(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+    )
+    .bind(table)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(found.is_some())
+}
+
+async fn has_column(
+    conn: &mut SqliteConnection,
+    table: &str,
+    column: &str,
+) -> Result<bool, String> {
+    let rows = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(rows.iter().any(|row| {
+        let name: String = row.try_get("name").unwrap_or_default();
+        name.eq_ignore_ascii_case(column)
+    }))
+}
+
+async fn has_user_tables(pool: &SqlitePool) -> Result<bool, String> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(1) FROM sqlite_master \
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name <> 'schema_version'",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|err| err.to_string())?;
+    Ok(count > 0)
+}
+
+async fn backup_before_migrate(
+    pool: &SqlitePool,
+    backup_dir: &Path,
+    version: i64,
+) -> Result<(), String> {
+    std::fs::create_dir_all(backup_dir).map_err(|err| err.to_string())?;
+    let file_name = format!(
+        "varManager-v{}-{}.db",
+        version,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let path = backup_dir.join(file_name);
+    sqlx::query("VACUUM INTO ?1")
+        .bind(path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|err| format!("pre-migration backup failed: {}", err))?;
+    tracing::info!(path = %path.display(), "database backed up before migration");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    #[tokio::test]
+    async fn upgrades_legacy_schema_without_losing_rows() {
+        let dir = std::env::temp_dir().join(format!("vm_migrate_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.join("varManager.db"))
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE HideFav (varName TEXT, hide INTEGER, fav INTEGER);
+             INSERT INTO HideFav VALUES ('a.b.1', 1, 0);
+             CREATE TABLE downloads (
+                 id INTEGER PRIMARY KEY, url TEXT NOT NULL, status TEXT NOT NULL,
+                 created_at INTEGER NOT NULL, updated_at INTEGER NOT NULL
+             );",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let backups = dir.join(DB_BACKUP_DIR);
+
+        migrate(&pool, &backups).await.unwrap();
+        migrate(&pool, &backups).await.unwrap();
+
+        let latest = MIGRATIONS.last().map(|m| m.version).unwrap();
+        assert_eq!(current_version(&pool).await.unwrap(), latest);
+        let legacy: i64 = sqlx::query_scalar("SELECT COUNT(1) FROM HideFav_legacy")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(legacy, 1);
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(has_column(&mut conn, "HideFav", "scenePath").await.unwrap());
+        assert!(has_column(&mut conn, "downloads", "temp_path").await.unwrap());
+        assert!(has_column(&mut conn, "installStatus", "profile").await.unwrap());
+        assert!(has_column(&mut conn, "downloads", "priority").await.unwrap());
+        assert!(has_column(&mut conn, "download_history", "var_name").await.unwrap());
+        assert!(has_column(&mut conn, "downloads", "resume_data").await.unwrap());
+        drop(conn);
+        let backup_count = std::fs::read_dir(&backups).unwrap().count();
+        assert_eq!(backup_count, 1);
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn profile_migration_keeps_rows_under_default() {
+        let dir = std::env::temp_dir().join(format!("vm_migrate_v8_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.join("varManager.db"))
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        let backups = dir.join(DB_BACKUP_DIR);
+        migrate_to(&pool, &backups, 7).await.unwrap();
+        assert_eq!(current_version(&pool).await.unwrap(), 7);
+        sqlx::query(
+            "INSERT INTO installStatus (varName, installed, disabled) VALUES ('a.b.1', 1, 0);
+             INSERT INTO installStatus (varName, installed, disabled) VALUES ('a.c.2', 1, 1);
+             INSERT INTO HideFav (varName, scenePath, hide, fav)
+                 VALUES ('a.b.1', 'Saves/scene/x.json', 0, 1);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        migrate(&pool, &backups).await.unwrap();
+
+        let installed: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT profile, varName, disabled FROM installStatus ORDER BY varName",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            installed,
+            vec![
+                ("default".to_string(), "a.b.1".to_string(), 0),
+                ("default".to_string(), "a.c.2".to_string(), 1),
+            ]
+        );
+        let hide_fav: Vec<(String, String, i64)> =
+            sqlx::query_as("SELECT profile, scenePath, fav FROM HideFav")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            hide_fav,
+            vec![("default".to_string(), "Saves/scene/x.json".to_string(), 1)]
+        );
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
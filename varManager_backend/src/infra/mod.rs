@@ -1,7 +1,11 @@
 pub mod db;
+pub mod db_maintenance;
 pub mod downloader;
 pub mod download_manager;
 pub mod fs_util;
+pub mod hub_auth;
+pub mod migrations;
+pub mod package_sources;
 pub mod paths;
 pub mod system_ops;
 pub mod winfs;
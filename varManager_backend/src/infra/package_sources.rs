@@ -0,0 +1,402 @@
+//! Where missing packages are fetched from: Hub, HTTP mirrors serving
+//! `<base>/<var name>.var`, and folders (local or SMB shares) holding `.var`
+//! files. A download tries the sources in priority order; each source's recent
+//! failures are tracked so a dead mirror stops being tried first. A mirror that
+//! is another instance's LAN cache also advertises each file's checksum.
+
+use crate::app::{DownloadConfig, PackageSource, PackageSourceKind};
+use crate::services::lan_cache::CHECKSUM_HEADER;
+use dashmap::DashMap;
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Download URL for a package known only by name; resolved through the
+/// configured sources when the download starts.
+pub const VAR_URL_PREFIX: &str = "var:";
+/// Name under which the row's own URL is tracked when no Hub source is
+/// configured.
+const ROW_URL_SOURCE: &str = "url";
+const FAILURES_BEFORE_COOLDOWN: u32 = 3;
+const FAILURE_COOLDOWN_SECS: i64 = 300;
+
+pub enum SourceCandidate {
+    /// `sha256` is the checksum a LAN cache mirror advertised, if any.
+    Url {
+        source: String,
+        url: String,
+        sha256: Option<String>,
+    },
+    File { source: String, path: PathBuf },
+}
+
+impl SourceCandidate {
+    pub fn source(&self) -> &str {
+        match self {
+            SourceCandidate::Url { source, .. } | SourceCandidate::File { source, .. } => source,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SourceHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+}
+
+impl SourceHealth {
+    /// Sources that keep failing are tried after the healthy ones for a while.
+    fn cooling_down(&self, now: i64) -> bool {
+        self.consecutive_failures >= FAILURES_BEFORE_COOLDOWN
+            && self
+                .last_failure_at
+                .is_some_and(|at| now - at < FAILURE_COOLDOWN_SECS)
+    }
+}
+
+#[derive(Serialize)]
+pub struct SourceStatus {
+    #[serde(flatten)]
+    pub source: PackageSource,
+    pub health: SourceHealth,
+    pub cooling_down: bool,
+}
+
+#[derive(Default)]
+pub struct SourceTracker {
+    health: DashMap<String, SourceHealth>,
+}
+
+impl SourceTracker {
+    pub fn record_success(&self, source: &str) {
+        let mut health = self.health.entry(source.to_string()).or_default();
+        health.successes += 1;
+        health.consecutive_failures = 0;
+        health.last_success_at = Some(now_ts());
+    }
+
+    pub fn record_failure(&self, source: &str, error: &str) {
+        let mut health = self.health.entry(source.to_string()).or_default();
+        health.failures += 1;
+        health.consecutive_failures += 1;
+        health.last_error = Some(error.to_string());
+        health.last_failure_at = Some(now_ts());
+    }
+
+    fn cooling_down(&self, source: &str, now: i64) -> bool {
+        self.health
+            .get(source)
+            .is_some_and(|health| health.cooling_down(now))
+    }
+
+    pub fn statuses(&self, sources: &[PackageSource]) -> Vec<SourceStatus> {
+        let now = now_ts();
+        sources
+            .iter()
+            .map(|source| {
+                let health = self
+                    .health
+                    .get(&source.name)
+                    .map(|health| health.clone())
+                    .unwrap_or_default();
+                SourceStatus {
+                    cooling_down: health.cooling_down(now),
+                    source: source.clone(),
+                    health,
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn hub_source() -> PackageSource {
+    PackageSource {
+        name: "hub".to_string(),
+        kind: PackageSourceKind::Hub,
+        location: String::new(),
+        priority: 0,
+        enabled: true,
+    }
+}
+
+/// Enabled sources, highest priority first; Hub alone when none are set.
+pub fn active_sources(cfg: &DownloadConfig) -> Vec<PackageSource> {
+    if cfg.sources.is_empty() {
+        return vec![hub_source()];
+    }
+    let mut sources: Vec<PackageSource> =
+        cfg.sources.iter().filter(|source| source.enabled).cloned().collect();
+    sources.sort_by_key(|source| std::cmp::Reverse(source.priority));
+    sources
+}
+
+/// The package a download row is for: the name in a `var:` URL, or the name
+/// hint when it looks like `creator.package.version`.
+pub fn download_var_name(url: &str, name_hint: Option<&str>) -> Option<String> {
+    if let Some(name) = url.strip_prefix(VAR_URL_PREFIX) {
+        return Some(name.trim().to_string()).filter(|name| !name.is_empty());
+    }
+    let hint = name_hint?.trim();
+    let hint = hint
+        .strip_suffix(".var")
+        .or_else(|| hint.strip_suffix(".VAR"))
+        .unwrap_or(hint);
+    (hint.split('.').count() == 3).then(|| hint.to_string())
+}
+
+/// Where the download can be fetched from, in the order to try. The row's own
+/// http(s) URL takes the Hub source's place, or goes last when Hub is not
+/// configured. Mirrors are probed so a package they lack is skipped without
+/// counting against them.
+pub async fn candidates(
+    tracker: &SourceTracker,
+    sources: &[PackageSource],
+    client: &Client,
+    url: &str,
+    var_name: Option<&str>,
+) -> Vec<SourceCandidate> {
+    let now = now_ts();
+    let mut ordered: Vec<&PackageSource> = sources.iter().collect();
+    ordered.sort_by_key(|source| tracker.cooling_down(&source.name, now));
+
+    let row_url = (url.starts_with("http://") || url.starts_with("https://")).then_some(url);
+    let mut found = Vec::new();
+    for source in ordered {
+        let result = match source.kind {
+            PackageSourceKind::Hub => match (row_url, var_name) {
+                (Some(url), _) => Ok(Some(SourceCandidate::Url {
+                    source: source.name.clone(),
+                    url: url.to_string(),
+                    sha256: None,
+                })),
+                (None, Some(name)) => hub_candidate(source, name).await,
+                (None, None) => Ok(None),
+            },
+            PackageSourceKind::Http => match var_name {
+                Some(name) => mirror_candidate(source, client, name).await,
+                None => Ok(None),
+            },
+            PackageSourceKind::Directory => match var_name {
+                Some(name) => directory_candidate(source, name),
+                None => Ok(None),
+            },
+        };
+        match result {
+            Ok(Some(candidate)) => found.push(candidate),
+            Ok(None) => {}
+            Err(err) => tracker.record_failure(&source.name, &err),
+        }
+    }
+    let has_hub = sources.iter().any(|source| source.kind == PackageSourceKind::Hub);
+    if let (Some(url), false) = (row_url, has_hub) {
+        found.push(SourceCandidate::Url {
+            source: ROW_URL_SOURCE.to_string(),
+            url: url.to_string(),
+            sha256: None,
+        });
+    }
+    found
+}
+
+async fn hub_candidate(
+    source: &PackageSource,
+    var_name: &str,
+) -> Result<Option<SourceCandidate>, String> {
+    let name = var_name.to_string();
+    let (by_name, by_package) = tokio::task::spawn_blocking(move || {
+        crate::jobs::hub::find_packages_maps(std::slice::from_ref(&name))
+    })
+    .await
+    .map_err(|err| err.to_string())??;
+    let url = by_name.get(var_name).or_else(|| {
+        let (base, version) = var_name.rsplit_once('.')?;
+        if version.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        by_package.get(base)
+    });
+    Ok(url.map(|url| SourceCandidate::Url {
+        source: source.name.clone(),
+        url: url.clone(),
+        sha256: None,
+    }))
+}
+
+async fn mirror_candidate(
+    source: &PackageSource,
+    client: &Client,
+    var_name: &str,
+) -> Result<Option<SourceCandidate>, String> {
+    let base = source.location.trim();
+    let base = if base.ends_with('/') {
+        base.to_string()
+    } else {
+        format!("{}/", base)
+    };
+    let url = Url::parse(&base)
+        .and_then(|base| base.join(&format!("{}.var", var_name)))
+        .map_err(|err| format!("invalid mirror url {}: {}", source.location, err))?;
+    let response = client
+        .head(url.as_str())
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    let status = response.status();
+    if status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() && !status.is_redirection() {
+        return Err(format!("HEAD {} failed with status {}", url, status));
+    }
+    let sha256 = response
+        .headers()
+        .get(CHECKSUM_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase());
+    Ok(Some(SourceCandidate::Url {
+        source: source.name.clone(),
+        url: url.to_string(),
+        sha256,
+    }))
+}
+
+fn directory_candidate(
+    source: &PackageSource,
+    var_name: &str,
+) -> Result<Option<SourceCandidate>, String> {
+    let dir = Path::new(source.location.trim());
+    if !dir.is_dir() {
+        return Err(format!("source folder not reachable: {}", dir.display()));
+    }
+    let exact = dir.join(format!("{}.var", var_name));
+    if exact.is_file() {
+        return Ok(Some(SourceCandidate::File {
+            source: source.name.clone(),
+            path: exact,
+        }));
+    }
+    let Some((base, version)) = var_name.rsplit_once('.') else {
+        return Ok(None);
+    };
+    if !version.eq_ignore_ascii_case("latest") {
+        return Ok(None);
+    }
+    let prefix = format!("{}.", base.to_ascii_lowercase());
+    let mut newest: Option<(u64, PathBuf)> = None;
+    for entry in fs::read_dir(dir).map_err(|err| err.to_string())?.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_ascii_lowercase();
+        let Some(version) = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".var"))
+            .and_then(|version| version.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        if newest.as_ref().is_none_or(|(best, _)| version > *best) {
+            newest = Some((version, entry.path()));
+        }
+    }
+    Ok(newest.map(|(_, path)| SourceCandidate::File {
+        source: source.name.clone(),
+        path,
+    }))
+}
+
+fn now_ts() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path as UrlPath, http::StatusCode as HttpStatus, routing::get, Router};
+
+    fn source(name: &str, kind: PackageSourceKind, location: &str, priority: i32) -> PackageSource {
+        PackageSource {
+            name: name.to_string(),
+            kind,
+            location: location.to_string(),
+            priority,
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn tries_mirror_then_folder_and_tracks_dead_sources() {
+        let app = Router::new().route(
+            "/{file}",
+            get(|UrlPath(file): UrlPath<String>| async move {
+                if file == "a.b.1.var" {
+                    Ok(([(CHECKSUM_HEADER, "ABC123")], vec![0u8; 4]))
+                } else {
+                    Err(HttpStatus::NOT_FOUND)
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mirror = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("http://{}/", closed.local_addr().unwrap());
+        drop(closed);
+
+        let dir = std::env::temp_dir().join(format!("vm_sources_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.b.1.var", "a.b.3.var", "a.b.12.var"] {
+            fs::write(dir.join(name), b"x").unwrap();
+        }
+
+        let cfg = DownloadConfig {
+            sources: vec![
+                source("folder", PackageSourceKind::Directory, dir.to_str().unwrap(), 1),
+                source("mirror", PackageSourceKind::Http, &mirror, 5),
+                source("dead", PackageSourceKind::Http, &dead, 9),
+            ],
+            ..DownloadConfig::default()
+        };
+        let sources = active_sources(&cfg);
+        let tracker = SourceTracker::default();
+        let client = Client::new();
+
+        let found = candidates(&tracker, &sources, &client, "var:a.b.1", Some("a.b.1")).await;
+        let names: Vec<&str> = found.iter().map(|c| c.source()).collect();
+        assert_eq!(names, ["mirror", "folder"]);
+        match &found[0] {
+            SourceCandidate::Url { url, sha256, .. } => {
+                assert_eq!(url, &format!("{}a.b.1.var", mirror));
+                assert_eq!(sha256.as_deref(), Some("abc123"));
+            }
+            SourceCandidate::File { .. } => panic!("mirror should yield a url"),
+        }
+
+        let latest = Some("a.b.latest");
+        let found = candidates(&tracker, &sources, &client, "var:a.b.latest", latest).await;
+        let names: Vec<&str> = found.iter().map(|c| c.source()).collect();
+        assert_eq!(names, ["folder"]);
+        match &found[0] {
+            SourceCandidate::File { path, .. } => assert!(path.ends_with("a.b.12.var")),
+            SourceCandidate::Url { .. } => panic!("folder should yield a file"),
+        }
+
+        let statuses = tracker.statuses(&sources);
+        let health = |name: &str| {
+            statuses
+                .iter()
+                .find(|status| status.source.name == name)
+                .map(|status| status.health.clone())
+                .unwrap()
+        };
+        assert_eq!(health("dead").consecutive_failures, 2);
+        assert_eq!(health("mirror").failures, 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,5 +1,7 @@
-use crate::app::AppState;
+use crate::app::{data_dir, AppState, Config, DEFAULT_PROFILE};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
 
 pub const TIDIED_DIR: &str = "___VarTidied___";
 pub const INSTALL_LINK_DIR: &str = "___VarsLink___";
@@ -18,18 +20,171 @@ pub const FEELFAR_NAME: &str = "feelfar";
 pub const CACHE_DIR: &str = "Cache";
 pub const LOADSCENE_FILE: &str = "loadscene.json";
 
+/// The VaM installation a request or job works on.
+#[derive(Clone, Debug)]
+pub struct VamProfileRef {
+    pub name: String,
+    pub vampath: Option<PathBuf>,
+    pub vam_exec: Option<String>,
+}
+
+/// Look up `selector`, falling back to `active_profile`, then `default`.
+pub fn resolve_profile(cfg: &Config, selector: Option<&str>) -> Result<VamProfileRef, String> {
+    let name = selector
+        .or(cfg.active_profile.as_deref())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_PROFILE);
+    if name.eq_ignore_ascii_case(DEFAULT_PROFILE) {
+        return Ok(VamProfileRef {
+            name: DEFAULT_PROFILE.to_string(),
+            vampath: cfg.vampath.as_ref().and_then(|s| normalize_path(s)),
+            vam_exec: cfg.vam_exec.clone(),
+        });
+    }
+    let profile = cfg
+        .vam_profiles
+        .iter()
+        .find(|profile| profile.name.trim().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("unknown VaM profile: {}", name))?;
+    Ok(VamProfileRef {
+        name: profile.name.trim().to_string(),
+        vampath: normalize_path(&profile.vampath),
+        vam_exec: profile.vam_exec.clone(),
+    })
+}
+
+pub fn vam_profile(state: &AppState) -> Result<VamProfileRef, String> {
+    let cfg = state
+        .config
+        .read()
+        .map_err(|_| "config lock poisoned".to_string())?;
+    resolve_profile(&cfg, state.profile.as_deref())
+}
+
+/// Name install status and hide/fav rows are stored under.
+pub fn profile_name(state: &AppState) -> Result<String, String> {
+    vam_profile(state).map(|profile| profile.name)
+}
+
 pub fn config_paths(state: &AppState) -> Result<(PathBuf, Option<PathBuf>), String> {
+    let vampath = vam_profile(state)?.vampath;
     let cfg = state
         .config
         .read()
         .map_err(|_| "config lock poisoned".to_string())?;
     let varspath = cfg.varspath.as_ref().and_then(|s| normalize_path(s));
-    let vampath = cfg.vampath.as_ref().and_then(|s| normalize_path(s));
 
     let varspath = varspath.ok_or_else(|| "varspath is required in config.json".to_string())?;
     Ok((varspath, vampath))
 }
 
+/// A folder that holds VAR files, with its own `___VarTidied___`.
+#[derive(Clone, Debug)]
+pub struct LibraryRootPath {
+    pub path: PathBuf,
+    pub priority: i32,
+}
+
+/// `varspath` and `Config.library_roots`, highest priority first. On equal
+/// priority `varspath` comes first, then the configured order.
+pub fn library_roots(state: &AppState) -> Result<Vec<LibraryRootPath>, String> {
+    let cfg = state
+        .config
+        .read()
+        .map_err(|_| "config lock poisoned".to_string())?;
+    config_library_roots(&cfg)
+}
+
+fn config_library_roots(cfg: &Config) -> Result<Vec<LibraryRootPath>, String> {
+    let varspath = cfg
+        .varspath
+        .as_ref()
+        .and_then(|s| normalize_path(s))
+        .ok_or_else(|| "varspath is required in config.json".to_string())?;
+    let mut roots = vec![LibraryRootPath {
+        path: varspath,
+        priority: 0,
+    }];
+    for root in &cfg.library_roots {
+        let Some(path) = normalize_path(&root.path) else {
+            continue;
+        };
+        let key = path.to_string_lossy().to_ascii_lowercase();
+        match roots
+            .iter_mut()
+            .find(|known| known.path.to_string_lossy().to_ascii_lowercase() == key)
+        {
+            Some(known) => known.priority = root.priority,
+            None => roots.push(LibraryRootPath {
+                path,
+                priority: root.priority,
+            }),
+        }
+    }
+    roots.sort_by_key(|root| std::cmp::Reverse(root.priority));
+    Ok(roots)
+}
+
+/// The root `var_file` lives under, if any.
+pub fn library_root_of<'a>(roots: &'a [LibraryRootPath], var_file: &Path) -> Option<&'a Path> {
+    roots
+        .iter()
+        .map(|root| root.path.as_path())
+        .filter(|root| var_file.starts_with(root))
+        .max_by_key(|root| root.components().count())
+}
+
+pub fn read_only_library(state: &AppState) -> bool {
+    state
+        .config
+        .read()
+        .map(|cfg| cfg.read_only_library)
+        .unwrap_or(false)
+}
+
+/// Directory previews are extracted to: `___PreviewPics___` under varspath,
+/// or under the data dir when the library is read-only.
+pub fn preview_root(varspath: &Path, read_only: bool) -> PathBuf {
+    if read_only {
+        data_dir().join(PREVIEW_DIR)
+    } else {
+        varspath.join(PREVIEW_DIR)
+    }
+}
+
+/// Where each indexed var was found: the highest-priority copy across all
+/// library roots. Filled from the `vars` table at startup and after every
+/// scan. Paths are absolute, or relative to varspath for rows written
+/// before roots were recorded.
+static VAR_LOCATIONS: OnceLock<RwLock<HashMap<String, PathBuf>>> = OnceLock::new();
+
+fn var_locations() -> &'static RwLock<HashMap<String, PathBuf>> {
+    VAR_LOCATIONS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+pub fn set_var_locations(locations: HashMap<String, PathBuf>) {
+    match var_locations().write() {
+        Ok(mut guard) => *guard = locations,
+        Err(err) => *err.into_inner() = locations,
+    }
+}
+
+/// Drop the recorded location of a var whose row was deleted.
+pub fn forget_var_location(var_name: &str) {
+    match var_locations().write() {
+        Ok(mut guard) => guard.remove(var_name),
+        Err(err) => err.into_inner().remove(var_name),
+    };
+}
+
+fn recorded_var_location(var_name: &str) -> Option<PathBuf> {
+    var_locations()
+        .read()
+        .ok()
+        .and_then(|guard| guard.get(var_name).cloned())
+}
+
 pub fn normalize_path(value: &str) -> Option<PathBuf> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
@@ -44,6 +199,11 @@ pub fn resolve_var_file_path(varspath: &Path, var_name: &str) -> Result<PathBuf,
     if parts.len() != 3 {
         return Err(format!("invalid var name: {}", var_name));
     }
+    if let Some(recorded) = recorded_var_location(var_name).map(|path| varspath.join(path)) {
+        if recorded.exists() {
+            return Ok(recorded);
+        }
+    }
     let creator = parts[0];
     let candidate = varspath
         .join(TIDIED_DIR)
@@ -89,3 +249,105 @@ pub fn feelfar_dir(vampath: &Path) -> PathBuf {
 pub fn loadscene_path(vampath: &Path) -> PathBuf {
     feelfar_dir(vampath).join(LOADSCENE_FILE)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{LibraryRoot, VamProfile};
+
+    fn root(path: &str, priority: i32) -> LibraryRoot {
+        LibraryRoot {
+            path: path.to_string(),
+            priority,
+        }
+    }
+
+    #[test]
+    fn profiles_resolve_selector_then_active_then_default() {
+        let mut cfg = Config {
+            vampath: Some("C:/VaM".to_string()),
+            vam_profiles: vec![VamProfile {
+                name: "Test".to_string(),
+                vampath: "D:/VaM-test".to_string(),
+                vam_exec: None,
+            }],
+            ..Config::default()
+        };
+        let resolve = |cfg: &Config, selector| {
+            resolve_profile(cfg, selector).map(|profile| (profile.name, profile.vampath))
+        };
+        let default = Ok((DEFAULT_PROFILE.to_string(), Some(PathBuf::from("C:/VaM"))));
+        let test = Ok(("Test".to_string(), Some(PathBuf::from("D:/VaM-test"))));
+
+        assert_eq!(resolve(&cfg, None), default);
+        assert_eq!(resolve(&cfg, Some("test")), test);
+        assert_eq!(resolve(&cfg, Some(" ")), default);
+
+        cfg.active_profile = Some("Test".to_string());
+        assert_eq!(resolve(&cfg, None), test);
+        assert_eq!(resolve(&cfg, Some("Default")), default);
+
+        assert_eq!(
+            resolve(&cfg, Some("missing")),
+            Err("unknown VaM profile: missing".to_string())
+        );
+        cfg.active_profile = Some("gone".to_string());
+        assert!(resolve(&cfg, None).is_err());
+    }
+
+    #[test]
+    fn library_roots_order_and_merge() {
+        let cfg = Config {
+            varspath: Some("D:/Vars".to_string()),
+            library_roots: vec![
+                root("E:/Archive", 0),
+                root("F:/Fast", 5),
+                root(" ", 9),
+                root("e:/archive", -1),
+                root("G:/Also", 0),
+            ],
+            ..Config::default()
+        };
+        let roots = config_library_roots(&cfg).unwrap();
+        let order: Vec<(String, i32)> = roots
+            .iter()
+            .map(|root| (root.path.to_string_lossy().into_owned(), root.priority))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("F:/Fast".to_string(), 5),
+                ("D:/Vars".to_string(), 0),
+                ("G:/Also".to_string(), 0),
+                ("E:/Archive".to_string(), -1),
+            ]
+        );
+
+        let cfg = Config {
+            varspath: Some("D:/Vars".to_string()),
+            library_roots: vec![root("d:/vars", 3)],
+            ..Config::default()
+        };
+        let roots = config_library_roots(&cfg).unwrap();
+        assert_eq!(roots.len(), 1);
+        assert_eq!((roots[0].path.as_path(), roots[0].priority), (Path::new("D:/Vars"), 3));
+
+        assert!(config_library_roots(&Config::default()).is_err());
+    }
+
+    #[test]
+    fn var_files_belong_to_the_deepest_root() {
+        let roots: Vec<LibraryRootPath> = ["/lib", "/lib/nested", "/other"]
+            .iter()
+            .map(|path| LibraryRootPath {
+                path: PathBuf::from(path),
+                priority: 0,
+            })
+            .collect();
+        let of = |file: &str| library_root_of(&roots, Path::new(file));
+        assert_eq!(of("/lib/a/A.b.1.var"), Some(Path::new("/lib")));
+        assert_eq!(of("/lib/nested/A.b.1.var"), Some(Path::new("/lib/nested")));
+        assert_eq!(of("/lib/nestedish/A.b.1.var"), Some(Path::new("/lib")));
+        assert_eq!(of("/elsewhere/A.b.1.var"), None);
+    }
+}
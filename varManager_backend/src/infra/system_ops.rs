@@ -1,4 +1,4 @@
-use crate::infra::paths::loadscene_path;
+use crate::infra::paths::{loadscene_path, vam_profile};
 use crate::app::AppState;
 use serde_json::json;
 use std::fs;
@@ -17,18 +17,11 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 const DEFAULT_VAM_EXEC: &str = "VaM (Desktop Mode).bat";
 
 pub fn start_vam(state: &AppState) -> Result<(), String> {
-    let cfg = state
-        .config
-        .read()
-        .map_err(|_| "config lock poisoned".to_string())?;
-    let vampath = cfg
+    let profile = vam_profile(state)?;
+    let vampath = profile
         .vampath
-        .as_ref()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .map(PathBuf::from)
         .ok_or_else(|| "vampath is required in config.json".to_string())?;
-    let exec_name = cfg
+    let exec_name = profile
         .vam_exec
         .as_ref()
         .map(|s| s.trim().to_string())
@@ -44,16 +37,8 @@ pub fn start_vam(state: &AppState) -> Result<(), String> {
 }
 
 pub fn rescan_packages(state: &AppState) -> Result<bool, String> {
-    let cfg = state
-        .config
-        .read()
-        .map_err(|_| "config lock poisoned".to_string())?;
-    let vampath = cfg
+    let vampath = vam_profile(state)?
         .vampath
-        .as_ref()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .map(PathBuf::from)
         .ok_or_else(|| "vampath is required in config.json".to_string())?;
 
     let mut system = System::new();
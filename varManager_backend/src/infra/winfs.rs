@@ -4,8 +4,11 @@ use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use windows::core::{Error as WinError, PCWSTR};
 use windows::Win32::Foundation::{
-    CloseHandle, GetLastError, ERROR_INVALID_PARAMETER, ERROR_PRIVILEGE_NOT_HELD, FILETIME,
-    WIN32_ERROR,
+    CloseHandle, GetLastError, LocalFree, ERROR_INVALID_PARAMETER, ERROR_PRIVILEGE_NOT_HELD,
+    FILETIME, HLOCAL, WIN32_ERROR,
+};
+use windows::Win32::Security::Cryptography::{
+    CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
 };
 use windows::Win32::Storage::FileSystem::{
     CreateFileW, CreateSymbolicLinkW, SetFileTime, FILE_ATTRIBUTE_NORMAL, FILE_FLAG_BACKUP_SEMANTICS,
@@ -64,6 +67,61 @@ pub fn set_symlink_file_times(
     Ok(())
 }
 
+/// Encrypt `data` with DPAPI for the current Windows user.
+pub fn protect_data(data: &[u8]) -> Result<Vec<u8>, String> {
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptProtectData(
+            &input,
+            PCWSTR::null(),
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+    }
+    .map_err(|err| format!("CryptProtectData failed ({}).", format_hresult_error(err)))?;
+    Ok(take_blob(output))
+}
+
+/// Decrypt data written by [`protect_data`] for the same Windows user.
+pub fn unprotect_data(data: &[u8]) -> Result<Vec<u8>, String> {
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptUnprotectData(
+            &input,
+            None,
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+    }
+    .map_err(|err| format!("CryptUnprotectData failed ({}).", format_hresult_error(err)))?;
+    Ok(take_blob(output))
+}
+
+fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    if blob.pbData.is_null() {
+        return Vec::new();
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(blob.pbData, blob.cbData as usize) }.to_vec();
+    unsafe {
+        LocalFree(Some(HLOCAL(blob.pbData as *mut _)));
+    }
+    bytes
+}
+
 fn create_symlink(link: &Path, target: &Path, is_dir: bool) -> Result<(), String> {
     let link_w = to_wide(link);
     let target_w = to_wide(target);
@@ -0,0 +1,58 @@
+//! Queues `import_downloads` for downloads as they finish.
+//!
+//! Downloads of one batch finish a few seconds apart, so ids are collected
+//! until the download queue goes idle and imported by a single job; a long
+//! batch is still imported in slices of `MAX_BATCH` so the library fills up
+//! while it runs. Nothing is queued while the library is read-only.
+
+use crate::app::AppState;
+use crate::jobs::queue_job;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Duration};
+
+/// How long to wait for another download to finish before checking whether
+/// the queue is idle.
+const BATCH_WINDOW: Duration = Duration::from_secs(3);
+const MAX_BATCH: usize = 50;
+
+pub fn spawn_auto_import(state: AppState, mut completed: mpsc::UnboundedReceiver<i64>) {
+    tokio::spawn(async move {
+        while let Some(id) = completed.recv().await {
+            let mut ids = vec![id];
+            let mut closed = false;
+            while ids.len() < MAX_BATCH {
+                match timeout(BATCH_WINDOW, completed.recv()).await {
+                    Ok(Some(id)) => ids.push(id),
+                    Ok(None) => {
+                        closed = true;
+                        break;
+                    }
+                    Err(_) if state.download_manager.is_idle() => break,
+                    Err(_) => {}
+                }
+            }
+
+            let (enabled, install, read_only) = match state.config.read() {
+                Ok(cfg) => (
+                    cfg.download.auto_import,
+                    cfg.download.auto_install,
+                    cfg.read_only_library,
+                ),
+                Err(_) => (false, false, false),
+            };
+            if enabled && read_only {
+                // Downloads are saved outside the library roots, and nothing
+                // is moved into a read-only library.
+                tracing::info!(count = ids.len(), "auto-import is off for a read-only library");
+            } else if enabled {
+                let args = json!({ "downloads": ids, "install": install });
+                let job_id = queue_job(&state, "import_downloads", Some(args), None).await;
+                tracing::info!(job_id, count = ids.len(), "auto-import queued");
+            }
+            if closed {
+                break;
+            }
+        }
+    });
+}
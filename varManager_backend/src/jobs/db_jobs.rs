@@ -0,0 +1,188 @@
+use crate::app::AppState;
+use crate::infra::db::{default_path, list_var_locations};
+use crate::infra::db_maintenance::{self, backup_dir, backup_file_name, resolve_backup};
+use crate::infra::paths::set_var_locations;
+use crate::jobs::job_channel::JobReporter;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+
+#[derive(Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DbBackupArgs {
+    /// Part of the backup file name, e.g. `before-cleanup`.
+    #[serde(default)]
+    label: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DbRestoreArgs {
+    /// File name from `GET /db/backups`.
+    file: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct DbBackupResult {
+    file: String,
+    size_bytes: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct DbRestoreResult {
+    restored: String,
+    /// Backup of the database as it was before the restore.
+    previous: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct VacuumResult {
+    size_before: u64,
+    size_after: u64,
+}
+
+pub async fn run_db_backup_job(
+    state: AppState,
+    reporter: JobReporter,
+    args: Option<Value>,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let args: DbBackupArgs = match args {
+            Some(args) => serde_json::from_value(args).map_err(|err| err.to_string())?,
+            None => DbBackupArgs::default(),
+        };
+        let handle = tokio::runtime::Handle::current();
+        let result = handle.block_on(backup(&state, &reporter, args.label.as_deref()))?;
+        reporter.set_result(serde_json::to_value(result).map_err(|err| err.to_string())?);
+        Ok(())
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+pub async fn run_db_restore_job(
+    state: AppState,
+    reporter: JobReporter,
+    args: Option<Value>,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let args = args.ok_or_else(|| "db_restore args required".to_string())?;
+        let args: DbRestoreArgs = serde_json::from_value(args).map_err(|err| err.to_string())?;
+        let src = resolve_backup(&args.file)?;
+        let handle = tokio::runtime::Handle::current();
+
+        // Downloads and the image cache write to the database on their own;
+        // hold them off so nothing lands in, or reverts under, the swap.
+        reporter.log("Pausing downloads and the image disk cache".to_string());
+        handle.block_on(state.download_manager.suspend());
+        let image_cache_gate = handle.block_on(state.image_cache.pause());
+        let restored = handle.block_on(restore(&state, &reporter, &args.file, &src));
+        drop(image_cache_gate);
+        if let Err(err) = handle.block_on(state.image_cache.reconcile()) {
+            reporter.log(format!("image cache reconcile failed: {}", err));
+        }
+        reporter.log("Reloading downloads".to_string());
+        let resumed = handle.block_on(state.download_manager.resume());
+        let previous = restored?;
+        resumed?;
+        reporter.progress(100);
+        reporter.log("Restore complete".to_string());
+        reporter.set_result(
+            serde_json::to_value(DbRestoreResult {
+                restored: args.file,
+                previous: previous.file,
+            })
+            .map_err(|err| err.to_string())?,
+        );
+        Ok(())
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+/// Back up the live database, then replace it with `src`.
+async fn restore(
+    state: &AppState,
+    reporter: &JobReporter,
+    file: &str,
+    src: &std::path::Path,
+) -> Result<DbBackupResult, String> {
+    reporter.log("Backing up current database before restore".to_string());
+    let previous = backup(state, reporter, Some("pre-restore")).await?;
+    reporter.progress(40);
+    reporter.log(format!("Restoring {}", file));
+    db_maintenance::restore_from(&state.db_pool, src).await?;
+    // Var locations are cached from the database that was just replaced.
+    set_var_locations(list_var_locations(&state.db_pool).await?);
+    Ok(previous)
+}
+
+pub async fn run_db_check_job(
+    state: AppState,
+    reporter: JobReporter,
+    _args: Option<Value>,
+) -> Result<(), String> {
+    reporter.log("Running integrity_check and foreign_key_check".to_string());
+    let report = db_maintenance::check(&state.db_pool).await?;
+    if report.ok {
+        reporter.log("Database is consistent".to_string());
+    } else {
+        for line in report.integrity.iter().filter(|line| line.as_str() != "ok") {
+            reporter.log(format!("integrity: {}", line));
+        }
+        for violation in &report.foreign_key_violations {
+            reporter.log(format!(
+                "foreign key: {} row {:?} -> {}",
+                violation.table, violation.rowid, violation.parent
+            ));
+        }
+    }
+    reporter
+        .set_result_async(serde_json::to_value(report).map_err(|err| err.to_string())?)
+        .await;
+    Ok(())
+}
+
+pub async fn run_vacuum_job(
+    state: AppState,
+    reporter: JobReporter,
+    _args: Option<Value>,
+) -> Result<(), String> {
+    let size_before = db_file_size();
+    reporter.log("VACUUM start".to_string());
+    sqlx::query("VACUUM")
+        .execute(&state.db_pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    let size_after = db_file_size();
+    reporter.log(format!("VACUUM end: {} -> {} bytes", size_before, size_after));
+    reporter
+        .set_result_async(
+            serde_json::to_value(VacuumResult {
+                size_before,
+                size_after,
+            })
+            .map_err(|err| err.to_string())?,
+        )
+        .await;
+    Ok(())
+}
+
+async fn backup(
+    state: &AppState,
+    reporter: &JobReporter,
+    label: Option<&str>,
+) -> Result<DbBackupResult, String> {
+    let dir = backup_dir();
+    let file = backup_file_name(&dir, label);
+    let dest = dir.join(&file);
+    db_maintenance::backup_to(&state.db_pool, &dest).await?;
+    let size_bytes = fs::metadata(&dest).map(|meta| meta.len()).unwrap_or(0);
+    reporter.log(format!("Backed up to {}", dest.display()));
+    Ok(DbBackupResult { file, size_bytes })
+}
+
+fn db_file_size() -> u64 {
+    fs::metadata(default_path()).map(|meta| meta.len()).unwrap_or(0)
+}
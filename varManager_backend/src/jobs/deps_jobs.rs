@@ -1,12 +1,13 @@
 use crate::infra::db::upsert_install_status;
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, resolve_var_file_path, INSTALL_LINK_DIR};
+use crate::infra::paths::{config_paths, profile_name, resolve_var_file_path, INSTALL_LINK_DIR};
 use crate::domain::var_logic::{resolve_var_exist_name, vars_dependencies};
 use crate::app::AppState;
 use crate::infra::winfs;
 use chrono::{DateTime, Local};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashSet;
@@ -16,14 +17,16 @@ use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 use sqlx::{Row, SqlitePool};
 
-#[derive(Deserialize)]
-struct SavesDepsArgs {}
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SavesDepsArgs {}
 
-#[derive(Deserialize)]
-struct LogDepsArgs {}
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LogDepsArgs {}
 
-#[derive(Serialize)]
-struct DepsJobResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct DepsJobResult {
     missing: Vec<String>,
     installed: Vec<String>,
     dependency_count: usize,
@@ -67,6 +70,7 @@ fn saves_deps_blocking(state: &AppState, reporter: &JobReporter, _args: SavesDep
     reporter.progress(1);
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     handle
@@ -120,6 +124,7 @@ fn saves_deps_blocking(state: &AppState, reporter: &JobReporter, _args: SavesDep
         handle.block_on(install_missing_dependencies(
             reporter,
             pool,
+            &profile,
             &varspath,
             &vampath,
             &dependencies,
@@ -169,11 +174,13 @@ fn log_deps_blocking(state: &AppState, reporter: &JobReporter, _args: LogDepsArg
     dependencies = distinct(dependencies);
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let (missing, installed_now) = handle.block_on(install_missing_dependencies(
         reporter,
         pool,
+        &profile,
         &varspath,
         &vampath,
         &dependencies,
@@ -265,6 +272,7 @@ async fn list_saved_dependencies(pool: &SqlitePool) -> Result<Vec<String>, Strin
 async fn install_missing_dependencies(
     reporter: &JobReporter,
     pool: &SqlitePool,
+    profile: &str,
     varspath: &Path,
     vampath: &Path,
     dependencies: &[String],
@@ -278,7 +286,7 @@ async fn install_missing_dependencies(
             missing.push(format!("{}$", dep));
         }
         if exist != "missing" {
-            match install_var(reporter, pool, varspath, vampath, &exist).await {
+            match install_var(reporter, pool, profile, varspath, vampath, &exist).await {
                 Ok(InstallOutcome::Installed) => installed.push(exist),
                 Ok(InstallOutcome::AlreadyInstalled) => {}
                 Err(err) => reporter.log(format!("install failed {} ({})", dep, err)),
@@ -295,6 +303,7 @@ async fn install_missing_dependencies(
 async fn install_var(
     reporter: &JobReporter,
     pool: &SqlitePool,
+    profile: &str,
     varspath: &Path,
     vampath: &Path,
     var_name: &str,
@@ -308,7 +317,7 @@ async fn install_var(
     let dest = resolve_var_file_path(varspath, var_name)?;
     winfs::create_symlink_file(&link_path, &dest)?;
     set_link_times(&link_path, &dest)?;
-    upsert_install_status(pool, var_name, true, false).await?;
+    upsert_install_status(pool, profile, var_name, true, false).await?;
     reporter.log(format!("{} installed", var_name));
     Ok(InstallOutcome::Installed)
 }
@@ -1,8 +1,12 @@
 use crate::jobs::job_channel::JobReporter;
+use crate::jobs::outcome::{ItemErrorCode, ItemOutcome};
 use crate::domain::var_logic::resolve_var_exist_name;
 use crate::app::AppState;
+use crate::infra::download_manager::EnqueueOutcome;
+use crate::infra::hub_auth;
 use reqwest::blocking::Client;
 use reqwest::header;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::cmp::Ordering;
@@ -19,12 +23,21 @@ type DownloadUrlMaps = (HashMap<String, String>, HashMap<String, String>);
 type DownloadUrlMapsWithSizes =
     (HashMap<String, String>, HashMap<String, String>, HashMap<String, i64>);
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct HubFindPackagesArgs {
     pub packages: Vec<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HubMissingScanArgs {
+    #[serde(default)]
+    pub packages: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct HubResourcesQuery {
     pub perpage: Option<u32>,
     pub location: Option<String>,
@@ -37,7 +50,8 @@ pub struct HubResourcesQuery {
     pub page: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct HubResourceDetailArgs {
     pub resource_id: String,
 }
@@ -55,27 +69,35 @@ pub struct HubOptionsCache {
 static HUB_OPTIONS_CACHE: OnceLock<Mutex<Option<HubOptionsCache>>> = OnceLock::new();
 static HUB_INFO_CACHE: OnceLock<Mutex<Option<HubInfoCacheEntry>>> = OnceLock::new();
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct HubDownloadList {
     pub download_urls: HashMap<String, String>,
     pub download_urls_no_version: HashMap<String, String>,
     pub download_sizes: HashMap<String, i64>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct HubDownloadItemArgs {
     pub url: String,
     pub name: Option<String>,
     pub size: Option<u64>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct HubDownloadAllArgs {
     pub urls: Option<Vec<String>>,
     pub items: Option<Vec<HubDownloadItemArgs>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
+pub struct HubDownloadAllResult {
+    pub added: usize,
+    pub items: Vec<ItemOutcome>,
+}
+
+#[derive(Serialize, JsonSchema)]
 pub struct HubOverviewPanelData {
     pub description: String,
     pub images: Vec<String>,
@@ -93,8 +115,8 @@ pub async fn run_hub_missing_scan_job(
     args: Option<Value>,
 ) -> Result<(), String> {
     tokio::task::spawn_blocking(move || {
-        let args: HubFindPackagesArgs =
-            args.map_or_else(|| Ok(HubFindPackagesArgs { packages: Vec::new() }), serde_json::from_value)
+        let args: HubMissingScanArgs =
+            args.map_or_else(|| Ok(HubMissingScanArgs { packages: Vec::new() }), serde_json::from_value)
                 .map_err(|err| err.to_string())?;
         missing_scan_blocking(&state, &reporter, args)
     })
@@ -237,7 +259,7 @@ pub async fn run_hub_find_packages_job(
 fn missing_scan_blocking(
     state: &AppState,
     reporter: &JobReporter,
-    args: HubFindPackagesArgs,
+    args: HubMissingScanArgs,
 ) -> Result<(), String> {
     reporter.log("Hub missing scan start".to_string());
     reporter.progress(1);
@@ -358,12 +380,36 @@ async fn download_all_async(
     if merged.is_empty() {
         return Err("no download urls provided".to_string());
     }
-    let items = merged.into_values().collect::<Vec<_>>();
-    let added = state.download_manager.enqueue_items(items).await?;
+    let mut added = 0usize;
+    let mut items = Vec::with_capacity(merged.len());
+    let batch: Vec<_> = merged.into_values().collect();
+    let urls: Vec<String> = batch.iter().map(|item| item.url.clone()).collect();
+    let outcomes = state.download_manager.enqueue_each(batch).await?;
+    for (url, outcome) in urls.into_iter().zip(outcomes) {
+        match outcome {
+            Ok(EnqueueOutcome::Queued) => {
+                added += 1;
+                items.push(ItemOutcome::succeeded(url, "download"));
+            }
+            Ok(EnqueueOutcome::Skipped(skipped)) => {
+                items.push(
+                    ItemOutcome::skipped(url, "download", ItemErrorCode::AlreadyDone)
+                        .with_message(skipped.message()),
+                );
+            }
+            Err(err) => {
+                reporter.log(format!("queue download failed {} ({})", url, err));
+                items.push(ItemOutcome::failed(url, "download", ItemErrorCode::Db, err));
+            }
+        }
+    }
     reporter.log(format!("Queued {} download(s).", added));
     reporter.progress(100);
     reporter
-        .set_result_async(serde_json::json!({ "added": added }))
+        .set_result_async(
+            serde_json::to_value(HubDownloadAllResult { added, items })
+                .map_err(|err| err.to_string())?,
+        )
         .await;
     Ok(())
 }
@@ -373,6 +419,7 @@ pub fn get_info() -> Result<Value, String> {
     let body = json!({ "source": "VaM", "action": "getInfo" });
     let resp = client
         .post(HUB_API)
+        .header(header::COOKIE, hub_auth::cookie_header(HUB_API))
         .json(&body)
         .send()
         .map_err(|err| err.to_string())?;
@@ -558,6 +605,7 @@ pub fn get_resources(query: HubResourcesQuery) -> Result<Value, String> {
     let client = Client::new();
     let resp = client
         .post(HUB_API)
+        .header(header::COOKIE, hub_auth::cookie_header(HUB_API))
         .json(&body)
         .send()
         .map_err(|err| err.to_string())?;
@@ -574,6 +622,7 @@ pub fn get_resource_detail(resource_id: &str) -> Result<Value, String> {
     });
     let resp = client
         .post(HUB_API)
+        .header(header::COOKIE, hub_auth::cookie_header(HUB_API))
         .json(&body)
         .send()
         .map_err(|err| err.to_string())?;
@@ -594,6 +643,7 @@ pub fn find_packages_maps(
     });
     let resp = client
         .post(HUB_API)
+        .header(header::COOKIE, hub_auth::cookie_header(HUB_API))
         .json(&body)
         .send()
         .map_err(|err| err.to_string())?;
@@ -805,7 +855,7 @@ async fn filter_existing_downloads(
     Ok(())
 }
 
-fn hub_headers() -> header::HeaderMap {
+fn hub_headers(url: &str) -> header::HeaderMap {
     let mut headers = header::HeaderMap::new();
     headers.insert(
         header::ACCEPT,
@@ -817,7 +867,9 @@ fn hub_headers() -> header::HeaderMap {
         header::ACCEPT_LANGUAGE,
         "en-US,en;q=0.9".parse().unwrap(),
     );
-    headers.insert(header::COOKIE, "vamhubconsent=yes".parse().unwrap());
+    if let Ok(cookie) = header::HeaderValue::from_str(&hub_auth::cookie_header(url)) {
+        headers.insert(header::COOKIE, cookie);
+    }
     headers.insert(
         header::USER_AGENT,
         "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/127.0.0.0 Safari/537.36"
@@ -833,7 +885,7 @@ pub fn get_overview_panel(resource_id: &str) -> Result<HubOverviewPanelData, Str
 
     let response = client
         .get(&url)
-        .headers(hub_headers())
+        .headers(hub_headers(&url))
         .send()
         .map_err(|err| err.to_string())?;
 
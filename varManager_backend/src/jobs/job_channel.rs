@@ -5,6 +5,9 @@
 //! - JobManager (single async task): consumes events and updates state.jobs
 //! - HTTP handlers: read state.jobs (no contention with job execution)
 
+use super::job_locks::{JobBlockers, JobResource};
+use super::job_log_store::JobLogStore;
+use super::outcome::ItemOutcome;
 use chrono::{Local, SecondsFormat};
 use serde_json::Value;
 use std::collections::{HashMap, VecDeque};
@@ -14,7 +17,7 @@ use tokio::sync::mpsc;
 /// Channel capacity for job events
 const EVENT_CHANNEL_CAPACITY: usize = 10_000;
 
-/// Maximum log lines per job
+/// Maximum log lines kept in memory per job; the full log is in `JobLogStore`
 const MAX_LOG_LINES: usize = 1000;
 
 #[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
@@ -26,7 +29,7 @@ pub enum JobLogLevel {
     Debug,
 }
 
-#[derive(Clone, Debug, serde::Serialize)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct JobLogEntry {
     pub timestamp: String,
     pub level: JobLogLevel,
@@ -45,6 +48,15 @@ impl JobLogEntry {
 }
 
 impl JobLogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobLogLevel::Debug => "debug",
+            JobLogLevel::Info => "info",
+            JobLogLevel::Warn => "warn",
+            JobLogLevel::Error => "error",
+        }
+    }
+
     pub fn severity(self) -> u8 {
         match self {
             JobLogLevel::Debug => 0,
@@ -100,6 +112,8 @@ fn infer_log_level(message: &str) -> JobLogLevel {
 /// Events sent from job execution threads to the JobManager
 #[derive(Debug)]
 pub enum JobEvent {
+    /// Job is queued behind conflicting jobs
+    Waiting { id: u64, blockers: JobBlockers },
     /// Job started running
     Started { id: u64, message: String },
     /// Log line from job
@@ -203,10 +217,18 @@ pub struct JobState {
     pub logs: VecDeque<JobLogEntry>,
     pub log_offset: usize,
     pub result: Option<Value>,
+    pub waiting_for: Vec<JobResource>,
+    pub blocked_by: Vec<u64>,
+    /// Args the job was started with, kept so it can be retried.
+    pub args: Option<Value>,
+    /// Job whose failed items this job retries.
+    pub retry_of: Option<u64>,
+    /// VaM profile the job was started for; `None` means the active one.
+    pub profile: Option<String>,
 }
 
 impl JobState {
-    pub fn new(id: u64, kind: String) -> Self {
+    pub fn new(id: u64, kind: String, args: Option<Value>) -> Self {
         Self {
             id,
             kind,
@@ -217,11 +239,22 @@ impl JobState {
             logs: VecDeque::new(),
             log_offset: 0,
             result: None,
+            waiting_for: Vec::new(),
+            blocked_by: Vec::new(),
+            args,
+            retry_of: None,
+            profile: None,
         }
     }
 
-    fn push_log(&mut self, level: JobLogLevel, message: String) {
+    /// Index of the next log line, counting lines dropped from the tail.
+    pub fn log_end(&self) -> usize {
+        self.log_offset + self.logs.len()
+    }
+
+    fn push_log(&mut self, store: &JobLogStore, level: JobLogLevel, message: String) {
         let entry = JobLogEntry::new(level, message);
+        store.append(self.id, self.log_end(), &entry);
         if self.logs.len() >= MAX_LOG_LINES {
             self.logs.pop_front();
             self.log_offset += 1;
@@ -242,6 +275,10 @@ pub struct JobView {
     pub log_offset: usize,
     pub log_count: usize,
     pub result_available: bool,
+    pub waiting_for: Vec<JobResource>,
+    pub blocked_by: Vec<u64>,
+    pub retry_of: Option<u64>,
+    pub profile: Option<String>,
 }
 
 impl From<&JobState> for JobView {
@@ -256,6 +293,10 @@ impl From<&JobState> for JobView {
             log_offset: job.log_offset,
             log_count: job.logs.len(),
             result_available: job.result.is_some(),
+            waiting_for: job.waiting_for.clone(),
+            blocked_by: job.blocked_by.clone(),
+            retry_of: job.retry_of,
+            profile: job.profile.clone(),
         }
     }
 }
@@ -277,6 +318,14 @@ pub struct JobResultResponse {
     pub result: Value,
 }
 
+/// Failed items of a finished job
+#[derive(serde::Serialize)]
+pub struct JobFailedItemsResponse {
+    pub id: u64,
+    pub kind: String,
+    pub items: Vec<ItemOutcome>,
+}
+
 /// Shared job state map (used by HTTP handlers and JobManager)
 pub type JobMap = Arc<tokio::sync::RwLock<HashMap<u64, JobState>>>;
 
@@ -290,11 +339,16 @@ pub fn create_job_map() -> JobMap {
 pub struct JobManager {
     rx: JobEventReceiver,
     jobs: JobMap,
+    log_store: Arc<JobLogStore>,
 }
 
 impl JobManager {
-    pub fn new(rx: JobEventReceiver, jobs: JobMap) -> Self {
-        Self { rx, jobs }
+    pub fn new(rx: JobEventReceiver, jobs: JobMap, log_store: Arc<JobLogStore>) -> Self {
+        Self {
+            rx,
+            jobs,
+            log_store,
+        }
     }
 
     /// Run the job manager. Call this in a spawned task.
@@ -308,12 +362,24 @@ impl JobManager {
 
     async fn handle_event(&self, event: JobEvent) {
         match event {
+            JobEvent::Waiting { id, blockers } => {
+                let mut jobs = self.jobs.write().await;
+                if let Some(job) = jobs.get_mut(&id) {
+                    let message = blockers.describe();
+                    job.message = message.clone();
+                    job.push_log(&self.log_store, JobLogLevel::Info, message);
+                    job.waiting_for = blockers.resources;
+                    job.blocked_by = blockers.job_ids;
+                }
+            }
             JobEvent::Started { id, message } => {
                 let mut jobs = self.jobs.write().await;
                 if let Some(job) = jobs.get_mut(&id) {
                     job.status = JobStatus::Running;
+                    job.waiting_for.clear();
+                    job.blocked_by.clear();
                     job.message = message.clone();
-                    job.push_log(JobLogLevel::Info, message.clone());
+                    job.push_log(&self.log_store, JobLogLevel::Info, message.clone());
                     job.result = None;
                     tracing::info!(job_id = id, job_kind = %job.kind, msg = %message, "job started");
                 }
@@ -321,7 +387,7 @@ impl JobManager {
             JobEvent::Log { id, level, message } => {
                 let mut jobs = self.jobs.write().await;
                 if let Some(job) = jobs.get_mut(&id) {
-                    job.push_log(level, message);
+                    job.push_log(&self.log_store, level, message);
                 }
             }
             JobEvent::Progress { id, value } => {
@@ -343,7 +409,8 @@ impl JobManager {
                     job.progress = 100;
                     job.message = message.clone();
                     job.error = None;
-                    job.push_log(JobLogLevel::Info, message.clone());
+                    job.push_log(&self.log_store, JobLogLevel::Info, message.clone());
+                    self.log_store.close(id);
                     tracing::info!(job_id = id, job_kind = %job.kind, msg = %message, "job completed");
                 }
             }
@@ -353,7 +420,8 @@ impl JobManager {
                     job.status = JobStatus::Failed;
                     job.message = "job failed".to_string();
                     job.error = Some(error.clone());
-                    job.push_log(JobLogLevel::Error, error.clone());
+                    job.push_log(&self.log_store, JobLogLevel::Error, error.clone());
+                    self.log_store.close(id);
                     tracing::error!(job_id = id, job_kind = %job.kind, error = %error, "job failed");
                 }
             }
@@ -362,6 +430,10 @@ impl JobManager {
 }
 
 /// Helper functions for sending events from main.rs (for job lifecycle management)
+pub async fn send_job_waiting(tx: &JobEventSender, id: u64, blockers: JobBlockers) {
+    let _ = tx.send(JobEvent::Waiting { id, blockers }).await;
+}
+
 pub async fn send_job_started(tx: &JobEventSender, id: u64, message: String) {
     let _ = tx.send(JobEvent::Started { id, message }).await;
 }
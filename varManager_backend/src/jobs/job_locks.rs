@@ -0,0 +1,260 @@
+//! Resource locks declared per job kind.
+//!
+//! `job_semaphore` only bounds how many jobs run at once. Jobs that write the
+//! same resource (library files, AddonPackages links, DB rows, Hub session)
+//! must not overlap, so each kind declares the resources it needs and the
+//! scheduler holds a queued job until all of them are free.
+//!
+//! Waiters are served in arrival order: a job cannot take a resource that an
+//! earlier queued job is still waiting for, so a stream of small jobs cannot
+//! starve a large one such as `update_db`.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::futures::Notified;
+use tokio::sync::Notify;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobResource {
+    /// Moves, deletes or writes files under varspath (tidy, stale, previews).
+    VarspathWrite,
+    /// Creates or removes links under AddonPackages.
+    AddonLinksWrite,
+    /// Writes index or install rows in varManager.db.
+    DbWrite,
+    /// Long-running Hub traffic (scans, bulk download queueing).
+    HubNetwork,
+}
+
+impl JobResource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobResource::VarspathWrite => "varspath_write",
+            JobResource::AddonLinksWrite => "addon_links_write",
+            JobResource::DbWrite => "db_write",
+            JobResource::HubNetwork => "hub_network",
+        }
+    }
+}
+
+use JobResource::{AddonLinksWrite, DbWrite, HubNetwork, VarspathWrite};
+
+/// Resources a job kind needs exclusively while it runs.
+pub fn job_resources(kind: &str) -> &'static [JobResource] {
+    match kind {
+        "update_db" | "import_downloads" => &[VarspathWrite, AddonLinksWrite, DbWrite],
+        // Restoring swaps every table, so nothing else may run alongside it.
+        "db_restore" => &[VarspathWrite, AddonLinksWrite, DbWrite, HubNetwork],
+        "db_backup" | "db_check" | "vacuum" => &[DbWrite],
+        "delete_vars" | "stale_vars" | "old_version_vars" => {
+            &[VarspathWrite, AddonLinksWrite, DbWrite]
+        }
+        // Reads the library without touching it, but must not see update_db
+        // moving files underneath it.
+        "reindex" => &[VarspathWrite, DbWrite],
+        "fix_previews" => &[VarspathWrite],
+        "missing_deps" | "rebuild_links" | "install_vars" | "uninstall_vars"
+        | "vars_install_batch" | "vars_toggle_install" | "saves_deps" | "log_deps"
+        | "packswitch_set" => &[AddonLinksWrite, DbWrite],
+        "links_move" | "links_missing_create" | "packswitch_add" | "packswitch_delete"
+        | "packswitch_rename" | "scene_load" => &[AddonLinksWrite],
+        "refresh_install_status" | "scene_hide" | "scene_fav" | "scene_unhide"
+        | "scene_unfav" => &[DbWrite],
+        "hub_missing_scan" | "hub_updates_scan" | "hub_download_all" => &[HubNetwork],
+        _ => &[],
+    }
+}
+
+/// What a queued job is blocked on.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct JobBlockers {
+    pub resources: Vec<JobResource>,
+    pub job_ids: Vec<u64>,
+}
+
+impl JobBlockers {
+    fn push(&mut self, resource: JobResource, job_id: u64) {
+        if !self.resources.contains(&resource) {
+            self.resources.push(resource);
+        }
+        if !self.job_ids.contains(&job_id) {
+            self.job_ids.push(job_id);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        let resources: Vec<&str> = self.resources.iter().map(|r| r.as_str()).collect();
+        let jobs: Vec<String> = self.job_ids.iter().map(|id| id.to_string()).collect();
+        format!(
+            "waiting for {} (jobs {})",
+            resources.join(", "),
+            jobs.join(", ")
+        )
+    }
+}
+
+#[derive(Default)]
+struct LockTable {
+    held: HashMap<JobResource, u64>,
+    waiting: VecDeque<(u64, &'static [JobResource])>,
+}
+
+impl LockTable {
+    fn blockers(&self, id: u64, resources: &[JobResource]) -> JobBlockers {
+        let mut blockers = JobBlockers::default();
+        for resource in resources {
+            if let Some(owner) = self.held.get(resource) {
+                blockers.push(*resource, *owner);
+            }
+        }
+        for (waiter, wanted) in &self.waiting {
+            if *waiter == id {
+                break;
+            }
+            for resource in resources {
+                if wanted.contains(resource) {
+                    blockers.push(*resource, *waiter);
+                }
+            }
+        }
+        blockers
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.waiting.retain(|(waiter, _)| *waiter != id);
+        self.held.retain(|_, owner| *owner != id);
+    }
+}
+
+#[derive(Default)]
+pub struct JobLocks {
+    table: Mutex<LockTable>,
+    changed: Notify,
+}
+
+impl JobLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `id` for `resources`. The returned guard releases the queue slot
+    /// or the held resources when dropped.
+    pub fn enqueue(self: &Arc<Self>, id: u64, resources: &'static [JobResource]) -> JobLockGuard {
+        if !resources.is_empty() {
+            let mut table = self.lock_table();
+            table.waiting.push_back((id, resources));
+        }
+        JobLockGuard {
+            locks: Arc::clone(self),
+            id,
+            resources,
+            acquired: resources.is_empty(),
+        }
+    }
+
+    /// Future that resolves the next time any lock is released.
+    pub fn changed(&self) -> Notified<'_> {
+        self.changed.notified()
+    }
+
+    fn lock_table(&self) -> std::sync::MutexGuard<'_, LockTable> {
+        match self.table.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+}
+
+pub struct JobLockGuard {
+    locks: Arc<JobLocks>,
+    id: u64,
+    resources: &'static [JobResource],
+    acquired: bool,
+}
+
+impl JobLockGuard {
+    /// Take all declared resources if nothing ahead of this job conflicts.
+    pub fn try_acquire(&mut self) -> Result<(), JobBlockers> {
+        if self.acquired {
+            return Ok(());
+        }
+        let mut table = self.locks.lock_table();
+        let blockers = table.blockers(self.id, self.resources);
+        if !blockers.is_empty() {
+            return Err(blockers);
+        }
+        table.waiting.retain(|(waiter, _)| *waiter != self.id);
+        for resource in self.resources {
+            table.held.insert(*resource, self.id);
+        }
+        self.acquired = true;
+        Ok(())
+    }
+}
+
+impl Drop for JobLockGuard {
+    fn drop(&mut self) {
+        if self.resources.is_empty() {
+            return;
+        }
+        self.locks.lock_table().remove(self.id);
+        self.locks.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicting_jobs_wait_for_holder() {
+        let locks = Arc::new(JobLocks::new());
+        let mut first = locks.enqueue(1, job_resources("update_db"));
+        let mut second = locks.enqueue(2, job_resources("rebuild_links"));
+        assert!(first.try_acquire().is_ok());
+
+        let blockers = second.try_acquire().unwrap_err();
+        assert_eq!(blockers.job_ids, vec![1]);
+        assert_eq!(blockers.resources, vec![AddonLinksWrite, DbWrite]);
+
+        drop(first);
+        assert!(second.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn disjoint_jobs_run_together() {
+        let locks = Arc::new(JobLocks::new());
+        let mut links = locks.enqueue(1, job_resources("links_move"));
+        let mut hub = locks.enqueue(2, job_resources("hub_download_all"));
+        let mut noop = locks.enqueue(3, job_resources("noop"));
+        assert!(links.try_acquire().is_ok());
+        assert!(hub.try_acquire().is_ok());
+        assert!(noop.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn later_job_does_not_overtake_earlier_waiter() {
+        let locks = Arc::new(JobLocks::new());
+        let mut holder = locks.enqueue(1, job_resources("refresh_install_status"));
+        let mut big = locks.enqueue(2, job_resources("rebuild_links"));
+        let mut small = locks.enqueue(3, job_resources("links_move"));
+        assert!(holder.try_acquire().is_ok());
+        assert!(big.try_acquire().is_err());
+
+        let blockers = small.try_acquire().unwrap_err();
+        assert_eq!(blockers.job_ids, vec![2]);
+        assert_eq!(blockers.resources, vec![AddonLinksWrite]);
+
+        drop(holder);
+        assert!(big.try_acquire().is_ok());
+        assert!(small.try_acquire().is_err());
+        drop(big);
+        assert!(small.try_acquire().is_ok());
+    }
+}
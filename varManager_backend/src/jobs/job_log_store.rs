@@ -0,0 +1,327 @@
+//! Full job logs persisted under the data dir.
+//!
+//! `JobState.logs` only keeps the last `MAX_LOG_LINES` entries. Every entry is
+//! also appended here as one JSON line tagged with its position in the job's
+//! log, so `/jobs/{id}/logs` can serve lines that already left the in-memory
+//! tail and `/jobs/{id}/logs/download` can return the whole log.
+//!
+//! Files rotate per job at `MAX_SEGMENT_BYTES`, keeping `MAX_SEGMENTS`
+//! segments. Job ids restart with the process, so the directory is cleared
+//! when the store is opened.
+//!
+//! Lines are written by a dedicated thread: `JobManager` appends while it
+//! holds the job map lock, so appending only queues the line.
+
+use super::job_channel::{JobLogEntry, JobLogLevel};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+pub const JOB_LOG_DIR: &str = "job_logs";
+
+const MAX_SEGMENT_BYTES: u64 = 8 * 1024 * 1024;
+const MAX_SEGMENTS: usize = 4;
+
+#[derive(Serialize, Deserialize)]
+struct StoredLine {
+    n: usize,
+    #[serde(flatten)]
+    entry: JobLogEntry,
+}
+
+struct OpenLog {
+    writer: BufWriter<File>,
+    written: u64,
+}
+
+/// Which stored lines to return.
+pub struct JobLogFilter {
+    pub from: usize,
+    pub min_level: JobLogLevel,
+    /// Lowercased substring the message must contain.
+    pub contains: Option<String>,
+    pub limit: usize,
+}
+
+impl JobLogFilter {
+    pub fn matches(&self, entry: &JobLogEntry) -> bool {
+        entry.level.severity() >= self.min_level.severity()
+            && self
+                .contains
+                .as_ref()
+                .map(|needle| entry.message.to_lowercase().contains(needle))
+                .unwrap_or(true)
+    }
+}
+
+/// Lines read back from disk.
+pub struct JobLogPage {
+    /// Index of the oldest line still on disk.
+    pub first: Option<usize>,
+    /// Index after the last line scanned.
+    pub next: usize,
+    pub entries: Vec<JobLogEntry>,
+}
+
+pub struct JobLogStore {
+    dir: PathBuf,
+    writer: mpsc::Sender<WriterOp>,
+}
+
+enum WriterOp {
+    Append {
+        id: u64,
+        n: usize,
+        entry: JobLogEntry,
+    },
+    /// Flush the job's file, then signal `done`.
+    Flush {
+        id: u64,
+        done: mpsc::SyncSender<()>,
+    },
+    Close(u64),
+}
+
+impl JobLogStore {
+    pub fn open(dir: PathBuf) -> Self {
+        if dir.exists() {
+            if let Err(err) = fs::remove_dir_all(&dir) {
+                tracing::warn!(dir = %dir.display(), error = %err, "clear job logs failed");
+            }
+        }
+        if let Err(err) = fs::create_dir_all(&dir) {
+            tracing::warn!(dir = %dir.display(), error = %err, "create job log dir failed");
+        }
+        let (writer, ops) = mpsc::channel();
+        let log_writer = LogWriter {
+            dir: dir.clone(),
+            open: HashMap::new(),
+        };
+        if let Err(err) = thread::Builder::new()
+            .name("job-log-writer".to_string())
+            .spawn(move || log_writer.run(ops))
+        {
+            tracing::warn!(error = %err, "start job log writer failed");
+        }
+        Self { dir, writer }
+    }
+
+    /// Queue entry `n` of job `id` for writing. Errors are logged and
+    /// otherwise ignored: the in-memory tail still has the line.
+    pub fn append(&self, id: u64, n: usize, entry: &JobLogEntry) {
+        let op = WriterOp::Append {
+            id,
+            n,
+            entry: entry.clone(),
+        };
+        if self.writer.send(op).is_err() {
+            tracing::warn!(job_id = id, "job log writer stopped; line not stored");
+        }
+    }
+
+    /// Flush and close the file of a finished job.
+    pub fn close(&self, id: u64) {
+        let _ = self.writer.send(WriterOp::Close(id));
+    }
+
+    /// Wait until every line queued for job `id` is on disk.
+    fn flush(&self, id: u64) {
+        let (done, flushed) = mpsc::sync_channel(1);
+        if self.writer.send(WriterOp::Flush { id, done }).is_ok() {
+            let _ = flushed.recv();
+        }
+    }
+
+    /// Stored lines from `filter.from` on, oldest first.
+    pub fn read(&self, id: u64, filter: &JobLogFilter) -> io::Result<JobLogPage> {
+        self.flush(id);
+        let mut page = JobLogPage {
+            first: None,
+            next: filter.from,
+            entries: Vec::new(),
+        };
+        for path in self.segments(id) {
+            let reader = BufReader::new(File::open(&path)?);
+            for line in reader.lines() {
+                let Ok(stored) = serde_json::from_str::<StoredLine>(&line?) else {
+                    continue;
+                };
+                page.first.get_or_insert(stored.n);
+                if stored.n < filter.from {
+                    continue;
+                }
+                if page.entries.len() >= filter.limit {
+                    return Ok(page);
+                }
+                page.next = stored.n + 1;
+                if filter.matches(&stored.entry) {
+                    page.entries.push(stored.entry);
+                }
+            }
+        }
+        Ok(page)
+    }
+
+    /// The whole stored log as plain text.
+    pub fn render_text(&self, id: u64) -> io::Result<String> {
+        self.flush(id);
+        let mut text = String::new();
+        for path in self.segments(id) {
+            let reader = BufReader::new(File::open(&path)?);
+            for line in reader.lines() {
+                let Ok(stored) = serde_json::from_str::<StoredLine>(&line?) else {
+                    continue;
+                };
+                text.push_str(&format!(
+                    "{} [{}] {}\n",
+                    stored.entry.timestamp,
+                    stored.entry.level.as_str().to_uppercase(),
+                    stored.entry.message
+                ));
+            }
+        }
+        Ok(text)
+    }
+
+    /// Existing segment files of a job, oldest first.
+    fn segments(&self, id: u64) -> Vec<PathBuf> {
+        (0..MAX_SEGMENTS)
+            .rev()
+            .map(|idx| self.segment_path(id, idx))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    fn segment_path(&self, id: u64, idx: usize) -> PathBuf {
+        segment_path(&self.dir, id, idx)
+    }
+}
+
+/// Owns the open log files; runs on the writer thread.
+struct LogWriter {
+    dir: PathBuf,
+    open: HashMap<u64, OpenLog>,
+}
+
+impl LogWriter {
+    fn run(mut self, ops: mpsc::Receiver<WriterOp>) {
+        for op in ops {
+            match op {
+                WriterOp::Append { id, n, entry } => {
+                    if let Err(err) = self.append(id, n, entry) {
+                        tracing::warn!(job_id = id, error = %err, "write job log failed");
+                    }
+                }
+                WriterOp::Flush { id, done } => {
+                    if let Some(log) = self.open.get_mut(&id) {
+                        let _ = log.writer.flush();
+                    }
+                    let _ = done.send(());
+                }
+                WriterOp::Close(id) => {
+                    if let Some(mut log) = self.open.remove(&id) {
+                        let _ = log.writer.flush();
+                    }
+                }
+            }
+        }
+    }
+
+    fn append(&mut self, id: u64, n: usize, entry: JobLogEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&StoredLine { n, entry })?;
+        line.push(b'\n');
+
+        if let Some(log) = self.open.get(&id) {
+            if log.written + line.len() as u64 > MAX_SEGMENT_BYTES {
+                if let Some(mut log) = self.open.remove(&id) {
+                    log.writer.flush()?;
+                }
+                self.rotate(id)?;
+            }
+        }
+        let log = match self.open.entry(id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(slot) => {
+                let path = segment_path(&self.dir, id, 0);
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                let written = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+                slot.insert(OpenLog {
+                    writer: BufWriter::new(file),
+                    written,
+                })
+            }
+        };
+        log.writer.write_all(&line)?;
+        log.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&self, id: u64) -> io::Result<()> {
+        let oldest = segment_path(&self.dir, id, MAX_SEGMENTS - 1);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for idx in (0..MAX_SEGMENTS - 1).rev() {
+            let from = segment_path(&self.dir, id, idx);
+            if from.exists() {
+                fs::rename(&from, segment_path(&self.dir, id, idx + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, id: u64, idx: usize) -> PathBuf {
+    if idx == 0 {
+        dir.join(format!("job-{}.log", id))
+    } else {
+        dir.join(format!("job-{}.log.{}", id, idx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(from: usize) -> JobLogFilter {
+        JobLogFilter {
+            from,
+            min_level: JobLogLevel::Debug,
+            contains: None,
+            limit: usize::MAX,
+        }
+    }
+
+    #[test]
+    fn reads_back_and_filters() {
+        let dir = std::env::temp_dir().join(format!("vm_job_logs_{}", std::process::id()));
+        let store = JobLogStore::open(dir.clone());
+        for n in 0..5 {
+            let level = if n == 3 { JobLogLevel::Error } else { JobLogLevel::Info };
+            store.append(7, n, &JobLogEntry::new(level, format!("line {}", n)));
+        }
+
+        let page = store.read(7, &filter(2)).unwrap();
+        assert_eq!(page.first, Some(0));
+        assert_eq!(page.next, 5);
+        assert_eq!(page.entries.len(), 3);
+
+        let errors = JobLogFilter {
+            min_level: JobLogLevel::Warn,
+            ..filter(0)
+        };
+        let page = store.read(7, &errors).unwrap();
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].message, "line 3");
+
+        let text = store.render_text(7).unwrap();
+        assert!(text.contains("[ERROR] line 3"));
+        store.close(7);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,192 @@
+//! Registry of job kinds with their argument and result types.
+//!
+//! `dispatch` still routes on the kind string; this table is what `POST /jobs`
+//! validates against before a job is queued, and what `GET /jobs/kinds`
+//! publishes so clients can build arguments from the schema instead of
+//! guessing field names.
+
+use super::db_jobs::{DbBackupArgs, DbBackupResult, DbRestoreArgs, DbRestoreResult, VacuumResult};
+use super::deps_jobs::{DepsJobResult, LogDepsArgs, SavesDepsArgs};
+use super::hub::{
+    HubDownloadAllArgs, HubDownloadAllResult, HubDownloadList, HubFindPackagesArgs,
+    HubMissingScanArgs, HubOverviewPanelData, HubResourceDetailArgs, HubResourcesQuery,
+};
+use super::links::{
+    MissingLinksArgs, MissingLinksResult, MoveLinksArgs, MoveLinksResult, RebuildLinksArgs,
+    RebuildLinksResult,
+};
+use super::missing_deps::{MissingDepsArgs, MissingDepsResult};
+use super::packswitch::{PackSwitchArgs, PackSwitchRenameArgs, PackSwitchResult, PackSwitchSetResult};
+use super::preview_jobs::FixPreviewResult;
+use super::provider_jobs::{ResourceProvidersArgs, ResourceProvidersResult};
+use super::stale_jobs::{CombinedStaleResult, StaleVarsArgs};
+use super::system_jobs::{OpenUrlArgs, RescanResult, StartResult};
+use super::update_db::{
+    ImportDownloadsArgs, ImportDownloadsResult, ReindexSummary, UpdateDbSummary,
+};
+use super::vars_jobs::{
+    DeleteVarsArgs, DeleteVarsResult, InstallVarsArgs, InstallVarsResult, PreviewUninstallArgs,
+    PreviewUninstallResult, UninstallVarsArgs, UninstallVarsResult,
+};
+use super::vars_misc::{
+    ExportInstalledArgs, ExportInstalledResult, InstallBatchArgs, InstallBatchResult, LocateArgs,
+    RefreshInstalledResult, ToggleInstallArgs, ToggleInstallResult,
+};
+use crate::infra::db_maintenance::DbCheckReport;
+use crate::scenes::{
+    CacheClearArgs, SceneAnalyzeArgs, SceneAnalyzeResult, SceneAtomsArgs, SceneHideFavArgs,
+    SceneLoadArgs, SceneLoadResult, ScenePresetArgs, ScenePresetLookArgs, ScenePresetSceneArgs,
+};
+use schemars::{schema_for, JsonSchema, Schema};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Arguments of kinds that take none. An empty object is accepted so clients
+/// can always send `{}`.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NoArgs {}
+
+pub struct JobKindSpec {
+    pub kind: &'static str,
+    /// The job fails without args; other kinds fall back to defaults.
+    pub args_required: bool,
+    args_schema: fn() -> Schema,
+    result_schema: fn() -> Schema,
+    parse_args: fn(Value) -> Result<(), serde_json::Error>,
+}
+
+impl JobKindSpec {
+    pub fn args_schema(&self) -> Schema {
+        (self.args_schema)()
+    }
+
+    pub fn result_schema(&self) -> Schema {
+        (self.result_schema)()
+    }
+}
+
+fn schema<T: JsonSchema>() -> Schema {
+    schema_for!(T)
+}
+
+fn parse<A: DeserializeOwned>(value: Value) -> Result<(), serde_json::Error> {
+    serde_json::from_value::<A>(value).map(|_| ())
+}
+
+const fn kind<A: DeserializeOwned + JsonSchema, R: JsonSchema>(
+    kind: &'static str,
+    args_required: bool,
+) -> JobKindSpec {
+    JobKindSpec {
+        kind,
+        args_required,
+        args_schema: schema::<A>,
+        result_schema: schema::<R>,
+        parse_args: parse::<A>,
+    }
+}
+
+/// Every kind handled by `dispatch`. `()` marks a job without a result,
+/// `Value` one that passes Hub JSON through unchanged.
+pub static JOB_KINDS: &[JobKindSpec] = &[
+    kind::<NoArgs, ()>("noop", false),
+    kind::<NoArgs, UpdateDbSummary>("update_db", false),
+    kind::<NoArgs, ReindexSummary>("reindex", false),
+    kind::<ImportDownloadsArgs, ImportDownloadsResult>("import_downloads", true),
+    kind::<MissingDepsArgs, MissingDepsResult>("missing_deps", true),
+    kind::<RebuildLinksArgs, RebuildLinksResult>("rebuild_links", false),
+    kind::<MoveLinksArgs, MoveLinksResult>("links_move", true),
+    kind::<MissingLinksArgs, MissingLinksResult>("links_missing_create", true),
+    kind::<InstallVarsArgs, InstallVarsResult>("install_vars", true),
+    kind::<PreviewUninstallArgs, PreviewUninstallResult>("preview_uninstall", true),
+    kind::<UninstallVarsArgs, UninstallVarsResult>("uninstall_vars", true),
+    kind::<DeleteVarsArgs, DeleteVarsResult>("delete_vars", true),
+    kind::<ExportInstalledArgs, ExportInstalledResult>("vars_export_installed", true),
+    kind::<InstallBatchArgs, InstallBatchResult>("vars_install_batch", true),
+    kind::<ToggleInstallArgs, ToggleInstallResult>("vars_toggle_install", true),
+    kind::<LocateArgs, ()>("vars_locate", true),
+    kind::<NoArgs, RefreshInstalledResult>("refresh_install_status", false),
+    kind::<SavesDepsArgs, DepsJobResult>("saves_deps", false),
+    kind::<LogDepsArgs, DepsJobResult>("log_deps", false),
+    kind::<NoArgs, FixPreviewResult>("fix_previews", false),
+    kind::<ResourceProvidersArgs, ResourceProvidersResult>("resource_providers", true),
+    kind::<DbBackupArgs, DbBackupResult>("db_backup", false),
+    kind::<DbRestoreArgs, DbRestoreResult>("db_restore", true),
+    kind::<NoArgs, DbCheckReport>("db_check", false),
+    kind::<NoArgs, VacuumResult>("vacuum", false),
+    kind::<StaleVarsArgs, CombinedStaleResult>("stale_vars", false),
+    kind::<NoArgs, CombinedStaleResult>("old_version_vars", false),
+    kind::<PackSwitchArgs, PackSwitchResult>("packswitch_add", true),
+    kind::<PackSwitchArgs, PackSwitchResult>("packswitch_delete", true),
+    kind::<PackSwitchRenameArgs, PackSwitchResult>("packswitch_rename", true),
+    kind::<PackSwitchArgs, PackSwitchSetResult>("packswitch_set", true),
+    kind::<HubMissingScanArgs, HubDownloadList>("hub_missing_scan", false),
+    kind::<NoArgs, HubDownloadList>("hub_updates_scan", false),
+    kind::<HubDownloadAllArgs, HubDownloadAllResult>("hub_download_all", true),
+    kind::<NoArgs, Value>("hub_info", false),
+    kind::<HubResourcesQuery, Value>("hub_resources", true),
+    kind::<HubResourceDetailArgs, HubDownloadList>("hub_resource_detail", true),
+    kind::<HubResourceDetailArgs, HubOverviewPanelData>("hub_overview_panel", true),
+    kind::<HubFindPackagesArgs, HubDownloadList>("hub_find_packages", true),
+    kind::<SceneLoadArgs, SceneLoadResult>("scene_load", true),
+    kind::<SceneAnalyzeArgs, SceneAnalyzeResult>("scene_analyze", true),
+    kind::<ScenePresetLookArgs, SceneLoadResult>("scene_preset_look", true),
+    kind::<ScenePresetArgs, SceneLoadResult>("scene_preset_plugin", true),
+    kind::<ScenePresetArgs, SceneLoadResult>("scene_preset_pose", true),
+    kind::<ScenePresetArgs, SceneLoadResult>("scene_preset_animation", true),
+    kind::<ScenePresetSceneArgs, SceneLoadResult>("scene_preset_scene", true),
+    kind::<SceneAtomsArgs, SceneLoadResult>("scene_add_atoms", true),
+    kind::<SceneAtomsArgs, SceneLoadResult>("scene_add_subscene", true),
+    kind::<SceneHideFavArgs, ()>("scene_hide", true),
+    kind::<SceneHideFavArgs, ()>("scene_fav", true),
+    kind::<SceneHideFavArgs, ()>("scene_unhide", true),
+    kind::<SceneHideFavArgs, ()>("scene_unfav", true),
+    kind::<CacheClearArgs, ()>("cache_clear", true),
+    kind::<NoArgs, StartResult>("vam_start", false),
+    kind::<NoArgs, RescanResult>("rescan_packages", false),
+    kind::<OpenUrlArgs, ()>("open_url", true),
+];
+
+pub fn find_job_kind(kind: &str) -> Option<&'static JobKindSpec> {
+    JOB_KINDS.iter().find(|spec| spec.kind == kind)
+}
+
+/// Check `args` against the kind's argument type before the job is queued,
+/// so a typo in a field name is reported instead of silently ignored.
+pub fn validate_job_args(kind: &str, args: Option<&Value>) -> Result<(), String> {
+    let spec = find_job_kind(kind).ok_or_else(|| format!("unknown job kind: {}", kind))?;
+    match args {
+        None | Some(Value::Null) => {
+            if spec.args_required {
+                return Err(format!("{} args required", kind));
+            }
+            Ok(())
+        }
+        Some(value) => (spec.parse_args)(value.clone())
+            .map_err(|err| format!("invalid args for {}: {}", kind, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let err = validate_job_args("install_vars", Some(&json!({ "var_name": ["a.b.1"] })))
+            .unwrap_err();
+        assert!(err.contains("unknown field `var_name`"), "{}", err);
+        assert!(validate_job_args("install_vars", Some(&json!({ "var_names": ["a.b.1"] }))).is_ok());
+    }
+
+    #[test]
+    fn optional_args_may_be_omitted() {
+        assert!(validate_job_args("update_db", None).is_ok());
+        assert!(validate_job_args("hub_missing_scan", Some(&json!({}))).is_ok());
+        assert!(validate_job_args("install_vars", None).is_err());
+        assert!(validate_job_args("no_such_kind", None).is_err());
+    }
+}
@@ -1,17 +1,22 @@
 use crate::infra::db::{upsert_install_status, var_exists_conn};
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, resolve_var_file_path, INSTALL_LINK_DIR, MISSING_LINK_DIR};
+use crate::jobs::outcome::{ItemErrorCode, ItemOutcome};
+use crate::infra::paths::{
+    config_paths, profile_name, resolve_var_file_path, INSTALL_LINK_DIR, MISSING_LINK_DIR,
+};
 use crate::app::AppState;
 use crate::infra::winfs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-#[derive(Deserialize)]
-struct RebuildLinksArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RebuildLinksArgs {
     #[serde(default = "default_true")]
     include_missing: bool,
 }
@@ -20,44 +25,50 @@ fn default_true() -> bool {
     true
 }
 
-#[derive(Serialize)]
-struct RebuildLinksResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct RebuildLinksResult {
     total: usize,
     rebuilt: usize,
     skipped: usize,
     failed: usize,
+    items: Vec<ItemOutcome>,
 }
 
-#[derive(Deserialize)]
-struct MoveLinksArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MoveLinksArgs {
     var_names: Vec<String>,
     target_dir: String,
 }
 
-#[derive(Serialize)]
-struct MoveLinksResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct MoveLinksResult {
     total: usize,
     moved: usize,
     skipped: usize,
+    items: Vec<ItemOutcome>,
 }
 
-#[derive(Deserialize)]
-struct MissingLinksArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MissingLinksArgs {
     links: Vec<MissingLinkItem>,
 }
 
-#[derive(Deserialize)]
-struct MissingLinkItem {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MissingLinkItem {
     missing_var: String,
     dest_var: String,
 }
 
-#[derive(Serialize)]
-struct MissingLinksResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct MissingLinksResult {
     total: usize,
     created: usize,
     skipped: usize,
     failed: usize,
+    items: Vec<ItemOutcome>,
 }
 
 pub async fn run_rebuild_links_job(
@@ -111,6 +122,7 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
     reporter.progress(1);
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let mut links =
@@ -130,6 +142,7 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
     let mut rebuilt = 0;
     let mut skipped = 0;
     let mut failed = 0;
+    let mut items = Vec::with_capacity(total);
 
     for (idx, link_path) in links.iter().enumerate() {
         let target = match winfs::read_link_target(link_path) {
@@ -141,6 +154,14 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
                     err
                 ));
                 skipped += 1;
+                items.push(
+                    ItemOutcome::skipped(
+                        link_path.display().to_string(),
+                        "relink",
+                        ItemErrorCode::InvalidInput,
+                    )
+                    .with_message(err),
+                );
                 continue;
             }
         };
@@ -160,6 +181,11 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
             Some(name) => name,
             None => {
                 skipped += 1;
+                items.push(ItemOutcome::skipped(
+                    link_path.display().to_string(),
+                    "relink",
+                    ItemErrorCode::InvalidInput,
+                ));
                 continue;
             }
         };
@@ -167,6 +193,7 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
         if !handle.block_on(var_exists_conn(pool, &var_name))? {
             reporter.log(format!("skip missing record {}", var_name));
             skipped += 1;
+            items.push(ItemOutcome::skipped(&var_name, "relink", ItemErrorCode::NotIndexed));
             continue;
         }
 
@@ -175,6 +202,7 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
             Err(err) => {
                 reporter.log(format!("skip {} ({})", var_name, err));
                 failed += 1;
+                items.push(ItemOutcome::failed(&var_name, "relink", ItemErrorCode::NotFound, err));
                 continue;
             }
         };
@@ -190,6 +218,7 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
         if let Err(err) = winfs::create_symlink_file(link_path, &dest) {
             reporter.log(format!("rebuild failed {} ({})", var_name, err));
             failed += 1;
+            items.push(ItemOutcome::failed(&var_name, "relink", ItemErrorCode::Io, err));
             continue;
         }
 
@@ -197,8 +226,9 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
             reporter.log(format!("set time failed {} ({})", var_name, err));
         }
 
-        let _ = handle.block_on(upsert_install_status(pool, &var_name, true, false));
+        let _ = handle.block_on(upsert_install_status(pool, &profile, &var_name, true, false));
         rebuilt += 1;
+        items.push(ItemOutcome::succeeded(&var_name, "relink"));
 
         if total > 0 && (idx % 200 == 0 || idx + 1 == total) {
             let progress = 5 + ((idx + 1) * 90 / total) as u8;
@@ -212,6 +242,7 @@ fn rebuild_links_blocking(state: &AppState, reporter: &JobReporter, args: Rebuil
             rebuilt,
             skipped,
             failed,
+            items,
         })
         .map_err(|err| err.to_string())?,
     );
@@ -237,23 +268,35 @@ fn move_links_blocking(state: &AppState, reporter: &JobReporter, args: MoveLinks
     let total = args.var_names.len();
     let mut moved = 0;
     let mut skipped = 0;
+    let mut items = Vec::with_capacity(total);
 
     for var_name in &args.var_names {
         let match_path = find_link_path(&link_root, var_name);
         let Some(src) = match_path else {
             skipped += 1;
+            items.push(ItemOutcome::skipped(var_name, "move_link", ItemErrorCode::NotInstalled));
             continue;
         };
         let dest = dest_dir.join(format!("{}.var", var_name));
         if dest.exists() {
             skipped += 1;
+            items.push(ItemOutcome::skipped(var_name, "move_link", ItemErrorCode::TargetExists));
             continue;
         }
         match fs::rename(&src, &dest) {
-            Ok(_) => moved += 1,
+            Ok(_) => {
+                moved += 1;
+                items.push(ItemOutcome::succeeded(var_name, "move_link"));
+            }
             Err(err) => {
                 reporter.log(format!("move failed {} ({})", src.display(), err));
                 skipped += 1;
+                items.push(ItemOutcome::failed(
+                    var_name,
+                    "move_link",
+                    ItemErrorCode::Io,
+                    err.to_string(),
+                ));
             }
         }
     }
@@ -263,6 +306,7 @@ fn move_links_blocking(state: &AppState, reporter: &JobReporter, args: MoveLinks
             total,
             moved,
             skipped,
+            items,
         })
         .map_err(|err| err.to_string())?,
     );
@@ -283,12 +327,19 @@ fn missing_links_create_blocking(
     let mut created = 0;
     let mut skipped = 0;
     let mut failed = 0;
+    let mut items = Vec::with_capacity(total);
 
     for item in args.links {
-        let mut missing_var = item.missing_var.trim().to_string();
+        let requested = item.missing_var.trim().to_string();
+        let mut missing_var = requested.clone();
         let dest_var = item.dest_var.trim();
         if missing_var.is_empty() {
             skipped += 1;
+            items.push(ItemOutcome::skipped(
+                &requested,
+                "link_missing",
+                ItemErrorCode::InvalidInput,
+            ));
             continue;
         }
 
@@ -301,6 +352,11 @@ fn missing_links_create_blocking(
 
         if dest_var.is_empty() {
             skipped += 1;
+            items.push(ItemOutcome::skipped(
+                &requested,
+                "link_missing",
+                ItemErrorCode::InvalidInput,
+            ));
             continue;
         }
 
@@ -317,6 +373,12 @@ fn missing_links_create_blocking(
             Err(err) => {
                 reporter.log(format!("missing link skip {} ({})", dest_var, err));
                 failed += 1;
+                items.push(ItemOutcome::failed(
+                    &requested,
+                    "link_missing",
+                    ItemErrorCode::NotFound,
+                    err,
+                ));
                 continue;
             }
         };
@@ -327,10 +389,12 @@ fn missing_links_create_blocking(
                     reporter.log(format!("set time failed {} ({})", missing_var, err));
                 }
                 created += 1;
+                items.push(ItemOutcome::succeeded(&requested, "link_missing"));
             }
             Err(err) => {
                 reporter.log(format!("link create failed {} ({})", missing_var, err));
                 failed += 1;
+                items.push(ItemOutcome::failed(&requested, "link_missing", ItemErrorCode::Io, err));
             }
         }
     }
@@ -341,6 +405,7 @@ fn missing_links_create_blocking(
             created,
             skipped,
             failed,
+            items,
         })
         .map_err(|err| err.to_string())?,
     );
@@ -3,24 +3,26 @@ use crate::infra::db::{
     list_var_versions, upsert_install_status, var_exists_conn,
 };
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, resolve_var_file_path, INSTALL_LINK_DIR};
+use crate::infra::paths::{config_paths, profile_name, resolve_var_file_path, INSTALL_LINK_DIR};
 use crate::app::AppState;
 use crate::infra::winfs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use std::path::Path;
 use sqlx::SqlitePool;
 
-#[derive(Deserialize)]
-struct MissingDepsArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MissingDepsArgs {
     scope: String,
     #[serde(default)]
     var_names: Vec<String>,
 }
 
-#[derive(Serialize)]
-struct MissingDepsResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct MissingDepsResult {
     scope: String,
     missing: Vec<String>,
     installed: Vec<String>,
@@ -48,10 +50,11 @@ fn missing_deps_blocking(state: &AppState, reporter: &JobReporter, args: Missing
     reporter.progress(1);
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let deps = match args.scope.as_str() {
-        "installed" => handle.block_on(list_dependencies_for_installed(pool))?,
+        "installed" => handle.block_on(list_dependencies_for_installed(pool, &profile))?,
         "all" => handle.block_on(list_dependencies_all(pool))?,
         "filtered" => handle.block_on(list_dependencies_for_vars(pool, &args.var_names))?,
         _ => return Err(format!("unsupported scope: {}", args.scope)),
@@ -87,6 +90,7 @@ fn missing_deps_blocking(state: &AppState, reporter: &JobReporter, args: Missing
                     match handle.block_on(install_var(
                         reporter,
                         pool,
+                        &profile,
                         varspath.as_ref().unwrap(),
                         vampath.as_ref().unwrap(),
                         &var_name,
@@ -106,6 +110,7 @@ fn missing_deps_blocking(state: &AppState, reporter: &JobReporter, args: Missing
                     match handle.block_on(install_var(
                         reporter,
                         pool,
+                        &profile,
                         varspath.as_ref().unwrap(),
                         vampath.as_ref().unwrap(),
                         &resolved,
@@ -238,6 +243,7 @@ enum InstallOutcome {
 async fn install_var(
     reporter: &JobReporter,
     pool: &SqlitePool,
+    profile: &str,
     varspath: &Path,
     vampath: &Path,
     var_name: &str,
@@ -259,7 +265,7 @@ async fn install_var(
     winfs::create_symlink_file(&link_path, &dest)?;
     set_link_times(&link_path, &dest)?;
 
-    upsert_install_status(pool, var_name, true, false).await?;
+    upsert_install_status(pool, profile, var_name, true, false).await?;
     reporter.log(format!("{} installed", var_name));
     Ok(InstallOutcome::Installed)
 }
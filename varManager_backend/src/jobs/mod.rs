@@ -1,24 +1,89 @@
+pub mod auto_import;
+pub mod db_jobs;
 pub mod deps_jobs;
 pub mod hub;
 pub mod job_channel;
+pub mod job_locks;
+pub mod job_log_store;
+pub mod kinds;
 pub mod links;
 pub mod missing_deps;
+pub mod outcome;
 pub mod packswitch;
 pub mod preview_jobs;
+pub mod provider_jobs;
+pub mod retry;
 pub mod stale_jobs;
 pub mod system_jobs;
 pub mod update_db;
 pub mod vars_jobs;
 pub mod vars_misc;
 
-use self::job_channel::{send_job_failed, send_job_finished, send_job_started, JobReporter};
+use self::job_channel::{
+    send_job_failed, send_job_finished, send_job_started, send_job_waiting, JobReporter,
+    JobState,
+};
+use self::job_locks::job_resources;
 use crate::app::AppState;
+use crate::infra::paths::read_only_library;
 use crate::scenes;
 use serde_json::Value;
+use std::sync::atomic::Ordering;
+
+/// Kinds that move or delete files under varspath.
+const LIBRARY_MOVING_KINDS: &[&str] = &["delete_vars", "stale_vars", "old_version_vars"];
+
+/// Refuse kinds that would move or delete library files while the library
+/// is read-only.
+pub fn check_library_writable(state: &AppState, kind: &str) -> Result<(), String> {
+    if LIBRARY_MOVING_KINDS.contains(&kind) && read_only_library(state) {
+        return Err(format!("{} is not allowed on a read-only library", kind));
+    }
+    Ok(())
+}
+
+/// Register a job and start it. The job keeps `state.profile`, so a retry
+/// runs against the same VaM profile.
+pub async fn queue_job(
+    state: &AppState,
+    kind: &str,
+    args: Option<Value>,
+    retry_of: Option<u64>,
+) -> u64 {
+    let id = state.job_counter.fetch_add(1, Ordering::SeqCst);
+    let mut job = JobState::new(id, kind.to_string(), args.clone());
+    job.retry_of = retry_of;
+    job.profile = state.profile.clone();
+    {
+        let mut jobs = state.jobs.write().await;
+        jobs.insert(id, job);
+    }
+
+    spawn_job(state.clone(), id, kind.to_string(), args);
+    id
+}
 
 pub fn spawn_job(state: AppState, id: u64, kind: String, args: Option<Value>) {
     let job_tx = state.job_tx.clone();
     tokio::spawn(async move {
+        // Wait for conflicting jobs before taking a concurrency slot, so a
+        // blocked job does not hold a permit other jobs could use.
+        let mut lock_guard = state.job_locks.enqueue(id, job_resources(&kind));
+        let mut last_blockers = None;
+        loop {
+            let changed = state.job_locks.changed();
+            match lock_guard.try_acquire() {
+                Ok(()) => break,
+                Err(blockers) => {
+                    if last_blockers.as_ref() != Some(&blockers) {
+                        send_job_waiting(&job_tx, id, blockers.clone()).await;
+                        last_blockers = Some(blockers);
+                    }
+                }
+            }
+            changed.await;
+        }
+
         let semaphore = match state.job_semaphore.read() {
             Ok(guard) => guard.clone(),
             Err(err) => err.into_inner().clone(),
@@ -34,7 +99,9 @@ pub fn spawn_job(state: AppState, id: u64, kind: String, args: Option<Value>) {
         // Create JobReporter for this job
         let reporter = JobReporter::new(id, job_tx.clone());
 
-        if let Err(err) = dispatch(&state, &reporter, &kind, args).await {
+        let outcome = dispatch(&state, &reporter, &kind, args).await;
+        drop(lock_guard);
+        if let Err(err) = outcome {
             send_job_failed(&job_tx, id, err).await;
             return;
         }
@@ -48,6 +115,7 @@ pub async fn dispatch(
     kind: &str,
     args: Option<Value>,
 ) -> Result<(), String> {
+    check_library_writable(state, kind)?;
     match kind {
         "noop" => {
             let _args = args;
@@ -60,6 +128,10 @@ pub async fn dispatch(
             Ok(())
         }
         "update_db" => update_db::run_update_db_job(state.clone(), reporter.clone()).await,
+        "reindex" => update_db::run_reindex_job(state.clone(), reporter.clone()).await,
+        "import_downloads" => {
+            update_db::run_import_downloads_job(state.clone(), reporter.clone(), args).await
+        }
         "missing_deps" => {
             missing_deps::run_missing_deps_job(state.clone(), reporter.clone(), args).await
         }
@@ -92,6 +164,13 @@ pub async fn dispatch(
         "saves_deps" => deps_jobs::run_saves_deps_job(state.clone(), reporter.clone(), args).await,
         "log_deps" => deps_jobs::run_log_deps_job(state.clone(), reporter.clone(), args).await,
         "fix_previews" => preview_jobs::run_fix_previews_job(state.clone(), reporter.clone(), args).await,
+        "db_backup" => db_jobs::run_db_backup_job(state.clone(), reporter.clone(), args).await,
+        "db_restore" => db_jobs::run_db_restore_job(state.clone(), reporter.clone(), args).await,
+        "db_check" => db_jobs::run_db_check_job(state.clone(), reporter.clone(), args).await,
+        "vacuum" => db_jobs::run_vacuum_job(state.clone(), reporter.clone(), args).await,
+        "resource_providers" => {
+            provider_jobs::run_resource_providers_job(state.clone(), reporter.clone(), args).await
+        }
         "stale_vars" => stale_jobs::run_stale_vars_job(state.clone(), reporter.clone(), args).await,
         "old_version_vars" => {
             stale_jobs::run_old_version_vars_job(state.clone(), reporter.clone(), args).await
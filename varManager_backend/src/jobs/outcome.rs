@@ -0,0 +1,164 @@
+//! Per-item outcomes shared by job results.
+//!
+//! Jobs that act on many vars, links or downloads list every item they
+//! touched in an `items` field next to their summary counts, so a caller can
+//! see which items failed and why without parsing log lines.
+//! `GET /jobs/{id}/failed` reads these lists back out of a finished job.
+
+use crate::infra::paths::resolve_var_file_path;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Succeeded,
+    Skipped,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemErrorCode {
+    /// The var file is not in the library.
+    NotFound,
+    /// Nothing to do: already installed, already queued.
+    AlreadyDone,
+    /// The var has no install link to act on.
+    NotInstalled,
+    /// Another var still depends on it.
+    HasDependents,
+    /// The destination path is already taken.
+    TargetExists,
+    /// The var has no index record.
+    NotIndexed,
+    /// The input could not be interpreted as a var name or URL.
+    InvalidInput,
+    /// The file is not a usable var package (name, zip or meta.json).
+    InvalidPackage,
+    /// The library is read-only.
+    ReadOnly,
+    /// A filesystem operation failed.
+    Io,
+    /// A database write failed.
+    Db,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ItemOutcome {
+    /// Var name, link name or URL, as it appears in the job args.
+    pub item: String,
+    pub action: String,
+    pub status: ItemStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ItemErrorCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl ItemOutcome {
+    pub fn succeeded(item: impl Into<String>, action: &str) -> Self {
+        Self {
+            item: item.into(),
+            action: action.to_string(),
+            status: ItemStatus::Succeeded,
+            code: None,
+            message: None,
+        }
+    }
+
+    pub fn skipped(item: impl Into<String>, action: &str, code: ItemErrorCode) -> Self {
+        Self {
+            item: item.into(),
+            action: action.to_string(),
+            status: ItemStatus::Skipped,
+            code: Some(code),
+            message: None,
+        }
+    }
+
+    pub fn failed(
+        item: impl Into<String>,
+        action: &str,
+        code: ItemErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            item: item.into(),
+            action: action.to_string(),
+            status: ItemStatus::Failed,
+            code: Some(code),
+            message: Some(message.into()),
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+/// Failed `install` item for `var_name`: `not_found` when its file is not
+/// in the library, `io` otherwise.
+pub fn install_failure(varspath: &Path, var_name: &str, err: String) -> ItemOutcome {
+    let code = if resolve_var_file_path(varspath, var_name).is_err() {
+        ItemErrorCode::NotFound
+    } else {
+        ItemErrorCode::Io
+    };
+    ItemOutcome::failed(var_name, "install", code, err)
+}
+
+/// Failed items recorded in a job result, either in its top-level `items`
+/// or in the `items` of a nested section (e.g. `stale` / `old_version`).
+pub fn failed_items(result: &Value) -> Vec<ItemOutcome> {
+    let mut failed = Vec::new();
+    collect_failed(result, &mut failed);
+    if let Value::Object(map) = result {
+        for (key, value) in map {
+            if key != "items" {
+                collect_failed(value, &mut failed);
+            }
+        }
+    }
+    failed
+}
+
+fn collect_failed(section: &Value, out: &mut Vec<ItemOutcome>) {
+    let Some(items) = section.get("items").and_then(|v| v.as_array()) else {
+        return;
+    };
+    for item in items {
+        if let Ok(outcome) = serde_json::from_value::<ItemOutcome>(item.clone()) {
+            if outcome.status == ItemStatus::Failed {
+                out.push(outcome);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn failed_items_reads_top_level_and_sections() {
+        let result = json!({
+            "total": 2,
+            "items": [
+                ItemOutcome::succeeded("a.b.1", "install"),
+                ItemOutcome::failed("a.c.1", "install", ItemErrorCode::NotFound, "missing"),
+            ],
+            "old_version": {
+                "items": [ItemOutcome::failed("a.d.1", "move", ItemErrorCode::Io, "denied")],
+            },
+        });
+        let failed = failed_items(&result);
+        let names: Vec<&str> = failed.iter().map(|item| item.item.as_str()).collect();
+        assert_eq!(names, vec!["a.c.1", "a.d.1"]);
+        assert_eq!(failed[0].code, Some(ItemErrorCode::NotFound));
+    }
+}
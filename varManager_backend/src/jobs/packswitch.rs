@@ -2,11 +2,13 @@ use crate::infra::db::{upsert_install_status, var_exists_conn};
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
 use crate::infra::paths::{
-    addon_packages_dir, addon_switch_root, config_paths, INSTALL_LINK_DIR, MISSING_LINK_DIR,
+    addon_packages_dir, addon_switch_root, config_paths, profile_name, INSTALL_LINK_DIR,
+    MISSING_LINK_DIR,
     TEMP_LINK_DIR,
 };
 use crate::app::AppState;
 use crate::infra::{system_ops, winfs};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::BTreeSet;
@@ -15,24 +17,26 @@ use std::path::{Path, PathBuf};
 use sqlx::SqlitePool;
 use walkdir::WalkDir;
 
-#[derive(Deserialize)]
-struct PackSwitchArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PackSwitchArgs {
     name: String,
 }
 
-#[derive(Deserialize)]
-struct PackSwitchRenameArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PackSwitchRenameArgs {
     old_name: String,
     new_name: String,
 }
 
-#[derive(Serialize)]
-struct PackSwitchResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct PackSwitchResult {
     name: String,
 }
 
-#[derive(Serialize)]
-struct PackSwitchSetResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct PackSwitchSetResult {
     status: String,
     name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -238,7 +242,8 @@ fn set_switch_blocking(
 
     let pool = &state.db_pool;
     let handle = tokio::runtime::Handle::current();
-    let _ = handle.block_on(refresh_install_status(pool, &vampath));
+    let profile = profile_name(state)?;
+    let _ = handle.block_on(refresh_install_status(pool, &profile, &vampath));
     let _ = system_ops::rescan_packages(state);
     reporter.log(format!("switch to {}", name));
     Ok(PackSwitchSetOutcome::Switched)
@@ -393,8 +398,13 @@ fn ensure_addon_dir_link(addon_dir: &Path, pack_dir: &Path) -> Result<(), String
     Ok(())
 }
 
-async fn refresh_install_status(pool: &SqlitePool, vampath: &Path) -> Result<usize, String> {
-    sqlx::query("DELETE FROM installStatus")
+async fn refresh_install_status(
+    pool: &SqlitePool,
+    profile: &str,
+    vampath: &Path,
+) -> Result<usize, String> {
+    sqlx::query("DELETE FROM installStatus WHERE profile = ?1")
+        .bind(profile)
         .execute(pool)
         .await
         .map_err(|err| err.to_string())?;
@@ -406,7 +416,7 @@ async fn refresh_install_status(pool: &SqlitePool, vampath: &Path) -> Result<usi
             continue;
         }
         let disabled = link_path.with_extension("var.disabled").exists();
-        upsert_install_status(pool, &var_name, true, disabled).await?;
+        upsert_install_status(pool, profile, &var_name, true, disabled).await?;
         installed += 1;
     }
     Ok(installed)
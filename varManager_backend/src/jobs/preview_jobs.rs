@@ -1,6 +1,7 @@
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, resolve_var_file_path, PREVIEW_DIR};
+use crate::infra::paths::{config_paths, preview_root, read_only_library, resolve_var_file_path};
 use crate::app::AppState;
+use schemars::JsonSchema;
 use serde::Serialize;
 use serde_json::Value;
 use std::fs::{self, File};
@@ -9,8 +10,8 @@ use std::path::{Path, PathBuf};
 use zip::ZipArchive;
 use sqlx::{Row, SqlitePool};
 
-#[derive(Serialize)]
-struct FixPreviewResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct FixPreviewResult {
     total: usize,
     fixed: usize,
     skipped: usize,
@@ -29,6 +30,7 @@ pub async fn run_fix_previews_job(
 
 fn fix_previews_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), String> {
     let (varspath, _) = config_paths(state)?;
+    let previews_dir = preview_root(&varspath, read_only_library(state));
     reporter.log("FixPreviews start".to_string());
     reporter.progress(1);
 
@@ -41,7 +43,7 @@ fn fix_previews_blocking(state: &AppState, reporter: &JobReporter) -> Result<(),
     let mut failed = 0;
 
     for (idx, scene) in scenes.iter().enumerate() {
-        let preview_path = preview_file_path(&varspath, scene);
+        let preview_path = preview_file_path(&previews_dir, scene);
         if preview_path.exists() {
             skipped += 1;
         } else {
@@ -108,9 +110,8 @@ async fn list_scenes_with_preview(pool: &SqlitePool) -> Result<Vec<ScenePreview>
     Ok(scenes)
 }
 
-fn preview_file_path(varspath: &Path, scene: &ScenePreview) -> PathBuf {
-    varspath
-        .join(PREVIEW_DIR)
+fn preview_file_path(preview_root: &Path, scene: &ScenePreview) -> PathBuf {
+    preview_root
         .join(&scene.atom_type)
         .join(&scene.var_name)
         .join(&scene.preview_pic)
@@ -0,0 +1,79 @@
+use crate::app::AppState;
+use crate::domain::providers::{find_providers, gather_refs, summarize, ProvidersReport};
+use crate::infra::paths::config_paths;
+use crate::jobs::job_channel::JobReporter;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ResourceProvidersArgs {
+    /// Resource paths, with or without a `Creator.Package.Version:/` prefix.
+    #[serde(default)]
+    paths: Vec<String>,
+    /// Scene JSON to collect references from.
+    #[serde(default)]
+    scene: Option<Value>,
+    /// Scene file to read, absolute or relative to the VaM folder.
+    #[serde(default)]
+    scene_file: Option<String>,
+}
+
+pub type ResourceProvidersResult = ProvidersReport;
+
+pub async fn run_resource_providers_job(
+    state: AppState,
+    reporter: JobReporter,
+    args: Option<Value>,
+) -> Result<(), String> {
+    let args = args.ok_or_else(|| "resource_providers args required".to_string())?;
+    let args: ResourceProvidersArgs = serde_json::from_value(args).map_err(|err| err.to_string())?;
+
+    let mut scene = args.scene;
+    if let Some(scene_file) = args.scene_file.as_deref() {
+        let path = scene_file_path(&state, scene_file)?;
+        reporter.log(format!("Reading scene {}", path.display()));
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|err| format!("read {} failed: {}", path.display(), err))?;
+        let parsed: Value = serde_json::from_str(&contents)
+            .map_err(|err| format!("parse {} failed: {}", path.display(), err))?;
+        scene = Some(parsed);
+    }
+    let refs = gather_refs(&args.paths, scene.as_ref())?;
+    reporter.log(format!("Looking up {} resource paths", refs.len()));
+    reporter.progress(1);
+
+    let total = refs.len();
+    let mut lookups = Vec::with_capacity(total);
+    for (idx, resource) in refs.iter().enumerate() {
+        let lookup = find_providers(&state.db_pool, resource).await?;
+        if lookup.candidates.is_empty() {
+            reporter.log(format!("no provider for {}", resource.raw));
+        }
+        lookups.push(lookup);
+        reporter.progress((((idx + 1) * 100) / total).min(100) as u8);
+    }
+
+    let report = summarize(lookups);
+    reporter.log(format!(
+        "ResourceProviders end: {} found, {} missing",
+        report.found, report.missing
+    ));
+    reporter
+        .set_result_async(serde_json::to_value(report).map_err(|err| err.to_string())?)
+        .await;
+    Ok(())
+}
+
+fn scene_file_path(state: &AppState, scene_file: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(scene_file);
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    let (_, vampath) = config_paths(state)?;
+    let vampath = vampath.ok_or_else(|| "vampath is required in config.json".to_string())?;
+    Ok(vampath.join(path))
+}
@@ -0,0 +1,215 @@
+//! Args for retrying the failed items of a finished job.
+//!
+//! A retry runs the same kind again with the original args narrowed to the
+//! items the first run reported as failed. Kinds that select their own items
+//! (`rebuild_links`, `stale_vars`, `old_version_vars`) rerun with the original
+//! args. `vars_toggle_install` becomes an explicit install or uninstall of the
+//! failed names, since toggling again would undo what did succeed.
+
+use super::outcome::ItemOutcome;
+use serde_json::{json, Map, Value};
+
+pub struct RetryPlan {
+    pub kind: String,
+    pub args: Option<Value>,
+}
+
+pub fn plan_retry(
+    kind: &str,
+    args: Option<&Value>,
+    failed: &[ItemOutcome],
+) -> Result<RetryPlan, String> {
+    let mut names: Vec<String> = Vec::new();
+    for outcome in failed {
+        if !names.contains(&outcome.item) {
+            names.push(outcome.item.clone());
+        }
+    }
+    let same_kind = |args: Value| RetryPlan {
+        kind: kind.to_string(),
+        args: Some(args),
+    };
+
+    match kind {
+        "install_vars" | "uninstall_vars" | "delete_vars" | "links_move"
+        | "vars_install_batch" => {
+            let mut args = args_object(args)?;
+            args.insert("var_names".to_string(), json!(names));
+            Ok(same_kind(Value::Object(args)))
+        }
+        "links_missing_create" => {
+            let mut args = args_object(args)?;
+            let links = take_array(&mut args, "links")
+                .into_iter()
+                .filter(|link| {
+                    link.get("missing_var")
+                        .and_then(|v| v.as_str())
+                        .map(|name| names.iter().any(|n| n == name.trim()))
+                        .unwrap_or(false)
+                })
+                .collect::<Vec<_>>();
+            args.insert("links".to_string(), Value::Array(links));
+            Ok(same_kind(Value::Object(args)))
+        }
+        "hub_download_all" => {
+            let mut args = args_object(args)?;
+            let is_failed = |url: Option<&str>| {
+                url.map(|url| names.iter().any(|n| n == url.trim()))
+                    .unwrap_or(false)
+            };
+            if args.contains_key("items") {
+                let items = take_array(&mut args, "items")
+                    .into_iter()
+                    .filter(|item| is_failed(item.get("url").and_then(|v| v.as_str())))
+                    .collect::<Vec<_>>();
+                args.insert("items".to_string(), Value::Array(items));
+            }
+            if args.contains_key("urls") {
+                let urls = take_array(&mut args, "urls")
+                    .into_iter()
+                    .filter(|url| is_failed(url.as_str()))
+                    .collect::<Vec<_>>();
+                args.insert("urls".to_string(), Value::Array(urls));
+            }
+            Ok(same_kind(Value::Object(args)))
+        }
+        "vars_toggle_install" => {
+            let uninstall = failed.iter().all(|outcome| outcome.action == "uninstall");
+            let (kind, args) = if uninstall {
+                (
+                    "uninstall_vars",
+                    json!({ "var_names": names, "include_implicated": false }),
+                )
+            } else {
+                (
+                    "install_vars",
+                    json!({ "var_names": names, "include_dependencies": false }),
+                )
+            };
+            Ok(RetryPlan {
+                kind: kind.to_string(),
+                args: Some(args),
+            })
+        }
+        "import_downloads" => {
+            let named = |action: &str| {
+                failed
+                    .iter()
+                    .filter(|outcome| outcome.action == action)
+                    .map(|outcome| outcome.item.clone())
+                    .collect::<Vec<_>>()
+            };
+            let installs = named("install");
+            // A download that had not completed is reported as `download <id>`.
+            let (downloads, files): (Vec<String>, Vec<String>) = named("import")
+                .into_iter()
+                .partition(|item| download_id(item).is_some());
+            if downloads.is_empty() && files.is_empty() {
+                return Ok(RetryPlan {
+                    kind: "install_vars".to_string(),
+                    args: Some(json!({
+                        "var_names": installs,
+                        "include_dependencies": false,
+                    })),
+                });
+            }
+            let downloads: Vec<i64> =
+                downloads.iter().filter_map(|item| download_id(item)).collect();
+            let mut args = args_object(args)?;
+            if downloads.is_empty() {
+                args.remove("downloads");
+            } else {
+                args.insert("downloads".to_string(), json!(downloads));
+            }
+            args.insert("files".to_string(), json!(files));
+            if installs.is_empty() {
+                args.remove("install_var_names");
+            } else {
+                args.insert("install_var_names".to_string(), json!(installs));
+            }
+            Ok(same_kind(Value::Object(args)))
+        }
+        "rebuild_links" | "stale_vars" | "old_version_vars" => Ok(RetryPlan {
+            kind: kind.to_string(),
+            args: args.cloned(),
+        }),
+        _ => Err(format!("{} does not support retry", kind)),
+    }
+}
+
+fn download_id(item: &str) -> Option<i64> {
+    item.strip_prefix("download ")?.trim().parse().ok()
+}
+
+fn args_object(args: Option<&Value>) -> Result<Map<String, Value>, String> {
+    match args {
+        Some(Value::Object(map)) => Ok(map.clone()),
+        _ => Err("original job args are not an object".to_string()),
+    }
+}
+
+fn take_array(args: &mut Map<String, Value>, key: &str) -> Vec<Value> {
+    match args.remove(key) {
+        Some(Value::Array(values)) => values,
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::outcome::ItemErrorCode;
+
+    #[test]
+    fn narrows_var_names_and_keeps_flags() {
+        let args = json!({ "var_names": ["a.b.1", "a.c.1"], "include_dependencies": false });
+        let failed = [ItemOutcome::failed("a.c.1", "install", ItemErrorCode::Io, "denied")];
+        let plan = plan_retry("install_vars", Some(&args), &failed).unwrap();
+        assert_eq!(plan.kind, "install_vars");
+        assert_eq!(
+            plan.args,
+            Some(json!({ "var_names": ["a.c.1"], "include_dependencies": false }))
+        );
+    }
+
+    #[test]
+    fn toggle_retries_as_explicit_install() {
+        let args = json!({ "var_name": "a.b.1" });
+        let failed = [ItemOutcome::failed("a.dep.2", "install", ItemErrorCode::NotFound, "missing")];
+        let plan = plan_retry("vars_toggle_install", Some(&args), &failed).unwrap();
+        assert_eq!(plan.kind, "install_vars");
+        assert_eq!(
+            plan.args,
+            Some(json!({ "var_names": ["a.dep.2"], "include_dependencies": false }))
+        );
+    }
+
+    #[test]
+    fn import_retries_failed_files_only() {
+        let args = json!({ "downloads": [3, 4, 5], "install": true });
+        let failed = [
+            ItemOutcome::failed("D:/dl/a.b.1.var", "import", ItemErrorCode::Io, "locked"),
+            ItemOutcome::failed("download 5", "import", ItemErrorCode::NotFound, "not completed"),
+            ItemOutcome::failed("a.dep.2", "install", ItemErrorCode::Io, "denied"),
+        ];
+        let plan = plan_retry("import_downloads", Some(&args), &failed).unwrap();
+        assert_eq!(plan.kind, "import_downloads");
+        assert_eq!(
+            plan.args,
+            Some(json!({
+                "downloads": [5],
+                "files": ["D:/dl/a.b.1.var"],
+                "install": true,
+                "install_var_names": ["a.dep.2"],
+            }))
+        );
+
+        let failed = [ItemOutcome::failed("a.dep.2", "install", ItemErrorCode::Io, "denied")];
+        let plan = plan_retry("import_downloads", Some(&args), &failed).unwrap();
+        assert_eq!(plan.kind, "install_vars");
+        assert_eq!(
+            plan.args,
+            Some(json!({ "var_names": ["a.dep.2"], "include_dependencies": false }))
+        );
+    }
+}
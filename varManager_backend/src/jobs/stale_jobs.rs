@@ -1,9 +1,14 @@
 use crate::infra::db::{delete_var_related_conn, upsert_install_status};
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, resolve_var_file_path, OLD_VERSION_DIR, STALE_DIR};
+use crate::jobs::outcome::{ItemErrorCode, ItemOutcome};
+use crate::infra::paths::{
+    config_paths, forget_var_location, library_root_of, library_roots, profile_name,
+    resolve_var_file_path, OLD_VERSION_DIR, STALE_DIR,
+};
 use crate::app::AppState;
 use crate::infra::{system_ops, winfs};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -11,22 +16,24 @@ use std::fs;
 use std::path::Path;
 use sqlx::{Row, SqlitePool};
 
-#[derive(Deserialize)]
-struct StaleVarsArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StaleVarsArgs {
     #[serde(default)]
     include_old_versions: bool,
 }
 
-#[derive(Serialize)]
-struct StaleVarsResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct StaleVarsResult {
     total: usize,
     moved: usize,
     skipped: usize,
     failed: usize,
+    items: Vec<ItemOutcome>,
 }
 
-#[derive(Serialize)]
-struct CombinedStaleResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct CombinedStaleResult {
     stale: StaleVarsResult,
     #[serde(skip_serializing_if = "Option::is_none")]
     old_version: Option<StaleVarsResult>,
@@ -101,6 +108,7 @@ fn stale_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result<Stale
     let vars = handle.block_on(load_vars(pool, false))?;
     let (old_vars, _latest) = find_old_versions(&vars);
 
+    let roots = library_roots(state)?;
     let stale_dir = varspath.join(STALE_DIR);
     fs::create_dir_all(&stale_dir).map_err(|err| err.to_string())?;
 
@@ -109,10 +117,12 @@ fn stale_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result<Stale
     let mut skipped = 0;
     let mut failed = 0;
     let total = old_vars.len();
+    let mut items = Vec::with_capacity(total);
 
     for (idx, oldvar) in old_vars.iter().enumerate() {
         if handle.block_on(has_dependents(pool, oldvar))? {
             skipped += 1;
+            items.push(ItemOutcome::skipped(oldvar, "move_stale", ItemErrorCode::HasDependents));
             continue;
         }
         if let Some(path) = installed_links.get(&oldvar.to_ascii_lowercase()) {
@@ -123,18 +133,32 @@ fn stale_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result<Stale
             Err(err) => {
                 reporter.log(format!("skip {} ({})", oldvar, err));
                 failed += 1;
+                items.push(ItemOutcome::failed(oldvar, "move_stale", ItemErrorCode::NotFound, err));
                 continue;
             }
         };
-        let dest = stale_dir.join(format!("{}.var", oldvar));
+        let dest = library_root_of(&roots, &src)
+            .unwrap_or(&varspath)
+            .join(STALE_DIR)
+            .join(format!("{}.var", oldvar));
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
         match fs::rename(&src, &dest) {
             Ok(_) => {
                 handle.block_on(cleanup_var(pool, &varspath, oldvar))?;
                 moved += 1;
+                items.push(ItemOutcome::succeeded(oldvar, "move_stale"));
             }
             Err(err) => {
                 reporter.log(format!("move failed {} ({})", oldvar, err));
                 failed += 1;
+                items.push(ItemOutcome::failed(
+                    oldvar,
+                    "move_stale",
+                    ItemErrorCode::Io,
+                    err.to_string(),
+                ));
             }
         }
         if total > 0 && (idx % 50 == 0 || idx + 1 == total) {
@@ -150,6 +174,7 @@ fn stale_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result<Stale
         moved,
         skipped,
         failed,
+        items,
     })
 }
 
@@ -160,11 +185,13 @@ fn old_version_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result
     let vampath = vampath.ok_or_else(|| "vampath is required in config.json".to_string())?;
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let vars = handle.block_on(load_vars(pool, true))?;
     let (old_vars, latest_by_base) = find_old_versions(&vars);
 
+    let roots = library_roots(state)?;
     let old_dir = varspath.join(OLD_VERSION_DIR);
     fs::create_dir_all(&old_dir).map_err(|err| err.to_string())?;
 
@@ -173,6 +200,7 @@ fn old_version_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result
     let skipped = 0;
     let mut failed = 0;
     let total = old_vars.len();
+    let mut items = Vec::with_capacity(total);
 
     for (idx, oldvar) in old_vars.iter().enumerate() {
         if let Some(path) = installed_links.get(&oldvar.to_ascii_lowercase()) {
@@ -180,7 +208,13 @@ fn old_version_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result
             if let Some(base) = base_without_version(oldvar) {
                 if let Some(latest_ver) = latest_by_base.get(&base) {
                     let latest_name = format!("{}.{}", base, latest_ver);
-                    let _ = handle.block_on(install_var(pool, &varspath, &vampath, &latest_name));
+                    let _ = handle.block_on(install_var(
+                        pool,
+                        &profile,
+                        &varspath,
+                        &vampath,
+                        &latest_name,
+                    ));
                 }
             }
         }
@@ -190,18 +224,37 @@ fn old_version_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result
             Err(err) => {
                 reporter.log(format!("skip {} ({})", oldvar, err));
                 failed += 1;
+                items.push(ItemOutcome::failed(
+                    oldvar,
+                    "move_old_version",
+                    ItemErrorCode::NotFound,
+                    err,
+                ));
                 continue;
             }
         };
-        let dest = old_dir.join(format!("{}.var", oldvar));
+        let dest = library_root_of(&roots, &src)
+            .unwrap_or(&varspath)
+            .join(OLD_VERSION_DIR)
+            .join(format!("{}.var", oldvar));
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
         match fs::rename(&src, &dest) {
             Ok(_) => {
                 handle.block_on(cleanup_var(pool, &varspath, oldvar))?;
                 moved += 1;
+                items.push(ItemOutcome::succeeded(oldvar, "move_old_version"));
             }
             Err(err) => {
                 reporter.log(format!("move failed {} ({})", oldvar, err));
                 failed += 1;
+                items.push(ItemOutcome::failed(
+                    oldvar,
+                    "move_old_version",
+                    ItemErrorCode::Io,
+                    err.to_string(),
+                ));
             }
         }
 
@@ -218,6 +271,7 @@ fn old_version_vars_blocking(state: &AppState, reporter: &JobReporter) -> Result
         moved,
         skipped,
         failed,
+        items,
     })
 }
 
@@ -321,6 +375,7 @@ async fn cleanup_var(
     var_name: &str,
 ) -> Result<(), String> {
     delete_var_related_conn(pool, var_name).await?;
+    forget_var_location(var_name);
     sqlx::query("DELETE FROM installStatus WHERE varName = ?1")
         .bind(var_name)
         .execute(pool)
@@ -352,6 +407,7 @@ fn base_without_version(var_name: &str) -> Option<String> {
 
 async fn install_var(
     pool: &SqlitePool,
+    profile: &str,
     varspath: &Path,
     vampath: &Path,
     var_name: &str,
@@ -365,7 +421,7 @@ async fn install_var(
     let dest = resolve_var_file_path(varspath, var_name)?;
     winfs::create_symlink_file(&link_path, &dest)?;
     set_link_times(&link_path, &dest)?;
-    upsert_install_status(pool, var_name, true, false).await?;
+    upsert_install_status(pool, profile, var_name, true, false).await?;
     Ok(())
 }
 
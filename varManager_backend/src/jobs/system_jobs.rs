@@ -2,21 +2,23 @@ use crate::jobs::job_channel::JobReporter;
 use crate::app::AppState;
 use crate::infra::system_ops;
 use crate::util;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Deserialize)]
-struct OpenUrlArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct OpenUrlArgs {
     url: String,
 }
 
-#[derive(Serialize)]
-struct RescanResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct RescanResult {
     rescan: bool,
 }
 
-#[derive(Serialize)]
-struct StartResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct StartResult {
     started: bool,
 }
 
@@ -1,17 +1,25 @@
 use crate::infra::db::{
-    delete_var_related, list_scenes_for_var, list_var_scan_info, list_vars, replace_dependencies,
-    replace_hide_fav, replace_scenes, upsert_install_status, upsert_var, var_exists_conn,
-    HideFavRecord, SceneRecord, VarRecord,
+    delete_var_related, list_scenes_for_var, list_search_indexed, list_var_locations,
+    list_var_scan_info, list_vars, replace_dependencies, replace_entries, replace_hide_fav,
+    replace_scenes, replace_search_index, set_var_location, upsert_install_status, upsert_var,
+    var_exists_conn, EntryRecord, HideFavRecord, SceneRecord, VarRecord,
 };
+use crate::infra::downloader::verify_var_package;
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::resolve_var_file_path;
+use crate::jobs::outcome::{ItemErrorCode, ItemOutcome};
+use crate::infra::paths::{
+    config_paths, library_root_of, library_roots, preview_root, profile_name, read_only_library,
+    resolve_var_file_path, set_var_locations, LibraryRootPath, PREVIEW_DIR,
+};
 use crate::domain::var_logic::vars_dependencies;
 use crate::app::AppState;
 use crate::infra::{system_ops, winfs};
 use chrono::{DateTime, Local};
 use regex::Regex;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
@@ -25,7 +33,6 @@ use sqlx::{Sqlite, SqlitePool, Transaction};
 const TIDIED_DIR: &str = "___VarTidied___";
 const REDUNDANT_DIR: &str = "___VarRedundant___";
 const NOT_COMPLY_DIR: &str = "___VarnotComplyRule___";
-const PREVIEW_DIR: &str = "___PreviewPics___";
 const STALE_DIR: &str = "___StaleVars___";
 const OLD_VERSION_DIR: &str = "___OldVersionVars___";
 const DELETED_DIR: &str = "___DeletedVars___";
@@ -82,19 +89,68 @@ struct TidyStats {
     moves: MoveCounter,
 }
 
-#[derive(Serialize)]
-struct MoveSummary {
+impl TidyStats {
+    fn absorb(&mut self, other: TidyStats) {
+        self.scanned += other.scanned;
+        for ((from, to), count) in other.moves.counts {
+            self.moves.add(&from, &to, count);
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct MoveSummary {
     from: String,
     to: String,
     count: u64,
 }
 
-#[derive(Serialize)]
-struct UpdateDbSummary {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct UpdateDbSummary {
     scanned: usize,
     moves: Vec<MoveSummary>,
 }
 
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct ReindexSummary {
+    scanned: usize,
+    indexed: usize,
+    /// Files that could not be indexed; they stay where they are.
+    skipped: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ImportDownloadsArgs {
+    /// Ids from `GET /downloads`; the file each one saved is imported.
+    #[serde(default)]
+    downloads: Vec<i64>,
+    /// `.var` files to import by path.
+    #[serde(default)]
+    files: Vec<String>,
+    /// Install the imported vars with their dependencies. Defaults to
+    /// `download.auto_install`. Files taken from `AddonPackages` are always
+    /// installed again, since VaM was already loading them.
+    #[serde(default)]
+    install: Option<bool>,
+    /// Library vars to install as well, with dependencies, whatever
+    /// `install` says; a retry passes the installs that failed here.
+    #[serde(default)]
+    install_var_names: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct ImportDownloadsResult {
+    /// Vars moved into the library and indexed.
+    imported: Vec<String>,
+    /// Vars whose file was already in the library; the download went to
+    /// `___VarRedundant___`.
+    already_in_library: Vec<String>,
+    installed: Vec<String>,
+    /// `import` items are keyed by file path, `install` items by var name.
+    items: Vec<ItemOutcome>,
+}
+
 pub async fn run_update_db_job(state: AppState, reporter: JobReporter) -> Result<(), String> {
     tokio::task::spawn_blocking(move || {
         update_db_blocking(&state, &reporter)
@@ -106,14 +162,24 @@ pub async fn run_update_db_job(state: AppState, reporter: JobReporter) -> Result
 fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), String> {
     let overall_start = std::time::Instant::now();
     let (varspath, vampath) = config_paths(state)?;
-    let tidied_dir = varspath.join(TIDIED_DIR);
-    let not_comply_dir = varspath.join(NOT_COMPLY_DIR);
+    let profile = profile_name(state)?;
+    let roots = library_roots(state)?;
+    let read_only = read_only_library(state);
+    let previews_dir = preview_root(&varspath, read_only);
     reporter.log(format!("UpdateDB started: varspath={}", varspath.display()));
+    for root in roots.iter().filter(|root| root.path != varspath) {
+        reporter.log(format!(
+            "Library root: {} (priority {})",
+            root.path.display(),
+            root.priority
+        ));
+    }
     reporter.progress(1);
 
+    // AddonPackages files would have to move into the library to be managed.
     let addon_vars = match vampath.as_ref() {
-        Some(vampath) => collect_addonpackages_vars(vampath),
-        None => Vec::new(),
+        Some(vampath) if !read_only => collect_addonpackages_vars(vampath),
+        _ => Vec::new(),
     };
     let addon_root = vampath.as_ref().map(|vampath| vampath.join("AddonPackages"));
 
@@ -131,13 +197,21 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
     vars_for_install = dedup_strings(vars_for_install);
     save_vars_for_install(&vars_for_install)?;
 
-    reporter.log("Phase 1/5: Tidying VAR files...".to_string());
-    let mut tidy_stats = tidy_vars(
-        &varspath,
-        if vampath.is_some() { Some(&addon_vars) } else { None },
-        addon_root.as_deref(),
-        reporter,
-    )?;
+    let mut tidy_stats = TidyStats::default();
+    if read_only {
+        reporter.log("Phase 1/5: Read-only library, indexing VAR files in place".to_string());
+    } else {
+        reporter.log("Phase 1/5: Tidying VAR files...".to_string());
+        for root in &roots {
+            // AddonPackages files are moved into varspath only.
+            let addon = if root.path == varspath && vampath.is_some() {
+                Some(addon_vars.as_slice())
+            } else {
+                None
+            };
+            tidy_stats.absorb(tidy_vars(&root.path, addon, addon_root.as_deref(), reporter)?);
+        }
+    }
     reporter.progress(10);
 
     let pool = state.db_pool.clone();
@@ -145,17 +219,10 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
     let db_path = crate::infra::db::default_path();
     reporter.log(format!("DB ready: {}", db_path.display()));
 
-    let var_files = collect_var_files(
-        &tidied_dir,
-        &[
-            REDUNDANT_DIR,
-            NOT_COMPLY_DIR,
-            STALE_DIR,
-            OLD_VERSION_DIR,
-            DELETED_DIR,
-        ],
-        false,
-    );
+    let var_files = library_var_files(&roots, read_only);
+    if read_only {
+        tidy_stats.scanned = var_files.len();
+    }
     if var_files.is_empty() {
             reporter.log("No VAR files found under tidied directory".to_string());
         let summary = UpdateDbSummary {
@@ -170,42 +237,59 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
 
     reporter.log(format!("Phase 2/5: Processing {} VAR files into database...", var_files.len()));
 
-    let dependency_regex = Regex::new(
-        r#"\x22(([^\r\n\x22\x3A\x2E]{1,60})\x2E([^\r\n\x22\x3A\x2E]{1,80})\x2E(\d+|latest))(\x22?\s*)\x3A"#,
-    )
-    .map_err(|err| err.to_string())?;
+    let dependency_regex = dependency_regex()?;
 
     let pool_for_tx = pool.clone();
-    let varspath_async = varspath.clone();
+    let previews_dir_async = previews_dir.clone();
     let reporter_async = reporter.clone();
     let vampath_async = vampath.clone();
+    let profile_async = profile.clone();
     let dependency_regex = dependency_regex.clone();
     let var_files = var_files.clone();
     let invalid_to_not_comply = handle.block_on(async move {
-        let mut invalid_moves = 0u64;
+        let mut invalid_moves: HashMap<PathBuf, u64> = HashMap::new();
         let mut exist_vars: HashSet<String> = HashSet::new();
         let mut tx = pool_for_tx.begin().await.map_err(|err| err.to_string())?;
         let mut scan_map = HashMap::new();
         for info in list_var_scan_info(&mut tx).await? {
-            scan_map.insert(info.var_name, (info.var_date, info.fsize));
+            let location = (info.var_root, info.var_path);
+            scan_map.insert(info.var_name, (info.var_date, info.fsize, location));
         }
+        // Vars indexed before search existed are reprocessed once to fill it.
+        let search_indexed = list_search_indexed(&mut tx).await?;
         let mut skipped_unchanged = 0u64;
         let total_vars = var_files.len();
         let start_time = std::time::Instant::now();
 
-        for (idx, var_file) in var_files.iter().enumerate() {
+        for (idx, (root, var_file)) in var_files.iter().enumerate() {
             let basename = match var_file.file_stem() {
                 Some(stem) => stem.to_string_lossy().to_string(),
                 None => continue,
             };
-            exist_vars.insert(basename.clone());
+            // Roots are scanned in priority order, so the first copy wins.
+            if !exist_vars.insert(basename.clone()) {
+                reporter_async.log(format!(
+                    "{} is already indexed from another location, skipped",
+                    var_file.display()
+                ));
+                continue;
+            }
 
             let scan_entry = scan_map.get(&basename).cloned();
             let mut skipped = false;
-            if let Some((db_date, db_fsize)) = scan_entry {
-                if comply_var_file(var_file) {
+            if let Some((db_date, db_fsize, db_location)) = scan_entry {
+                if comply_var_file(var_file) && search_indexed.contains(&basename) {
                     if let Some((file_date, file_size_mb)) = read_var_scan_signature(var_file) {
                         if is_var_unchanged(&db_date, db_fsize, &file_date, file_size_mb) {
+                            // Only the location may have changed (first scan
+                            // after upgrading, or a file moved by hand).
+                            let location = var_location(root, var_file)
+                                .filter(|(root, path)| {
+                                    db_location != (Some(root.clone()), Some(path.clone()))
+                                });
+                            if let Some((root, path)) = location {
+                                set_var_location(&mut tx, &basename, &root, &path).await?;
+                            }
                             if let Some(vampath) = vampath_async.as_ref() {
                                 let scenes = list_scenes_for_var(&mut tx, &basename).await?;
                                 let entries = collect_hide_fav_records(
@@ -213,7 +297,8 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
                                     &basename,
                                     &scenes,
                                 );
-                                replace_hide_fav(&mut tx, &basename, &entries).await?;
+                                replace_hide_fav(&mut tx, &profile_async, &basename, &entries)
+                                    .await?;
                             }
                             skipped_unchanged += 1;
                             skipped = true;
@@ -223,49 +308,42 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
             }
 
             if !skipped {
-                let result = process_var_file(&dependency_regex, &varspath_async, var_file);
-                match result {
+                let result = process_var_file(
+                    &dependency_regex,
+                    root,
+                    &previews_dir_async,
+                    var_file,
+                    PreviewMode::Extract,
+                );
+                let invalid = match result {
                     Ok(processed) => {
-                        upsert_var(&mut tx, &processed.var_record).await?;
-                        replace_dependencies(
+                        store_processed_var(
                             &mut tx,
-                            &processed.var_record.var_name,
-                            &processed.dependencies,
+                            &processed,
+                            &profile_async,
+                            vampath_async.as_deref(),
                         )
                         .await?;
-                        replace_scenes(&mut tx, &processed.var_record.var_name, &processed.scenes)
-                            .await?;
-                        if let Some(vampath) = vampath_async.as_ref() {
-                            let entries = collect_hide_fav_records(
-                                vampath,
-                                &processed.var_record.var_name,
-                                &processed.scenes,
-                            );
-                            replace_hide_fav(&mut tx, &processed.var_record.var_name, &entries)
-                                .await?;
-                        }
+                        None
                     }
-                    Err(ProcessError::NotComply(err)) => {
-                        reporter_async.log(err);
-                        move_to_not_comply(&varspath_async, var_file, &reporter_async)?;
-                        invalid_moves += 1;
-                        continue;
-                    }
-                    Err(ProcessError::InvalidPackage(err)) => {
-                        reporter_async.log(err);
-                        move_to_not_comply(&varspath_async, var_file, &reporter_async)?;
-                        invalid_moves += 1;
-                        continue;
+                    Err(ProcessError::NotComply(err)) | Err(ProcessError::InvalidPackage(err)) => {
+                        Some(err)
                     }
-                    Err(ProcessError::Io(err)) => {
-                        if is_zip_error(&err) {
-                            reporter_async.log(err);
-                            move_to_not_comply(&varspath_async, var_file, &reporter_async)?;
-                            invalid_moves += 1;
-                            continue;
-                        }
-                        return Err(err);
+                    Err(ProcessError::Io(err)) if is_zip_error(&err) => Some(err),
+                    Err(ProcessError::Io(err)) => return Err(err),
+                };
+                if let Some(err) = invalid {
+                    reporter_async.log(err);
+                    if read_only {
+                        reporter_async.log(format!(
+                            "Read-only library: left {} in place",
+                            var_file.display()
+                        ));
+                    } else {
+                        move_to_not_comply(root, var_file, &reporter_async)?;
+                        *invalid_moves.entry(root.clone()).or_insert(0) += 1;
                     }
+                    continue;
                 }
             }
 
@@ -295,15 +373,25 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
             ));
         }
 
-        cleanup_missing_vars(&mut tx, &exist_vars, &varspath_async, &reporter_async).await?;
+        cleanup_missing_vars(
+            &mut tx,
+            &exist_vars,
+            &previews_dir_async,
+            PreviewMode::Extract,
+            &reporter_async,
+        )
+        .await?;
         reporter_async.log("Phase 3/5: Committing database changes...".to_string());
         tx.commit().await.map_err(|err| err.to_string())?;
-        Ok::<u64, String>(invalid_moves)
+        set_var_locations(list_var_locations(&pool_for_tx).await?);
+        Ok::<HashMap<PathBuf, u64>, String>(invalid_moves)
     })?;
 
-    tidy_stats
-        .moves
-        .add(&tidied_dir, &not_comply_dir, invalid_to_not_comply);
+    for (root, count) in invalid_to_not_comply {
+        tidy_stats
+            .moves
+            .add(&root.join(TIDIED_DIR), &root.join(NOT_COMPLY_DIR), count);
+    }
 
     reporter.progress(90);
 
@@ -319,7 +407,7 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
             let start_time = std::time::Instant::now();
 
             for (idx, var_name) in pending.iter().enumerate() {
-                match handle.block_on(install_var(&pool, &varspath, vampath, var_name)) {
+                match handle.block_on(install_var(&pool, &profile, &varspath, vampath, var_name)) {
                     Ok(InstallOutcome::Installed) => {
                         reporter.log(format!("{} installed", var_name));
                     }
@@ -354,7 +442,7 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
 
     if let Some(vampath) = vampath.as_ref() {
         reporter.log("Phase 5/5: Refreshing installation status...".to_string());
-        handle.block_on(refresh_install_status(&pool, vampath, reporter))?;
+        handle.block_on(refresh_install_status(&pool, &profile, vampath, reporter))?;
         reporter.progress(97);
         match system_ops::rescan_packages(state) {
             Ok(true) => reporter.log("RescanPackages triggered".to_string()),
@@ -383,6 +471,411 @@ fn update_db_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), St
     Ok(())
 }
 
+pub async fn run_reindex_job(state: AppState, reporter: JobReporter) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || reindex_blocking(&state, &reporter))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+/// Rebuild the index from the library as it is: no tidying, no moves, no
+/// preview extraction and no installs, so varspath may be read-only.
+fn reindex_blocking(state: &AppState, reporter: &JobReporter) -> Result<(), String> {
+    let (varspath, vampath) = config_paths(state)?;
+    let profile = profile_name(state)?;
+    let roots = library_roots(state)?;
+    let read_only = read_only_library(state);
+    let previews_dir = preview_root(&varspath, read_only);
+    for root in &roots {
+        let scan_root = if read_only { root.path.clone() } else { root.path.join(TIDIED_DIR) };
+        reporter.log(format!("Reindex started: {}", scan_root.display()));
+    }
+    reporter.progress(1);
+
+    let var_files = library_var_files(&roots, read_only);
+    let dependency_regex = dependency_regex()?;
+    let pool = state.db_pool.clone();
+    let handle = tokio::runtime::Handle::current();
+    let total = var_files.len();
+
+    let summary = handle.block_on(async {
+        let mut tx = pool.begin().await.map_err(|err| err.to_string())?;
+        let mut exist_vars: HashSet<String> = HashSet::new();
+        let mut skipped = Vec::new();
+        for (idx, (root, var_file)) in var_files.iter().enumerate() {
+            match process_var_file(
+                &dependency_regex,
+                root,
+                &previews_dir,
+                var_file,
+                PreviewMode::ExistingOnly,
+            ) {
+                Ok(processed) => {
+                    if !exist_vars.insert(processed.var_record.var_name.clone()) {
+                        reporter.log(format!("skip {} (duplicate)", var_file.display()));
+                        skipped.push(var_file.display().to_string());
+                        continue;
+                    }
+                    store_processed_var(&mut tx, &processed, &profile, vampath.as_deref())
+                        .await?;
+                }
+                Err(ProcessError::NotComply(err))
+                | Err(ProcessError::InvalidPackage(err))
+                | Err(ProcessError::Io(err)) => {
+                    reporter.log(format!("skip {} ({})", var_file.display(), err));
+                    skipped.push(var_file.display().to_string());
+                }
+            }
+            if idx % 50 == 0 || idx + 1 == total {
+                reporter.progress((1 + (idx + 1) * 89 / total.max(1)) as u8);
+                reporter.log(format!("Reindexing VARs: {}/{}", idx + 1, total));
+            }
+        }
+        cleanup_missing_vars(
+            &mut tx,
+            &exist_vars,
+            &previews_dir,
+            PreviewMode::ExistingOnly,
+            reporter,
+        )
+        .await?;
+        tx.commit().await.map_err(|err| err.to_string())?;
+        set_var_locations(list_var_locations(&pool).await?);
+        Ok::<ReindexSummary, String>(ReindexSummary {
+            scanned: total,
+            indexed: exist_vars.len(),
+            skipped,
+        })
+    })?;
+    reporter.progress(90);
+
+    if let Some(vampath) = vampath.as_ref() {
+        handle.block_on(refresh_install_status(&pool, &profile, vampath, reporter))?;
+    }
+    reporter.log(format!(
+        "Reindex completed: indexed={}, skipped={}",
+        summary.indexed,
+        summary.skipped.len()
+    ));
+    reporter.set_result(serde_json::to_value(summary).map_err(|err| err.to_string())?);
+    reporter.progress(100);
+    Ok(())
+}
+
+pub async fn run_import_downloads_job(
+    state: AppState,
+    reporter: JobReporter,
+    args: Option<Value>,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let args = args.ok_or_else(|| "import_downloads args required".to_string())?;
+        let args: ImportDownloadsArgs =
+            serde_json::from_value(args).map_err(|err| err.to_string())?;
+        import_downloads_blocking(&state, &reporter, args)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+struct ImportContext<'a> {
+    pool: &'a SqlitePool,
+    profile: &'a str,
+    varspath: &'a Path,
+    vampath: Option<&'a Path>,
+    roots: &'a [LibraryRootPath],
+    read_only: bool,
+    previews_dir: &'a Path,
+    dependency_regex: &'a Regex,
+}
+
+enum ImportedVar {
+    Indexed(String),
+    AlreadyInLibrary(String),
+}
+
+/// Move finished downloads into `___VarTidied___/<creator>/` and index just
+/// those packages, so they can be installed without a full `update_db`.
+fn import_downloads_blocking(
+    state: &AppState,
+    reporter: &JobReporter,
+    args: ImportDownloadsArgs,
+) -> Result<(), String> {
+    let (varspath, vampath) = config_paths(state)?;
+    let profile = profile_name(state)?;
+    let roots = library_roots(state)?;
+    let read_only = read_only_library(state);
+    let previews_dir = preview_root(&varspath, read_only);
+    let install = match args.install {
+        Some(install) => install,
+        None => state
+            .config
+            .read()
+            .map_err(|_| "config lock poisoned".to_string())?
+            .download
+            .auto_install,
+    };
+    let pool = state.db_pool.clone();
+    let handle = tokio::runtime::Handle::current();
+
+    let mut items = Vec::new();
+    let mut files: Vec<PathBuf> = args
+        .files
+        .iter()
+        .map(|file| file.trim())
+        .filter(|file| !file.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    if !args.downloads.is_empty() {
+        let saved = handle.block_on(state.download_manager.completed_paths(&args.downloads))?;
+        for id in &args.downloads {
+            match saved.get(id) {
+                Some(path) => files.push(path.clone()),
+                None => items.push(ItemOutcome::failed(
+                    format!("download {}", id),
+                    "import",
+                    ItemErrorCode::NotFound,
+                    "download is not completed",
+                )),
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(path_dedupe_key(file)));
+    reporter.log(format!("ImportDownloads start: {} files", files.len()));
+    reporter.progress(1);
+
+    let dependency_regex = dependency_regex()?;
+    let ctx = ImportContext {
+        pool: &pool,
+        profile: &profile,
+        varspath: &varspath,
+        vampath: vampath.as_deref(),
+        roots: &roots,
+        read_only,
+        previews_dir: &previews_dir,
+        dependency_regex: &dependency_regex,
+    };
+    // Files saved straight into AddonPackages were loaded by VaM; like
+    // update_db, they get an install link once moved so they stay loaded.
+    let addon_root = vampath.as_ref().map(|vampath| vampath.join("AddonPackages"));
+    let total = files.len();
+    let mut imported = Vec::new();
+    let mut already_in_library = Vec::new();
+    let mut reinstall = Vec::new();
+    for (idx, file) in files.iter().enumerate() {
+        let item = file.display().to_string();
+        let from_addon = !read_only
+            && !fs_util::is_symlink(file)
+            && addon_root.as_ref().is_some_and(|root| file.starts_with(root));
+        match import_var_file(&ctx, &handle, file) {
+            Ok(ImportedVar::Indexed(var_name)) => {
+                reporter.log(format!("{} imported", var_name));
+                items.push(ItemOutcome::succeeded(&item, "import"));
+                if from_addon {
+                    reinstall.push(var_name.clone());
+                }
+                imported.push(var_name);
+            }
+            Ok(ImportedVar::AlreadyInLibrary(var_name)) => {
+                reporter.log(format!(
+                    "{} is already in the library, download moved to {}",
+                    var_name, REDUNDANT_DIR
+                ));
+                items.push(ItemOutcome::skipped(&item, "import", ItemErrorCode::AlreadyDone));
+                if from_addon {
+                    reinstall.push(var_name.clone());
+                }
+                already_in_library.push(var_name);
+            }
+            Err(failure) => {
+                // A file that was already moved is retried from where it is now.
+                let item = failure
+                    .moved_to
+                    .map(|path| path.display().to_string())
+                    .unwrap_or(item);
+                reporter.log(format!("import failed {} ({})", item, failure.message));
+                items.push(ItemOutcome::failed(&item, "import", failure.code, failure.message));
+            }
+        }
+        reporter.progress((1 + (idx + 1) * 79 / total.max(1)) as u8);
+    }
+    if !imported.is_empty() {
+        set_var_locations(handle.block_on(list_var_locations(&pool))?);
+    }
+
+    let mut installed = Vec::new();
+    let mut wanted: Vec<String> = if install {
+        imported.iter().chain(&already_in_library).cloned().collect()
+    } else {
+        reinstall
+    };
+    for var_name in &args.install_var_names {
+        let var_name = var_name.trim();
+        if !var_name.is_empty() && !wanted.iter().any(|wanted| wanted == var_name) {
+            wanted.push(var_name.to_string());
+        }
+    }
+    if !wanted.is_empty() {
+        match vampath.as_ref() {
+            Some(vampath) => {
+                let pending = handle.block_on(vars_dependencies(&pool, wanted))?;
+                reporter.log(format!("Installing {} vars with dependencies", pending.len()));
+                for var_name in pending {
+                    match handle.block_on(install_var(
+                        &pool, &profile, &varspath, vampath, &var_name,
+                    )) {
+                        Ok(InstallOutcome::Installed) => {
+                            items.push(ItemOutcome::succeeded(&var_name, "install"));
+                            installed.push(var_name);
+                        }
+                        Ok(InstallOutcome::AlreadyInstalled) => {}
+                        Err(err) => {
+                            reporter.log(format!("install failed {} ({})", var_name, err));
+                            items.push(ItemOutcome::failed(
+                                &var_name,
+                                "install",
+                                ItemErrorCode::Io,
+                                err,
+                            ));
+                        }
+                    }
+                }
+            }
+            None => reporter.log("vampath not set; skip install".to_string()),
+        }
+    }
+    reporter.progress(95);
+    if !installed.is_empty() {
+        match system_ops::rescan_packages(state) {
+            Ok(true) => reporter.log("RescanPackages triggered".to_string()),
+            Ok(false) => reporter.log("RescanPackages skipped (VaM not running)".to_string()),
+            Err(err) => reporter.log(format!("RescanPackages failed ({})", err)),
+        }
+    }
+
+    reporter.log(format!(
+        "ImportDownloads completed: imported={}, already_in_library={}, installed={}",
+        imported.len(),
+        already_in_library.len(),
+        installed.len()
+    ));
+    reporter.set_result(
+        serde_json::to_value(ImportDownloadsResult {
+            imported,
+            already_in_library,
+            installed,
+            items,
+        })
+        .map_err(|err| err.to_string())?,
+    );
+    reporter.progress(100);
+    Ok(())
+}
+
+struct ImportFailure {
+    code: ItemErrorCode,
+    message: String,
+    /// Where the file was moved before the failure, if it was.
+    moved_to: Option<PathBuf>,
+}
+
+impl From<(ItemErrorCode, String)> for ImportFailure {
+    fn from((code, message): (ItemErrorCode, String)) -> Self {
+        Self {
+            code,
+            message,
+            moved_to: None,
+        }
+    }
+}
+
+/// Another copy of `var_name` in any library root, other than `file`.
+fn library_copy(ctx: &ImportContext<'_>, var_name: &str, file: &Path) -> Option<PathBuf> {
+    let key = path_dedupe_key(file);
+    let creator = var_name.split('.').next().unwrap_or_default();
+    let filename = format!("{}.var", var_name);
+    ctx.roots
+        .iter()
+        .map(|root| root.path.join(TIDIED_DIR).join(creator).join(&filename))
+        .chain(resolve_var_file_path(ctx.varspath, var_name).ok())
+        .find(|path| path.is_file() && path_dedupe_key(path) != key)
+}
+
+fn import_var_file(
+    ctx: &ImportContext<'_>,
+    handle: &tokio::runtime::Handle,
+    file: &Path,
+) -> Result<ImportedVar, ImportFailure> {
+    if !file.is_file() {
+        return Err((ItemErrorCode::NotFound, "file not found".to_string()).into());
+    }
+    let var_name = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if !comply_var_name(&var_name) {
+        return Err((
+            ItemErrorCode::InvalidPackage,
+            format!("invalid var name: {}", var_name),
+        )
+            .into());
+    }
+    verify_var_package(file).map_err(|err| (ItemErrorCode::InvalidPackage, err))?;
+
+    let (root, dest) = if ctx.read_only {
+        // Nothing is moved into a read-only library; files already inside
+        // one of its roots are indexed where they are.
+        let root = library_root_of(ctx.roots, file).ok_or_else(|| {
+            (
+                ItemErrorCode::ReadOnly,
+                "read-only library: file is outside the library roots".to_string(),
+            )
+        })?;
+        (root.to_path_buf(), file.to_path_buf())
+    } else {
+        let filename = format!("{}.var", var_name);
+        let creator = var_name.split('.').next().unwrap_or_default();
+        let dest = ctx.varspath.join(TIDIED_DIR).join(creator).join(&filename);
+        if library_copy(ctx, &var_name, file).is_some() {
+            let redundant = unique_path(&ctx.varspath.join(REDUNDANT_DIR), &filename);
+            move_file(file, &redundant).map_err(|err| (ItemErrorCode::Io, err))?;
+            return Ok(ImportedVar::AlreadyInLibrary(var_name));
+        }
+        if !dest.exists() {
+            move_file(file, &dest).map_err(|err| (ItemErrorCode::Io, err))?;
+        }
+        (ctx.varspath.to_path_buf(), dest)
+    };
+    let moved_to = (path_dedupe_key(&dest) != path_dedupe_key(file)).then(|| dest.clone());
+    let failed = |(code, message): (ItemErrorCode, String)| ImportFailure {
+        code,
+        message,
+        moved_to: moved_to.clone(),
+    };
+
+    let processed = process_var_file(
+        ctx.dependency_regex,
+        &root,
+        ctx.previews_dir,
+        &dest,
+        PreviewMode::Extract,
+    )
+    .map_err(|err| match err {
+        ProcessError::NotComply(err) | ProcessError::InvalidPackage(err) => {
+            (ItemErrorCode::InvalidPackage, err)
+        }
+        ProcessError::Io(err) => (ItemErrorCode::Io, err),
+    })
+    .map_err(failed)?;
+    handle
+        .block_on(async {
+            let mut tx = ctx.pool.begin().await.map_err(|err| err.to_string())?;
+            store_processed_var(&mut tx, &processed, ctx.profile, ctx.vampath).await?;
+            tx.commit().await.map_err(|err| err.to_string())
+        })
+        .map_err(|err| failed((ItemErrorCode::Db, err)))?;
+    Ok(ImportedVar::Indexed(var_name))
+}
+
 fn log_update_db_summary(stats: &TidyStats, reporter: &JobReporter) {
     let total_moved = stats.moves.total();
     reporter.log(format!(
@@ -415,27 +908,6 @@ fn move_prefix_label(dest: &str) -> &'static str {
     "Succeed"
 }
 
-fn config_paths(state: &AppState) -> Result<(PathBuf, Option<PathBuf>), String> {
-    let cfg = state
-        .config
-        .read()
-        .map_err(|_| "config lock poisoned".to_string())?;
-    let varspath = cfg.varspath.as_ref().and_then(|s| normalize_path(s));
-    let vampath = cfg.vampath.as_ref().and_then(|s| normalize_path(s));
-
-    let varspath = varspath.ok_or_else(|| "varspath is required in config.json".to_string())?;
-    Ok((varspath, vampath))
-}
-
-fn normalize_path(value: &str) -> Option<PathBuf> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        None
-    } else {
-        Some(PathBuf::from(trimmed))
-    }
-}
-
 fn read_hide_fav_for_scene(vampath: &Path, var_name: &str, scene_path: &str) -> (bool, bool) {
     let scenepath = Path::new(scene_path)
         .parent()
@@ -586,6 +1058,40 @@ fn tidy_vars(
     Ok(stats)
 }
 
+/// Var files to index with the root holding each, in root priority order:
+/// each root's `___VarTidied___`, or everything under the root outside the
+/// bookkeeping folders when the library is read-only.
+fn library_var_files(roots: &[LibraryRootPath], read_only: bool) -> Vec<(PathBuf, PathBuf)> {
+    let exclude = [
+        REDUNDANT_DIR,
+        NOT_COMPLY_DIR,
+        STALE_DIR,
+        OLD_VERSION_DIR,
+        DELETED_DIR,
+        PREVIEW_DIR,
+    ];
+    let mut all = Vec::new();
+    for root in roots {
+        let mut files = if read_only {
+            collect_var_files(&root.path, &exclude, false)
+        } else {
+            collect_var_files(&root.path.join(TIDIED_DIR), &exclude, false)
+        };
+        files.sort();
+        all.extend(files.into_iter().map(|file| (root.path.clone(), file)));
+    }
+    all
+}
+
+/// `(root, path relative to root)` as stored in `vars`.
+fn var_location(root: &Path, var_file: &Path) -> Option<(String, String)> {
+    let rel = var_file.strip_prefix(root).ok()?;
+    Some((
+        root.to_string_lossy().to_string(),
+        rel.to_string_lossy().to_string(),
+    ))
+}
+
 fn path_dedupe_key(path: &Path) -> String {
     let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
     canonical.to_string_lossy().to_ascii_lowercase()
@@ -749,10 +1255,12 @@ fn collect_addonpackages_vars(vampath: &Path) -> Vec<PathBuf> {
 
 async fn refresh_install_status(
     pool: &SqlitePool,
+    profile: &str,
     vampath: &Path,
     reporter: &JobReporter,
 ) -> Result<(), String> {
-    sqlx::query("DELETE FROM installStatus")
+    sqlx::query("DELETE FROM installStatus WHERE profile = ?1")
+        .bind(profile)
         .execute(pool)
         .await
         .map_err(|err| err.to_string())?;
@@ -768,7 +1276,7 @@ async fn refresh_install_status(
             continue;
         }
         let disabled = link_path.with_extension("var.disabled").exists();
-        upsert_install_status(pool, &var_name, true, disabled).await?;
+        upsert_install_status(pool, profile, &var_name, true, disabled).await?;
         installed += 1;
     }
     reporter.log(format!("UpdateVarsInstalled completed: {}", installed));
@@ -782,6 +1290,7 @@ enum InstallOutcome {
 
 async fn install_var(
     pool: &SqlitePool,
+    profile: &str,
     varspath: &Path,
     vampath: &Path,
     var_name: &str,
@@ -807,7 +1316,7 @@ async fn install_var(
     let dest = resolve_var_file_path(varspath, var_name)?;
     winfs::create_symlink_file(&link_path, &dest)?;
     set_link_times(&link_path, &dest)?;
-    upsert_install_status(pool, var_name, true, false).await?;
+    upsert_install_status(pool, profile, var_name, true, false).await?;
     tracing::debug!(
         var_name = %var_name,
         link_path = %link_path.display(),
@@ -828,6 +1337,10 @@ struct ProcessedVar {
     var_record: VarRecord,
     scenes: Vec<SceneRecord>,
     dependencies: Vec<String>,
+    /// Space-separated tags from meta.json, for the search index.
+    tags: String,
+    /// Every file entry in the archive.
+    entries: Vec<EntryRecord>,
 }
 
 enum ProcessError {
@@ -836,15 +1349,45 @@ enum ProcessError {
     Io(String),
 }
 
+async fn store_processed_var(
+    tx: &mut Transaction<'_, Sqlite>,
+    processed: &ProcessedVar,
+    profile: &str,
+    vampath: Option<&Path>,
+) -> Result<(), String> {
+    let var_name = &processed.var_record.var_name;
+    upsert_var(tx, &processed.var_record).await?;
+    replace_dependencies(tx, var_name, &processed.dependencies).await?;
+    replace_scenes(tx, var_name, &processed.scenes).await?;
+    replace_search_index(tx, &processed.var_record, &processed.tags).await?;
+    replace_entries(tx, var_name, &processed.entries).await?;
+    if let Some(vampath) = vampath {
+        let entries = collect_hide_fav_records(vampath, var_name, &processed.scenes);
+        replace_hide_fav(tx, profile, var_name, &entries).await?;
+    }
+    Ok(())
+}
+
 fn is_zip_error(err: &str) -> bool {
     let msg = err.to_ascii_lowercase();
     msg.contains("zip") || msg.contains("eocd") || msg.contains("archive")
 }
 
+/// How `process_var_file` treats preview images.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PreviewMode {
+    /// Extract missing previews into the preview root.
+    Extract,
+    /// Only reference previews already on disk; never write any.
+    ExistingOnly,
+}
+
 fn process_var_file(
     dependency_regex: &Regex,
-    varspath: &Path,
+    library_root: &Path,
+    preview_root: &Path,
     var_file: &Path,
+    previews: PreviewMode,
 ) -> Result<ProcessedVar, ProcessError> {
     if !comply_var_file(var_file) {
         return Err(ProcessError::NotComply(format!(
@@ -886,9 +1429,11 @@ fn process_var_file(
     let meta_json = read_meta_json(&mut zip).map_err(ProcessError::InvalidPackage)?;
     let meta_date = meta_json.meta_date;
     let dependencies = extract_dependencies(dependency_regex, &meta_json.contents);
+    let (description, tags) = meta_search_fields(&meta_json.contents);
 
     let mut counts = Counts::default();
     let mut scenes = Vec::new();
+    let mut entries = Vec::new();
 
     for i in 0..zip.len() {
         let entry_name = {
@@ -898,21 +1443,33 @@ fn process_var_file(
             if entry.is_dir() {
                 continue;
             }
+            entries.push(EntryRecord {
+                path: entry.name().to_string(),
+                size: entry.size() as i64,
+                compressed_size: entry.compressed_size() as i64,
+                crc: entry.crc32() as i64,
+                content_type: String::new(),
+            });
             entry.name().to_string()
         };
 
         let name_lc = entry_name.to_lowercase();
-        if let Some(entry_info) = classify_entry(&name_lc) {
+        let classified = classify_entry(&name_lc);
+        if let Some(last) = entries.last_mut() {
+            last.content_type = entry_content_type(&name_lc, classified).to_string();
+        }
+        if let Some(entry_info) = classified {
             let (typename, is_preset) = entry_info;
             let count = counts.bump(typename);
 
             let preview_pic = extract_preview(
                 &mut zip,
                 &entry_name,
-                varspath,
+                preview_root,
                 basename,
                 typename,
                 count,
+                previews,
             )
             .ok();
 
@@ -939,6 +1496,7 @@ fn process_var_file(
         counts.plugin_cs
     };
 
+    let location = var_location(library_root, var_file);
     let var_record = VarRecord {
         var_name: basename.to_string(),
         creator_name: Some(creator_name),
@@ -946,7 +1504,7 @@ fn process_var_file(
         meta_date,
         var_date,
         version: Some(version),
-        description: None,
+        description,
         morph: Some(counts.morphs as i64),
         cloth: Some(counts.clothing as i64),
         hair: Some(counts.hairstyle as i64),
@@ -962,19 +1520,52 @@ fn process_var_file(
         appearance: Some(0),
         dependency_cnt: Some(dependencies.len() as i64),
         fsize: Some(fsize_mb),
+        var_root: location.as_ref().map(|(root, _)| root.clone()),
+        var_path: location.map(|(_, path)| path),
     };
 
     Ok(ProcessedVar {
         var_record,
         scenes,
         dependencies,
+        tags,
+        entries,
     })
 }
 
+/// Description and tags from meta.json. Creators write either a tag array or
+/// one comma-separated string; files that do not parse yield neither.
+fn meta_search_fields(contents: &str) -> (Option<String>, String) {
+    let Ok(meta) = serde_json::from_str::<serde_json::Value>(contents) else {
+        return (None, String::new());
+    };
+    let description = meta
+        .get("description")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let tags: Vec<String> = match meta.get("tags") {
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .collect(),
+        Some(serde_json::Value::String(s)) => s.split(',').map(|t| t.trim().to_string()).collect(),
+        _ => Vec::new(),
+    };
+    let tags = tags
+        .into_iter()
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (description, tags)
+}
+
 async fn cleanup_missing_vars(
     tx: &mut Transaction<'_, Sqlite>,
     exist_vars: &HashSet<String>,
-    varspath: &Path,
+    preview_root: &Path,
+    previews: PreviewMode,
     reporter: &JobReporter,
 ) -> Result<(), String> {
     let db_vars = list_vars(tx).await?;
@@ -982,7 +1573,11 @@ async fn cleanup_missing_vars(
     for var_name in db_vars {
         if !exist_vars.contains(&var_name) {
             delete_var_related(tx, &var_name).await?;
-            if let Err(err) = delete_preview_pics(varspath, &var_name) {
+            if previews == PreviewMode::ExistingOnly {
+                removed += 1;
+                continue;
+            }
+            if let Err(err) = delete_preview_pics(preview_root, &var_name) {
                 reporter.log(format!(
                     "delete preview pics failed {} ({})",
                     var_name, err
@@ -997,12 +1592,12 @@ async fn cleanup_missing_vars(
     Ok(())
 }
 
-fn delete_preview_pics(varspath: &Path, var_name: &str) -> Result<(), String> {
+fn delete_preview_pics(preview_root: &Path, var_name: &str) -> Result<(), String> {
     let types = [
         "scenes", "looks", "hairstyle", "clothing", "assets", "morphs", "skin", "pose",
     ];
     for typename in types {
-        let dir = varspath.join(PREVIEW_DIR).join(typename).join(var_name);
+        let dir = preview_root.join(typename).join(var_name);
         if dir.exists() {
             fs::remove_dir_all(&dir).map_err(|err| err.to_string())?;
         }
@@ -1045,6 +1640,13 @@ fn zip_datetime_to_string(dt: Option<zip::DateTime>) -> Option<String> {
     ))
 }
 
+fn dependency_regex() -> Result<Regex, String> {
+    Regex::new(
+        r#"\x22(([^\r\n\x22\x3A\x2E]{1,60})\x2E([^\r\n\x22\x3A\x2E]{1,80})\x2E(\d+|latest))(\x22?\s*)\x3A"#,
+    )
+    .map_err(|err| err.to_string())
+}
+
 fn extract_dependencies(regex: &Regex, json: &str) -> Vec<String> {
     let mut deps = Vec::new();
     for cap in regex.captures_iter(json) {
@@ -1118,6 +1720,32 @@ fn classify_entry(name_lc: &str) -> Option<(&'static str, bool)> {
     None
 }
 
+/// Content type stored with each archive entry. Entries `classify_entry`
+/// recognizes keep its type name; the rest are typed by location and
+/// extension.
+fn entry_content_type(name_lc: &str, classified: Option<(&'static str, bool)>) -> &'static str {
+    if let Some((typename, _)) = classified {
+        return typename;
+    }
+    if name_lc == "meta.json" {
+        return "meta";
+    }
+    if is_plugin_cs(name_lc) || is_plugin_cslist(name_lc) {
+        return "plugin";
+    }
+    let ext = name_lc.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    match ext {
+        "cs" | "cslist" | "dll" => "script",
+        "jpg" | "jpeg" | "png" | "tif" | "tiff" | "tga" | "bmp" => "texture",
+        "wav" | "mp3" | "ogg" | "aif" | "aiff" => "audio",
+        "assetbundle" | "scene" => "assetbundle",
+        "vmi" | "vmb" | "dsf" => "morph_data",
+        "vam" | "vaj" | "vab" | "vap" | "vac" => "preset",
+        "json" => "json",
+        _ => "other",
+    }
+}
+
 fn is_scene_record_type(typename: &str) -> bool {
     matches!(
         typename,
@@ -1138,10 +1766,11 @@ fn is_plugin_cslist(name_lc: &str) -> bool {
 fn extract_preview(
     zip: &mut ZipArchive<BufReader<File>>,
     entry_name: &str,
-    varspath: &Path,
+    preview_root: &Path,
     var_name: &str,
     typename: &str,
     count: usize,
+    previews: PreviewMode,
 ) -> Result<String, String> {
     let dot = entry_name.rfind('.').ok_or_else(|| "no extension".to_string())?;
     let jpg_entry = format!("{}{}", &entry_name[..dot], ".jpg");
@@ -1155,12 +1784,15 @@ fn extract_preview(
         .to_lowercase();
 
     let jpgname = format!("{}{:03}_{}.jpg", typename, count, namejpg);
-    let type_dir = varspath.join(PREVIEW_DIR).join(typename).join(var_name);
-    fs::create_dir_all(&type_dir).map_err(|err| err.to_string())?;
+    let type_dir = preview_root.join(typename).join(var_name);
     let jpg_path = type_dir.join(&jpgname);
     if jpg_path.exists() {
         return Ok(jpgname);
     }
+    if previews == PreviewMode::ExistingOnly {
+        return Err("preview not extracted".to_string());
+    }
+    fs::create_dir_all(&type_dir).map_err(|err| err.to_string())?;
     let mut out = File::create(&jpg_path).map_err(|err| err.to_string())?;
     std::io::copy(&mut jpg, &mut out).map_err(|err| err.to_string())?;
     Ok(jpgname)
@@ -1244,3 +1876,219 @@ impl Counts {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{data_dir, Config, ImageCacheConfig};
+    use crate::infra::download_manager::DownloadManager;
+    use crate::jobs::job_channel::{create_job_channel, create_job_map};
+    use crate::jobs::job_locks::JobLocks;
+    use crate::jobs::job_log_store::JobLogStore;
+    use crate::services::image_cache::ImageCacheService;
+    use std::io::Write;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::{Arc, RwLock};
+
+    fn make_temp_dir(prefix: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}_{}", prefix, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A var with one scene and its preview image.
+    fn write_var(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("meta.json", options).unwrap();
+        zip.write_all(br#"{ "description": "a chair", "dependencies": {} }"#).unwrap();
+        zip.start_file("Saves/scene/Chair.json", options).unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.start_file("Saves/scene/Chair.jpg", options).unwrap();
+        zip.write_all(b"jpg").unwrap();
+        zip.finish().unwrap();
+    }
+
+    /// Every file under `root` with its size, relative and sorted.
+    fn tree(root: &Path) -> Vec<(PathBuf, u64)> {
+        let mut files: Vec<(PathBuf, u64)> = WalkDir::new(root)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                let rel = entry.path().strip_prefix(root).unwrap().to_path_buf();
+                (rel, entry.metadata().unwrap().len())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    async fn test_state(dir: &Path, varspath: &Path, read_only: bool) -> AppState {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(dir.join("varManager.db"))
+            .create_if_missing(true);
+        let db_pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        crate::infra::migrations::migrate(&db_pool, &dir.join("db_backups"))
+            .await
+            .unwrap();
+        let config = Config {
+            varspath: Some(varspath.to_string_lossy().to_string()),
+            read_only_library: read_only,
+            ..Config::default()
+        };
+        let config = Arc::new(RwLock::new(config));
+        let image_cache = ImageCacheService::new(ImageCacheConfig::default(), db_pool.clone())
+            .await
+            .unwrap();
+        let (completed, _) = tokio::sync::mpsc::unbounded_channel();
+        let download_manager =
+            DownloadManager::new(db_pool.clone(), Arc::clone(&config), completed);
+        let (job_tx, _) = create_job_channel();
+        AppState {
+            config,
+            shutdown_tx: Arc::new(tokio::sync::Mutex::new(None)),
+            jobs: create_job_map(),
+            job_counter: Arc::new(AtomicU64::new(1)),
+            job_semaphore: Arc::new(RwLock::new(Arc::new(tokio::sync::Semaphore::new(1)))),
+            job_tx,
+            job_locks: Arc::new(JobLocks::new()),
+            job_logs: Arc::new(JobLogStore::open(dir.join("job_logs"))),
+            db_pool,
+            image_cache: Arc::new(image_cache),
+            download_manager: Arc::new(download_manager),
+            profile: None,
+        }
+    }
+
+    async fn scene_previews(pool: &SqlitePool, var_name: &str) -> Vec<Option<String>> {
+        sqlx::query_scalar("SELECT previewPic FROM scenes WHERE varName = ?1")
+            .bind(var_name)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reindex_moves_nothing_and_only_links_existing_previews() {
+        let dir = make_temp_dir("vm_reindex");
+        let varspath = dir.join("library");
+        let var_name = format!("Acme.Reindex{}.1", std::process::id());
+        let tidied = varspath.join(TIDIED_DIR).join("Acme").join(format!("{}.var", var_name));
+        write_var(&tidied);
+        let preview = varspath.join(PREVIEW_DIR).join("scenes").join(&var_name);
+        fs::create_dir_all(&preview).unwrap();
+        fs::write(preview.join("scenes001_chair.jpg"), b"jpg").unwrap();
+        // update_db would tidy this one away; reindex leaves it.
+        write_var(&varspath.join(format!("Acme.Loose{}.1.var", std::process::id())));
+        let before = tree(&varspath);
+
+        let state = test_state(&dir, &varspath, false).await;
+        let (tx, _rx) = create_job_channel();
+        let reporter = JobReporter::new(1, tx);
+        let job_state = state.clone();
+        tokio::task::spawn_blocking(move || reindex_blocking(&job_state, &reporter))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tree(&varspath), before);
+        let indexed: Vec<String> = sqlx::query_scalar("SELECT varName FROM vars")
+            .fetch_all(&state.db_pool)
+            .await
+            .unwrap();
+        assert_eq!(indexed, vec![var_name.clone()]);
+        assert_eq!(
+            scene_previews(&state.db_pool, &var_name).await,
+            vec![Some("scenes001_chair.jpg".to_string())]
+        );
+
+        state.db_pool.close().await;
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn read_only_library_is_indexed_in_place() {
+        let dir = make_temp_dir("vm_read_only");
+        let varspath = dir.join("library");
+        let pid = std::process::id();
+        let loose = format!("Acme.ReadOnlyLoose{}.1", pid);
+        let nested = format!("Acme.ReadOnlyNested{}.1", pid);
+        let imported = format!("Acme.ReadOnlyImport{}.1", pid);
+        write_var(&varspath.join(format!("{}.var", loose)));
+        write_var(&varspath.join("shared").join(format!("{}.var", nested)));
+        let before = tree(&varspath);
+
+        let state = test_state(&dir, &varspath, true).await;
+        let previews_dir = data_dir().join(PREVIEW_DIR);
+        let preview_of = |var_name: &str| {
+            data_dir().join(PREVIEW_DIR).join("scenes").join(var_name).join("scenes001_chair.jpg")
+        };
+        let (tx, _rx) = create_job_channel();
+        let reporter = JobReporter::new(1, tx);
+        let job_state = state.clone();
+        tokio::task::spawn_blocking(move || update_db_blocking(&job_state, &reporter))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tree(&varspath), before);
+        for var_name in [&loose, &nested] {
+            assert!(preview_of(var_name).is_file(), "{}", var_name);
+            assert_eq!(
+                scene_previews(&state.db_pool, var_name).await,
+                vec![Some("scenes001_chair.jpg".to_string())]
+            );
+        }
+
+        // Imports are indexed where they are; files outside the library are refused.
+        let inside = varspath.join("shared").join(format!("{}.var", imported));
+        write_var(&inside);
+        let outside = dir.join("downloads").join(format!("Acme.Outside{}.1.var", pid));
+        write_var(&outside);
+        let before = tree(&varspath);
+        let job_state = state.clone();
+        let outside_file = outside.clone();
+        let (inside_result, outside_result) = tokio::task::spawn_blocking(move || {
+            let roots = library_roots(&job_state).unwrap();
+            let dependency_regex = dependency_regex().unwrap();
+            let ctx = ImportContext {
+                pool: &job_state.db_pool,
+                profile: "default",
+                varspath: &varspath,
+                vampath: None,
+                roots: &roots,
+                read_only: true,
+                previews_dir: &previews_dir,
+                dependency_regex: &dependency_regex,
+            };
+            let handle = tokio::runtime::Handle::current();
+            (
+                import_var_file(&ctx, &handle, &inside),
+                import_var_file(&ctx, &handle, &outside_file),
+            )
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(inside_result, Ok(ImportedVar::Indexed(ref name)) if *name == imported));
+        assert!(preview_of(&imported).is_file());
+        let failure = outside_result.err().unwrap();
+        assert_eq!(failure.code, ItemErrorCode::ReadOnly);
+        assert!(failure.moved_to.is_none());
+        assert!(outside.is_file());
+        assert_eq!(tree(&dir.join("library")), before);
+
+        for var_name in [&loose, &nested, &imported] {
+            let _ = fs::remove_dir_all(preview_of(var_name).parent().unwrap());
+        }
+        state.db_pool.close().await;
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,10 +1,15 @@
 use crate::infra::db::{self, delete_var_related_conn, upsert_install_status};
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, resolve_var_file_path, DELETED_DIR, INSTALL_LINK_DIR};
+use crate::jobs::outcome::{install_failure, ItemErrorCode, ItemOutcome};
+use crate::infra::paths::{
+    config_paths, forget_var_location, library_root_of, library_roots, profile_name,
+    resolve_var_file_path, DELETED_DIR, INSTALL_LINK_DIR,
+};
 use crate::domain::var_logic::{implicated_vars, vars_dependencies};
 use crate::app::AppState;
 use crate::infra::winfs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
@@ -12,8 +17,9 @@ use std::path::Path;
 use std::time::Instant;
 use sqlx::SqlitePool;
 
-#[derive(Deserialize)]
-struct InstallVarsArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct InstallVarsArgs {
     var_names: Vec<String>,
     #[serde(default = "default_true")]
     include_dependencies: bool,
@@ -23,22 +29,25 @@ struct InstallVarsArgs {
     disabled: bool,
 }
 
-#[derive(Deserialize)]
-struct UninstallVarsArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct UninstallVarsArgs {
     var_names: Vec<String>,
     #[serde(default = "default_true")]
     include_implicated: bool,
 }
 
-#[derive(Deserialize)]
-struct PreviewUninstallArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PreviewUninstallArgs {
     var_names: Vec<String>,
     #[serde(default = "default_true")]
     include_implicated: bool,
 }
 
-#[derive(Deserialize)]
-struct DeleteVarsArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DeleteVarsArgs {
     var_names: Vec<String>,
     #[serde(default = "default_true")]
     include_implicated: bool,
@@ -48,33 +57,36 @@ fn default_true() -> bool {
     true
 }
 
-#[derive(Serialize)]
-struct InstallVarsResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct InstallVarsResult {
     total: usize,
     installed: Vec<String>,
     already_installed: Vec<String>,
     failed: Vec<String>,
+    items: Vec<ItemOutcome>,
 }
 
-#[derive(Serialize)]
-struct UninstallVarsResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct UninstallVarsResult {
     total: usize,
     removed: Vec<String>,
     skipped: Vec<String>,
+    items: Vec<ItemOutcome>,
 }
 
-#[derive(Serialize)]
-struct PreviewUninstallResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct PreviewUninstallResult {
     var_list: Vec<String>,
     requested: Vec<String>,
     implicated: Vec<String>,
 }
 
-#[derive(Serialize)]
-struct DeleteVarsResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct DeleteVarsResult {
     total: usize,
     deleted: Vec<String>,
     failed: Vec<String>,
+    items: Vec<ItemOutcome>,
 }
 
 pub async fn run_install_vars_job(
@@ -126,6 +138,7 @@ fn install_vars_blocking(state: &AppState, reporter: &JobReporter, args: Install
     reporter.progress(1);
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let var_list = if args.include_dependencies {
@@ -138,22 +151,31 @@ fn install_vars_blocking(state: &AppState, reporter: &JobReporter, args: Install
     let mut installed = Vec::new();
     let mut already_installed = Vec::new();
     let mut failed = Vec::new();
+    let mut items = Vec::with_capacity(total);
 
     for (idx, var_name) in var_list.iter().enumerate() {
         match handle.block_on(install_var(
             reporter,
             pool,
+            &profile,
             &varspath,
             &vampath,
             var_name,
             args.temp,
             args.disabled,
         )) {
-            Ok(InstallOutcome::Installed) => installed.push(var_name.clone()),
-            Ok(InstallOutcome::AlreadyInstalled) => already_installed.push(var_name.clone()),
+            Ok(InstallOutcome::Installed) => {
+                installed.push(var_name.clone());
+                items.push(ItemOutcome::succeeded(var_name, "install"));
+            }
+            Ok(InstallOutcome::AlreadyInstalled) => {
+                already_installed.push(var_name.clone());
+                items.push(ItemOutcome::skipped(var_name, "install", ItemErrorCode::AlreadyDone));
+            }
             Err(err) => {
                 failed.push(var_name.clone());
                 reporter.log(format!("install failed {} ({})", var_name, err));
+                items.push(install_failure(&varspath, var_name, err));
             }
         }
 
@@ -169,6 +191,7 @@ fn install_vars_blocking(state: &AppState, reporter: &JobReporter, args: Install
             installed,
             already_installed,
             failed,
+            items,
         })
         .map_err(|err| err.to_string())?,
     );
@@ -202,6 +225,7 @@ fn uninstall_vars_blocking(state: &AppState, reporter: &JobReporter, args: Unins
     reporter.log(format!("UninstallVars db_path: {}", db_path.display()));
     let db_start = Instant::now();
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
     reporter.log(format!(
         "UninstallVars db ready in {}ms",
@@ -245,18 +269,27 @@ fn uninstall_vars_blocking(state: &AppState, reporter: &JobReporter, args: Unins
     ));
     let mut removed = Vec::new();
     let mut skipped = Vec::new();
+    let mut items = Vec::with_capacity(total);
 
     for (idx, var_name) in var_list.iter().enumerate() {
         if let Some(link_path) = installed_links.get(var_name) {
             if let Err(err) = fs::remove_file(link_path) {
                 reporter.log(format!("remove link failed {} ({})", var_name, err));
                 skipped.push(var_name.clone());
+                items.push(ItemOutcome::failed(
+                    var_name,
+                    "uninstall",
+                    ItemErrorCode::Io,
+                    err.to_string(),
+                ));
             } else {
                 removed.push(var_name.clone());
-                let _ = handle.block_on(remove_install_status(pool, var_name));
+                let _ = handle.block_on(remove_install_status(pool, &profile, var_name));
+                items.push(ItemOutcome::succeeded(var_name, "uninstall"));
             }
         } else {
             skipped.push(var_name.clone());
+            items.push(ItemOutcome::skipped(var_name, "uninstall", ItemErrorCode::NotInstalled));
         }
 
         if total > 0 && (idx % 50 == 0 || idx + 1 == total) {
@@ -266,8 +299,13 @@ fn uninstall_vars_blocking(state: &AppState, reporter: &JobReporter, args: Unins
     }
 
     reporter.set_result(
-        serde_json::to_value(UninstallVarsResult { total, removed, skipped })
-            .map_err(|err| err.to_string())?,
+        serde_json::to_value(UninstallVarsResult {
+            total,
+            removed,
+            skipped,
+            items,
+        })
+        .map_err(|err| err.to_string())?,
     );
 
     reporter.progress(100);
@@ -394,11 +432,13 @@ fn preview_uninstall_blocking(state: &AppState, reporter: &JobReporter, args: Pr
 
 fn delete_vars_blocking(state: &AppState, reporter: &JobReporter, args: DeleteVarsArgs) -> Result<(), String> {
     let (varspath, vampath) = config_paths(state)?;
+    let roots = library_roots(state)?;
     let vampath = vampath.ok_or_else(|| "vampath is required in config.json".to_string())?;
     reporter.log("DeleteVars start".to_string());
     reporter.progress(1);
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let var_list = if args.include_implicated {
@@ -411,34 +451,44 @@ fn delete_vars_blocking(state: &AppState, reporter: &JobReporter, args: DeleteVa
     let total = var_list.len();
     let mut deleted = Vec::new();
     let mut failed = Vec::new();
-
-    let deleted_dir = varspath.join(DELETED_DIR);
-    fs::create_dir_all(&deleted_dir).map_err(|err| err.to_string())?;
+    let mut items = Vec::with_capacity(total);
 
     for (idx, var_name) in var_list.iter().enumerate() {
         if let Some(link_path) = installed_links.get(var_name) {
             let _ = fs::remove_file(link_path);
         }
-        let _ = handle.block_on(remove_install_status(pool, var_name));
+        let _ = handle.block_on(remove_install_status(pool, &profile, var_name));
 
         let src = match resolve_var_file_path(&varspath, var_name) {
             Ok(path) => path,
             Err(err) => {
                 reporter.log(format!("skip {} ({})", var_name, err));
                 failed.push(var_name.clone());
+                items.push(ItemOutcome::failed(var_name, "delete", ItemErrorCode::NotFound, err));
                 continue;
             }
         };
+        // Stay on the root's volume so the rename does not turn into a copy.
+        let deleted_dir = library_root_of(&roots, &src).unwrap_or(&varspath).join(DELETED_DIR);
+        fs::create_dir_all(&deleted_dir).map_err(|err| err.to_string())?;
         let dest = deleted_dir.join(format!("{}.var", var_name));
         match fs::rename(&src, &dest) {
             Ok(_) => {
                 handle.block_on(delete_var_related_conn(pool, var_name))?;
+                forget_var_location(var_name);
                 delete_preview_pics(&varspath, var_name)?;
                 deleted.push(var_name.clone());
+                items.push(ItemOutcome::succeeded(var_name, "delete"));
             }
             Err(err) => {
                 reporter.log(format!("delete failed {} ({})", var_name, err));
                 failed.push(var_name.clone());
+                items.push(ItemOutcome::failed(
+                    var_name,
+                    "delete",
+                    ItemErrorCode::Io,
+                    err.to_string(),
+                ));
             }
         }
 
@@ -449,8 +499,13 @@ fn delete_vars_blocking(state: &AppState, reporter: &JobReporter, args: DeleteVa
     }
 
     reporter.set_result(
-        serde_json::to_value(DeleteVarsResult { total, deleted, failed })
-            .map_err(|err| err.to_string())?,
+        serde_json::to_value(DeleteVarsResult {
+            total,
+            deleted,
+            failed,
+            items,
+        })
+        .map_err(|err| err.to_string())?,
     );
 
     reporter.progress(100);
@@ -458,9 +513,11 @@ fn delete_vars_blocking(state: &AppState, reporter: &JobReporter, args: DeleteVa
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn install_var(
     reporter: &JobReporter,
     pool: &SqlitePool,
+    profile: &str,
     varspath: &Path,
     vampath: &Path,
     var_name: &str,
@@ -500,7 +557,7 @@ async fn install_var(
         let _ = fs::File::create(&disabled_path);
     }
 
-    upsert_install_status(pool, var_name, true, disabled).await?;
+    upsert_install_status(pool, profile, var_name, true, disabled).await?;
     tracing::debug!(
         var_name = %var_name,
         link_path = %link_path.display(),
@@ -519,8 +576,13 @@ fn set_link_times(link: &Path, target: &Path) -> Result<(), String> {
     winfs::set_symlink_file_times(link, created, modified)
 }
 
-async fn remove_install_status(pool: &SqlitePool, var_name: &str) -> Result<(), String> {
-    sqlx::query("DELETE FROM installStatus WHERE varName = ?1")
+async fn remove_install_status(
+    pool: &SqlitePool,
+    profile: &str,
+    var_name: &str,
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM installStatus WHERE profile = ?1 AND varName = ?2")
+        .bind(profile)
         .bind(var_name)
         .execute(pool)
         .await
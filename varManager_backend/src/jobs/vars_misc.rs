@@ -1,42 +1,51 @@
 use crate::infra::db::{upsert_install_status, var_exists_conn};
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, resolve_var_file_path, INSTALL_LINK_DIR};
+use crate::jobs::outcome::{install_failure, ItemErrorCode, ItemOutcome};
+use crate::infra::paths::{config_paths, profile_name, resolve_var_file_path, INSTALL_LINK_DIR};
 use crate::domain::var_logic::{implicated_vars, vars_dependencies};
 use crate::app::AppState;
 use crate::infra::winfs;
 use crate::util;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use std::path::{Path, PathBuf};
 use sqlx::{Row, SqlitePool};
 
-#[derive(Deserialize)]
-struct ExportInstalledArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ExportInstalledArgs {
     path: String,
 }
 
-#[derive(Serialize)]
-struct ExportInstalledResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct ExportInstalledResult {
     count: usize,
 }
 
-#[derive(Deserialize)]
-struct InstallBatchArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct InstallBatchArgs {
     path: String,
+    /// Only install these names from the list file (set when retrying).
+    #[serde(default)]
+    var_names: Option<Vec<String>>,
 }
 
-#[derive(Serialize)]
-struct InstallBatchResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct InstallBatchResult {
     total: usize,
     installed: Vec<String>,
     already_installed: Vec<String>,
     failed: Vec<String>,
+    items: Vec<ItemOutcome>,
 }
 
-#[derive(Deserialize)]
-struct ToggleInstallArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ToggleInstallArgs {
     var_name: String,
     #[serde(default = "default_true")]
     include_dependencies: bool,
@@ -44,22 +53,24 @@ struct ToggleInstallArgs {
     include_implicated: bool,
 }
 
-#[derive(Serialize)]
-struct ToggleInstallResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct ToggleInstallResult {
     action: String,
     installed: Vec<String>,
     removed: Vec<String>,
     failed: Vec<String>,
+    items: Vec<ItemOutcome>,
 }
 
-#[derive(Deserialize)]
-struct LocateArgs {
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LocateArgs {
     var_name: Option<String>,
     path: Option<String>,
 }
 
-#[derive(Serialize)]
-struct RefreshInstalledResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct RefreshInstalledResult {
     installed: usize,
 }
 
@@ -141,10 +152,12 @@ fn export_installed_blocking(
     args: ExportInstalledArgs,
 ) -> Result<(), String> {
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
     let rows = handle
         .block_on(
-            sqlx::query("SELECT varName FROM installStatus WHERE installed = 1")
+            sqlx::query("SELECT varName FROM installStatus WHERE profile = ?1 AND installed = 1")
+                .bind(&profile)
                 .fetch_all(pool),
         )
         .map_err(|err| err.to_string())?;
@@ -184,8 +197,12 @@ fn install_batch_blocking(
         .collect();
     targets.sort();
     targets.dedup();
+    if let Some(only) = &args.var_names {
+        targets.retain(|name| only.contains(name));
+    }
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let installed_links = fs_util::collect_installed_links_ci(&vampath);
@@ -193,25 +210,35 @@ fn install_batch_blocking(
     let mut installed = Vec::new();
     let mut already_installed = Vec::new();
     let mut failed = Vec::new();
+    let mut items = Vec::with_capacity(total);
 
     for (idx, var_name) in targets.iter().enumerate() {
         if installed_links.contains_key(&var_name.to_ascii_lowercase()) {
             already_installed.push(var_name.clone());
+            items.push(ItemOutcome::skipped(var_name, "install", ItemErrorCode::AlreadyDone));
             continue;
         }
         match handle.block_on(install_var(
             pool,
+            &profile,
             &varspath,
             &vampath,
             var_name,
             false,
             false,
         )) {
-            Ok(InstallOutcome::Installed) => installed.push(var_name.clone()),
-            Ok(InstallOutcome::AlreadyInstalled) => already_installed.push(var_name.clone()),
+            Ok(InstallOutcome::Installed) => {
+                installed.push(var_name.clone());
+                items.push(ItemOutcome::succeeded(var_name, "install"));
+            }
+            Ok(InstallOutcome::AlreadyInstalled) => {
+                already_installed.push(var_name.clone());
+                items.push(ItemOutcome::skipped(var_name, "install", ItemErrorCode::AlreadyDone));
+            }
             Err(err) => {
                 reporter.log(format!("install failed {} ({})", var_name, err));
                 failed.push(var_name.clone());
+                items.push(install_failure(&varspath, var_name, err));
             }
         }
         if total > 0 && (idx % 50 == 0 || idx + 1 == total) {
@@ -226,6 +253,7 @@ fn install_batch_blocking(
             installed,
             already_installed,
             failed,
+            items,
         })
         .map_err(|err| err.to_string())?,
     );
@@ -241,6 +269,7 @@ fn toggle_install_blocking(
     let vampath = vampath.ok_or_else(|| "vampath is required in config.json".to_string())?;
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
 
     let installed_links = fs_util::collect_installed_links_ci(&vampath);
@@ -256,14 +285,22 @@ fn toggle_install_blocking(
 
         let mut removed = Vec::new();
         let mut failed = Vec::new();
+        let mut items = Vec::with_capacity(var_list.len());
         for var_name in &var_list {
             if let Some(path) = installed_links.get(&var_name.to_ascii_lowercase()) {
                 if let Err(err) = fs::remove_file(path) {
                     reporter.log(format!("remove failed {} ({})", var_name, err));
                     failed.push(var_name.clone());
+                    items.push(ItemOutcome::failed(
+                        var_name,
+                        "uninstall",
+                        ItemErrorCode::Io,
+                        err.to_string(),
+                    ));
                 } else {
-                    let _ = handle.block_on(remove_install_status(pool, var_name));
+                    let _ = handle.block_on(remove_install_status(pool, &profile, var_name));
                     removed.push(var_name.clone());
+                    items.push(ItemOutcome::succeeded(var_name, "uninstall"));
                 }
             }
         }
@@ -274,6 +311,7 @@ fn toggle_install_blocking(
                 installed: Vec::new(),
                 removed,
                 failed,
+                items,
             })
             .map_err(|err| err.to_string())?,
         );
@@ -291,20 +329,28 @@ fn toggle_install_blocking(
     let total = var_list.len();
     let mut installed = Vec::new();
     let mut failed = Vec::new();
+    let mut items = Vec::with_capacity(total);
     for (idx, var_name) in var_list.iter().enumerate() {
         match handle.block_on(install_var(
             pool,
+            &profile,
             &varspath,
             &vampath,
             var_name,
             false,
             false,
         )) {
-            Ok(InstallOutcome::Installed) => installed.push(var_name.clone()),
-            Ok(InstallOutcome::AlreadyInstalled) => {}
+            Ok(InstallOutcome::Installed) => {
+                installed.push(var_name.clone());
+                items.push(ItemOutcome::succeeded(var_name, "install"));
+            }
+            Ok(InstallOutcome::AlreadyInstalled) => {
+                items.push(ItemOutcome::skipped(var_name, "install", ItemErrorCode::AlreadyDone));
+            }
             Err(err) => {
                 reporter.log(format!("install failed {} ({})", var_name, err));
                 failed.push(var_name.clone());
+                items.push(install_failure(&varspath, var_name, err));
             }
         }
         if total > 0 && (idx % 50 == 0 || idx + 1 == total) {
@@ -319,6 +365,7 @@ fn toggle_install_blocking(
             installed,
             removed: Vec::new(),
             failed,
+            items,
         })
         .map_err(|err| err.to_string())?,
     );
@@ -356,9 +403,14 @@ fn refresh_install_status_blocking(state: &AppState, reporter: &JobReporter) ->
     let vampath = vampath.ok_or_else(|| "vampath is required in config.json".to_string())?;
 
     let pool = &state.db_pool;
+    let profile = profile_name(state)?;
     let handle = tokio::runtime::Handle::current();
     handle
-        .block_on(sqlx::query("DELETE FROM installStatus").execute(pool))
+        .block_on(
+            sqlx::query("DELETE FROM installStatus WHERE profile = ?1")
+                .bind(&profile)
+                .execute(pool),
+        )
         .map_err(|err| err.to_string())?;
 
     let installed_links = fs_util::collect_installed_links(&vampath);
@@ -373,7 +425,7 @@ fn refresh_install_status_blocking(state: &AppState, reporter: &JobReporter) ->
             continue;
         }
         let disabled = link_path.with_extension("var.disabled").exists();
-        handle.block_on(upsert_install_status(pool, &var_name, true, disabled))?;
+        handle.block_on(upsert_install_status(pool, &profile, &var_name, true, disabled))?;
         installed += 1;
     }
 
@@ -386,6 +438,7 @@ fn refresh_install_status_blocking(state: &AppState, reporter: &JobReporter) ->
 
 async fn install_var(
     pool: &SqlitePool,
+    profile: &str,
     varspath: &Path,
     vampath: &Path,
     var_name: &str,
@@ -425,7 +478,7 @@ async fn install_var(
         let _ = fs::File::create(&disabled_path);
     }
 
-    upsert_install_status(pool, var_name, true, disabled).await?;
+    upsert_install_status(pool, profile, var_name, true, disabled).await?;
     tracing::debug!(
         var_name = %var_name,
         link_path = %link_path.display(),
@@ -443,8 +496,13 @@ fn set_link_times(link: &Path, target: &Path) -> Result<(), String> {
     winfs::set_symlink_file_times(link, created, modified)
 }
 
-async fn remove_install_status(pool: &SqlitePool, var_name: &str) -> Result<(), String> {
-    sqlx::query("DELETE FROM installStatus WHERE varName = ?1")
+async fn remove_install_status(
+    pool: &SqlitePool,
+    profile: &str,
+    var_name: &str,
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM installStatus WHERE profile = ?1 AND varName = ?2")
+        .bind(profile)
         .bind(var_name)
         .execute(pool)
         .await
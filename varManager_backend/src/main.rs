@@ -9,7 +9,7 @@ use std::{
         Arc, RwLock,
     },
 };
-use tokio::sync::{oneshot, Semaphore};
+use tokio::sync::{mpsc, oneshot, Semaphore};
 
 mod app;
 mod api;
@@ -24,6 +24,8 @@ use crate::app::{AppState, APP_VERSION};
 use crate::infra::db;
 use crate::infra::download_manager::DownloadManager;
 use crate::jobs::job_channel::{create_job_channel, create_job_map, JobManager};
+use crate::jobs::job_locks::JobLocks;
+use crate::jobs::job_log_store::{JobLogStore, JOB_LOG_DIR};
 use crate::services::image_cache::ImageCacheService;
 
 #[tokio::main]
@@ -36,9 +38,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_pool = db::open_default_pool()
         .await
         .map_err(std::io::Error::other)?;
+    crate::infra::paths::set_var_locations(
+        db::list_var_locations(&db_pool)
+            .await
+            .map_err(std::io::Error::other)?,
+    );
     let (shutdown_tx, shutdown_rx) = oneshot::channel();
     let (job_tx, job_rx) = create_job_channel();
     let jobs = create_job_map();
+    let job_logs = Arc::new(JobLogStore::open(app::data_dir().join(JOB_LOG_DIR)));
     let image_cache = Arc::new(
         ImageCacheService::new(config.image_cache.clone(), db_pool.clone())
             .await
@@ -46,11 +54,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     image_cache.clone().start_maintenance();
     let config_state = Arc::new(RwLock::new(config.clone()));
-    let download_manager = Arc::new(DownloadManager::new(db_pool.clone(), Arc::clone(&config_state)));
+    let (completed_tx, completed_rx) = mpsc::unbounded_channel();
+    let download_manager = Arc::new(DownloadManager::new(
+        db_pool.clone(),
+        Arc::clone(&config_state),
+        completed_tx,
+    ));
     download_manager
-        .pause_incomplete()
+        .recover_incomplete(config.download.resume_on_startup)
         .await
         .map_err(std::io::Error::other)?;
+    download_manager.spawn_scheduler();
     let state = AppState {
         config: Arc::clone(&config_state),
         shutdown_tx: Arc::new(tokio::sync::Mutex::new(Some(shutdown_tx))),
@@ -60,16 +74,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             config.job_concurrency,
         )))),
         job_tx,
+        job_locks: Arc::new(JobLocks::new()),
+        job_logs: Arc::clone(&job_logs),
         db_pool,
         image_cache,
         download_manager,
+        profile: None,
     };
 
     // Start JobManager to consume job events and update state
-    let job_manager = JobManager::new(job_rx, jobs);
+    let job_manager = JobManager::new(job_rx, jobs, job_logs);
     tokio::spawn(async move {
         job_manager.run().await;
     });
+    crate::jobs::auto_import::spawn_auto_import(state.clone(), completed_rx);
 
     if let Some(parent_pid) = app::read_parent_pid() {
         tracing::info!(parent_pid, "parent watchdog enabled");
@@ -91,12 +109,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/scenes", get(api::list_scenes))
         .route("/creators", get(api::list_creators))
         .route("/stats", get(api::get_stats))
+        .route("/stats/library", get(api::get_library_stats))
+        .route("/search", get(api::search))
+        .route("/entries", get(api::list_entries))
+        .route("/providers", post(api::find_resource_providers))
         .route("/preview", get(api::get_preview))
         .route("/cache/stats", get(api::get_cache_stats))
         .route("/cache/clear", post(api::clear_cache))
         .route("/cache/entry", axum::routing::delete(api::delete_cache_entry))
         .route("/packswitch", get(api::list_packswitch))
         .route("/hub/options", get(api::list_hub_options))
+        .route(
+            "/hub/auth",
+            get(api::get_hub_auth)
+                .put(api::update_hub_auth)
+                .delete(api::clear_hub_auth),
+        )
         .route("/dependents", get(api::list_dependents))
         .route("/analysis/atoms", get(api::list_analysis_atoms))
         .route("/analysis/summary", get(api::get_analysis_summary))
@@ -106,20 +134,52 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/missing/map/load", post(api::load_missing_map))
         .route("/missing/map/current", get(api::list_missing_links))
         .route("/jobs", post(api::start_job))
+        .route("/jobs/kinds", get(api::list_job_kinds))
         .route("/jobs/{id}", get(api::get_job))
         .route("/jobs/{id}/logs", get(api::get_job_logs))
+        .route("/jobs/{id}/logs/download", get(api::download_job_logs))
         .route("/jobs/{id}/result", get(api::get_job_result))
+        .route("/jobs/{id}/failed", get(api::get_job_failed_items))
+        .route("/jobs/{id}/retry", post(api::retry_job))
+        .route("/db/backups", get(api::list_db_backups))
+        .route("/db/backup", post(api::backup_db))
+        .route("/db/restore", post(api::restore_db))
+        .route("/db/check", post(api::check_db))
         .route("/downloads", get(api::list_downloads))
         .route("/downloads", post(api::enqueue_downloads))
         .route("/downloads/actions", post(api::download_actions))
+        .route("/downloads/events", get(api::download_events))
+        .route("/downloads/sources", get(api::list_download_sources))
+        .route("/downloads/history", get(api::list_download_history))
+        .route("/downloads/priority", post(api::set_download_priority))
+        .route("/downloads/prioritize_scene", post(api::prioritize_scene_downloads))
         .route("/shutdown", post(api::shutdown))
-        .with_state(state);
+        .with_state(state.clone());
 
     let addr: SocketAddr =
         format!("{}:{}", config.listen_host, config.listen_port).parse()?;
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     tracing::info!(%addr, version = APP_VERSION, "backend listening");
 
+    if config.lan_cache.enabled {
+        let lan_addr: SocketAddr = format!(
+            "{}:{}",
+            config.lan_cache.listen_host, config.lan_cache.listen_port
+        )
+        .parse()?;
+        let lan_listener = tokio::net::TcpListener::bind(&lan_addr).await?;
+        let lan_app = Router::new()
+            .route("/lan/vars/{file}", get(api::lan_get_var))
+            .with_state(state);
+        tracing::info!(addr = %lan_addr, "lan cache listening");
+        tokio::spawn(async move {
+            let service = lan_app.into_make_service_with_connect_info::<SocketAddr>();
+            if let Err(err) = axum::serve(lan_listener, service).await {
+                tracing::error!(error = %err, "lan cache listener stopped");
+            }
+        });
+    }
+
     axum::serve(listener, app)
         .with_graceful_shutdown(app::shutdown_signal(shutdown_rx))
         .await?;
@@ -1,12 +1,15 @@
 use crate::infra::db::var_exists_conn;
 use crate::infra::fs_util;
 use crate::jobs::job_channel::JobReporter;
-use crate::infra::paths::{config_paths, loadscene_path, resolve_var_file_path, temp_links_dir, CACHE_DIR};
+use crate::infra::paths::{
+    config_paths, loadscene_path, profile_name, resolve_var_file_path, temp_links_dir, CACHE_DIR,
+};
 use crate::domain::var_logic::{resolve_var_exist_name, vars_dependencies};
 use crate::app::{data_dir, AppState};
 use crate::infra::winfs;
 use crate::util;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
@@ -122,7 +125,8 @@ const DEFAULT_EYE_COLOR: &str = "{ \"setUnlistedParamsToDefault\": \"false\", \"
 const CLOTH_NAKED: &str = "{ \"setUnlistedParamsToDefault\" : \"true\", \"storables\" : [ { \"id\" : \"geometry\", \"clothing\" : [ ] } ] }";
 const HAIR_BALD: &str = "{ \"setUnlistedParamsToDefault\" : \"true\", \"storables\" : [ { \"id\" : \"geometry\", \"hair\" : [ ] } ] }";
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct SceneLoadArgs {
     json: Value,
     #[serde(default)]
@@ -135,29 +139,31 @@ pub(crate) struct SceneLoadArgs {
     person_order: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct SceneAnalyzeArgs {
     save_name: String,
     #[serde(default)]
     character_gender: Option<String>,
 }
 
-#[derive(Serialize)]
-struct SceneAnalyzeResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct SceneAnalyzeResult {
     var_name: String,
     entry_name: String,
     cache_dir: String,
     character_gender: String,
 }
 
-#[derive(Serialize)]
-struct SceneLoadResult {
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct SceneLoadResult {
     rescan: bool,
     temp_installed: Vec<String>,
     loadscene_path: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct ScenePresetLookArgs {
     var_name: String,
     entry_name: String,
@@ -180,7 +186,8 @@ pub(crate) struct ScenePresetLookArgs {
     person_order: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct ScenePresetArgs {
     var_name: String,
     entry_name: String,
@@ -191,7 +198,8 @@ pub(crate) struct ScenePresetArgs {
     person_order: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct ScenePresetSceneArgs {
     var_name: String,
     entry_name: String,
@@ -202,7 +210,8 @@ pub(crate) struct ScenePresetSceneArgs {
     person_order: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct SceneAtomsArgs {
     var_name: String,
     entry_name: String,
@@ -215,7 +224,8 @@ pub(crate) struct SceneAtomsArgs {
     pub(crate) as_subscene: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct SceneHideFavArgs {
     pub(crate) var_name: Option<String>,
     pub(crate) scene_path: String,
@@ -226,7 +236,8 @@ pub(crate) struct HideFavState {
     pub(crate) fav: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct CacheClearArgs {
     var_name: String,
     entry_name: String,
@@ -1442,8 +1453,10 @@ pub(crate) async fn sync_hide_fav_db(
     if var_name.eq_ignore_ascii_case("save") || var_name.eq_ignore_ascii_case("(save).") {
         return Ok(());
     }
+    let profile = profile_name(state)?;
     if !status.hide && !status.fav {
-        sqlx::query("DELETE FROM HideFav WHERE varName = ?1 AND scenePath = ?2")
+        sqlx::query("DELETE FROM HideFav WHERE profile = ?1 AND varName = ?2 AND scenePath = ?3")
+            .bind(&profile)
             .bind(var_name)
             .bind(scene_path)
             .execute(&state.db_pool)
@@ -1452,9 +1465,11 @@ pub(crate) async fn sync_hide_fav_db(
         return Ok(());
     }
     sqlx::query(
-        "INSERT INTO HideFav (varName, scenePath, hide, fav) VALUES (?1, ?2, ?3, ?4)\
-         ON CONFLICT(varName, scenePath) DO UPDATE SET hide = excluded.hide, fav = excluded.fav",
+        "INSERT INTO HideFav (profile, varName, scenePath, hide, fav) VALUES (?1, ?2, ?3, ?4, ?5)\
+         ON CONFLICT(profile, varName, scenePath) \
+         DO UPDATE SET hide = excluded.hide, fav = excluded.fav",
     )
+    .bind(&profile)
     .bind(var_name)
     .bind(scene_path)
     .bind(if status.hide { 1 } else { 0 })
@@ -7,6 +7,18 @@ pub use core::{
     analysis_summary,
     list_analysis_atoms,
 };
+pub(crate) use core::{
+    CacheClearArgs,
+    SceneAnalyzeArgs,
+    SceneAnalyzeResult,
+    SceneAtomsArgs,
+    SceneHideFavArgs,
+    SceneLoadArgs,
+    SceneLoadResult,
+    ScenePresetArgs,
+    ScenePresetLookArgs,
+    ScenePresetSceneArgs,
+};
 pub use jobs::{
     run_cache_clear_job,
     run_scene_add_atoms_job,
@@ -11,7 +11,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
-use tokio::sync::{Notify, Semaphore};
+use tokio::sync::{Notify, OwnedRwLockWriteGuard, RwLock, Semaphore};
 
 const MAX_DOWNLOAD_BYTES: u64 = 128 * 1024 * 1024;
 
@@ -129,6 +129,8 @@ pub struct ImageCacheService {
     metrics: Arc<CacheMetrics>,
     config: ImageCacheConfig,
     memory_max_bytes: u64,
+    /// Held shared by every disk-cache access and exclusively by `pause`.
+    disk_gate: Arc<RwLock<()>>,
 }
 
 impl ImageCacheService {
@@ -174,18 +176,35 @@ impl ImageCacheService {
             metrics,
             config,
             memory_max_bytes,
+            disk_gate: Arc::new(RwLock::new(())),
         })
     }
 
+    /// Keep the disk cache, and with it `image_cache_entries`, untouched
+    /// until the returned guard is dropped. Images are still served from
+    /// memory or fetched meanwhile, just not stored on disk.
+    pub async fn pause(&self) -> OwnedRwLockWriteGuard<()> {
+        Arc::clone(&self.disk_gate).write_owned().await
+    }
+
+    /// Drop index rows whose files are gone, after the database was swapped
+    /// for one whose index may not match the cache folder.
+    pub async fn reconcile(&self) -> Result<(), ImageCacheError> {
+        let _gate = self.disk_gate.read().await;
+        self.disk_cache.cleanup_orphaned().await
+    }
+
     pub fn start_maintenance(self: Arc<Self>) {
         if !self.config.enabled {
             return;
         }
         let disk_cache = Arc::clone(&self.disk_cache);
+        let disk_gate = Arc::clone(&self.disk_gate);
         tokio::spawn(async move {
             let mut ticker = tokio::time::interval(Duration::from_secs(3600));
             loop {
                 ticker.tick().await;
+                let _gate = disk_gate.read().await;
                 if let Err(err) = disk_cache.cleanup_expired().await {
                     tracing::warn!(error = %err, "image cache cleanup failed");
                 }
@@ -205,7 +224,7 @@ impl ImageCacheService {
             }
             self.metrics.l1_misses.fetch_add(1, Ordering::Relaxed);
 
-            match self.disk_cache.get(&key).await {
+            match self.disk_get(&key).await {
                 Ok(Some((bytes, content_type))) => {
                     self.metrics.l2_hits.fetch_add(1, Ordering::Relaxed);
                     self.insert_memory(&key, bytes.clone(), content_type.clone())
@@ -241,7 +260,7 @@ impl ImageCacheService {
                         self.metrics.l1_hits.fetch_add(1, Ordering::Relaxed);
                         return Ok((cached.bytes, cached.content_type));
                     }
-                    if let Ok(Some((bytes, content_type))) = self.disk_cache.get(&key).await {
+                    if let Ok(Some((bytes, content_type))) = self.disk_get(&key).await {
                         self.metrics.l2_hits.fetch_add(1, Ordering::Relaxed);
                         self.insert_memory(&key, bytes.clone(), content_type.clone())
                             .await;
@@ -257,17 +276,20 @@ impl ImageCacheService {
                 if self.config.enabled {
                     self.insert_memory(&key, bytes.clone(), content_type.clone())
                         .await;
-                    if let Err(err) = self
-                        .disk_cache
-                        .put(
-                            key.clone(),
-                            bytes.clone(),
-                            content_type.clone(),
-                            source.source.clone(),
-                        )
-                        .await
-                    {
-                        tracing::warn!(error = %err, "failed to write image cache entry");
+                    // Not stored on disk while the cache is paused.
+                    if let Ok(_gate) = self.disk_gate.try_read() {
+                        if let Err(err) = self
+                            .disk_cache
+                            .put(
+                                key.clone(),
+                                bytes.clone(),
+                                content_type.clone(),
+                                source.source.clone(),
+                            )
+                            .await
+                        {
+                            tracing::warn!(error = %err, "failed to write image cache entry");
+                        }
                     }
                 }
             } else {
@@ -283,7 +305,10 @@ impl ImageCacheService {
     pub async fn stats(&self) -> Result<CacheStats, ImageCacheError> {
         let memory_entries = self.memory_cache.entry_count();
         let memory_size = self.memory_cache.weighted_size();
-        let disk_stats = self.disk_cache.stats().await?;
+        let disk_stats = {
+            let _gate = self.disk_gate.read().await;
+            self.disk_cache.stats().await?
+        };
         Ok(CacheStats {
             memory: MemoryCacheStats {
                 entries: memory_entries,
@@ -308,14 +333,24 @@ impl ImageCacheService {
 
     pub async fn clear(&self) -> Result<(), ImageCacheError> {
         self.memory_cache.invalidate_all();
+        let _gate = self.disk_gate.read().await;
         self.disk_cache.clear().await
     }
 
     pub async fn delete_entry(&self, key: &str) -> Result<bool, ImageCacheError> {
         self.memory_cache.invalidate(key).await;
+        let _gate = self.disk_gate.read().await;
         self.disk_cache.remove(key).await
     }
 
+    /// Disk-cache lookup; a miss while the cache is paused.
+    async fn disk_get(&self, key: &str) -> Result<Option<(Bytes, String)>, ImageCacheError> {
+        let Ok(_gate) = self.disk_gate.try_read() else {
+            return Ok(None);
+        };
+        self.disk_cache.get(key).await
+    }
+
     async fn insert_memory(&self, key: &str, bytes: Bytes, content_type: String) {
         let cached = CachedImage { bytes, content_type };
         self.memory_cache.insert(key.to_string(), cached).await;
@@ -0,0 +1,156 @@
+//! Read-only package cache for other varManager instances on the network:
+//! library `.var` files are served by exact name, with a SHA-256 checksum and
+//! byte-range support, to clients on the allow-list. Another instance uses it
+//! as an `http` package source pointing at `/lan/vars/`. The route runs on
+//! its own listener (see [`LanCacheConfig`]), never beside the control API.
+
+use crate::app::LanCacheConfig;
+use crate::infra::fs_util::sha256_file;
+use dashmap::DashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+/// Response header carrying the hex SHA-256 of the whole file.
+pub const CHECKSUM_HEADER: &str = "x-checksum-sha256";
+
+/// Loopback clients are always allowed; everyone else must match an entry.
+pub fn client_allowed(cfg: &LanCacheConfig, addr: IpAddr) -> bool {
+    let addr = addr.to_canonical();
+    if addr.is_loopback() {
+        return true;
+    }
+    cfg.allowed_clients.iter().any(|entry| {
+        parse_allow_entry(entry)
+            .map(|(net, prefix)| in_network(addr, net, prefix))
+            .unwrap_or(false)
+    })
+}
+
+/// An allow-list entry: a single address or a `address/prefix` network.
+pub fn parse_allow_entry(raw: &str) -> Result<(IpAddr, u8), String> {
+    let raw = raw.trim();
+    let invalid = || format!("invalid client address '{}'", raw);
+    let (addr, prefix) = match raw.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (raw, None),
+    };
+    let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+        None => max,
+    };
+    if prefix > max {
+        return Err(invalid());
+    }
+    Ok((addr.to_canonical(), prefix))
+}
+
+fn in_network(addr: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (addr, net) {
+        (IpAddr::V4(addr), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(addr) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(addr) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Inclusive byte range requested by a `Range` header. `Ok(None)` serves the
+/// whole file (no header, or a form we do not handle such as multiple
+/// ranges); `Err` means the range cannot be satisfied.
+pub fn parse_range(raw: Option<&str>, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = raw.and_then(|raw| raw.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (start, end) = spec.split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().map_err(|_| ())?;
+            if suffix == 0 {
+                return Err(());
+            }
+            (len.saturating_sub(suffix), len.checked_sub(1).ok_or(())?)
+        }
+        (start, "") => (start.parse().map_err(|_| ())?, len.checked_sub(1).ok_or(())?),
+        (start, end) => {
+            let end: u64 = end.parse().map_err(|_| ())?;
+            (start.parse().map_err(|_| ())?, end.min(len.saturating_sub(1)))
+        }
+    };
+    if start >= len || start > end {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+struct CachedChecksum {
+    len: u64,
+    modified: Option<SystemTime>,
+    sha256: String,
+}
+
+fn checksum_cache() -> &'static DashMap<PathBuf, CachedChecksum> {
+    static CACHE: OnceLock<DashMap<PathBuf, CachedChecksum>> = OnceLock::new();
+    CACHE.get_or_init(DashMap::new)
+}
+
+/// SHA-256 of `path`, reused until the file's size or mtime changes.
+pub fn file_checksum(path: &Path) -> Result<String, String> {
+    let meta = std::fs::metadata(path).map_err(|err| err.to_string())?;
+    let modified = meta.modified().ok();
+    if let Some(cached) = checksum_cache().get(path) {
+        if cached.len == meta.len() && cached.modified == modified {
+            return Ok(cached.sha256.clone());
+        }
+    }
+    let sha256 = sha256_file(path)?;
+    checksum_cache().insert(
+        path.to_path_buf(),
+        CachedChecksum {
+            len: meta.len(),
+            modified,
+            sha256: sha256.clone(),
+        },
+    );
+    Ok(sha256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_and_allow_list() {
+        assert_eq!(parse_range(None, 100), Ok(None));
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), Ok(Some((0, 9))));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), Ok(Some((50, 99))));
+        assert_eq!(parse_range(Some("bytes=100-"), 100), Err(()));
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), Ok(None));
+
+        let cfg = LanCacheConfig {
+            enabled: true,
+            allowed_clients: vec!["192.168.1.0/24".to_string(), "10.0.0.7".to_string()],
+            ..LanCacheConfig::default()
+        };
+        let allowed = |ip: &str| client_allowed(&cfg, ip.parse().unwrap());
+        assert!(allowed("192.168.1.42"));
+        assert!(allowed("::ffff:192.168.1.42"));
+        assert!(allowed("10.0.0.7"));
+        assert!(allowed("127.0.0.1"));
+        assert!(!allowed("10.0.0.8"));
+        assert!(!allowed("192.168.2.1"));
+        assert!(parse_allow_entry("10.0.0.0/33").is_err());
+    }
+}
@@ -1 +1,2 @@
 pub mod image_cache;
+pub mod lan_cache;